@@ -26,12 +26,14 @@ export interface Identity {
   urn: string;
   peerId: string;
   metadata: Metadata;
+  petname?: string;
 }
 
 export const identitySchema: zod.Schema<Identity> = zod.object({
   urn: zod.string(),
   peerId: zod.string(),
   metadata: metadataSchema,
+  petname: zod.string().optional(),
 });
 
 // A claim over an Ethereum Address
@@ -44,12 +46,144 @@ export interface RemoteIdentity {
   urn: string;
   peerIds: string[];
   metadata: Metadata;
+  petname?: string;
 }
 
 export const remoteIdentitySchema = zod.object({
   urn: zod.string(),
   peerIds: zod.array(zod.string()),
   metadata: metadataSchema,
+  petname: zod.string().optional(),
+});
+
+export type PayloadChange =
+  | { type: "added"; path: string[]; value: unknown }
+  | { type: "removed"; path: string[]; value: unknown }
+  | { type: "changed"; path: string[]; old: unknown; new: unknown };
+
+export type Delegation =
+  | { type: "key"; peerId: string }
+  | { type: "person"; urn: string; peerIds: string[] };
+
+export interface Revision {
+  commit: string;
+  revision: string;
+  parents: string[];
+  timestamp: string;
+  payloadDiff: PayloadChange[];
+  delegations: Delegation[];
+  signatures: Array<{ peerId: string; valid: boolean }>;
+  verification: {
+    signatures: boolean;
+    quorum: boolean;
+    parentQuorum: boolean | null;
+  };
+}
+
+export const revisionSchema: zod.Schema<Revision> = zod.object({
+  commit: zod.string(),
+  revision: zod.string(),
+  parents: zod.array(zod.string()),
+  timestamp: zod.string(),
+  payloadDiff: zod.array(
+    zod.union([
+      zod.object({
+        type: zod.literal("added"),
+        path: zod.array(zod.string()),
+        value: zod.unknown(),
+      }),
+      zod.object({
+        type: zod.literal("removed"),
+        path: zod.array(zod.string()),
+        value: zod.unknown(),
+      }),
+      zod.object({
+        type: zod.literal("changed"),
+        path: zod.array(zod.string()),
+        old: zod.unknown(),
+        new: zod.unknown(),
+      }),
+    ])
+  ),
+  delegations: zod.array(
+    zod.union([
+      zod.object({ type: zod.literal("key"), peerId: zod.string() }),
+      zod.object({
+        type: zod.literal("person"),
+        urn: zod.string(),
+        peerIds: zod.array(zod.string()),
+      }),
+    ])
+  ),
+  signatures: zod.array(
+    zod.object({ peerId: zod.string(), valid: zod.boolean() })
+  ),
+  verification: zod.object({
+    signatures: zod.boolean(),
+    quorum: zod.boolean(),
+    parentQuorum: zod.boolean().nullable(),
+  }),
+});
+
+export interface DirectoryEntry extends RemoteIdentity {
+  projects: string[];
+}
+
+export interface DirectoryPage {
+  persons: DirectoryEntry[];
+  total: number;
+  next: number | null;
+}
+
+const directoryPageSchema: zod.Schema<DirectoryPage> = zod.object({
+  persons: zod.array(
+    remoteIdentitySchema.extend({ projects: zod.array(zod.string()) })
+  ),
+  total: zod.number(),
+  next: zod.number().nullable(),
+});
+
+export interface DirectoryQuery {
+  // Prefix of the handle, ignoring case.
+  handle?: string;
+  offset?: number;
+  limit?: number;
+}
+
+export interface LinkingRequest {
+  peerId: string;
+  addrs: string[];
+}
+
+const linkingRequestSchema: zod.Schema<LinkingRequest> = zod.object({
+  peerId: zod.string(),
+  addrs: zod.array(zod.string()),
+});
+
+export interface Link {
+  urn: string;
+  peerId: string;
+  addrs: string[];
+}
+
+const linkSchema: zod.Schema<Link> = zod.object({
+  urn: zod.string(),
+  peerId: zod.string(),
+  addrs: zod.array(zod.string()),
+});
+
+export type DeviceStatus = "linked" | "linking" | "revoking";
+
+export interface Device {
+  peerId: string;
+  local: boolean;
+  status: DeviceStatus;
+}
+
+const deviceSchema: zod.Schema<Device> = zod.object({
+  peerId: zod.string(),
+  local: zod.boolean(),
+  status: zod.enum(["linked", "linking", "revoking"]),
 });
 
 export class Client {
@@ -74,6 +208,138 @@ export class Client {
     );
   }
 
+  // Adopt the person identity a linked device added this device to. Only
+  // works while there is no session yet.
+  public async adoptDevice(
+    link: Link,
+    options?: RequestOptions
+  ): Promise<Identity> {
+    return this.fetcher.fetchOk(
+      {
+        method: "PUT",
+        path: "identities/devices/adopt",
+        body: link,
+        options,
+      },
+      identitySchema
+    );
+  }
+
+  public async devices(options?: RequestOptions): Promise<Device[]> {
+    return this.fetcher.fetchOk(
+      {
+        method: "GET",
+        path: "identities/devices",
+        options,
+      },
+      zod.array(deviceSchema)
+    );
+  }
+
+  public async directory(
+    query: DirectoryQuery = {},
+    options?: RequestOptions
+  ): Promise<DirectoryPage> {
+    return this.fetcher.fetchOk(
+      {
+        method: "GET",
+        path: "identities",
+        query,
+        options,
+      },
+      directoryPageSchema
+    );
+  }
+
+  // Revisions of the identity document of a person, oldest first.
+  public async history(
+    urn: string,
+    options?: RequestOptions
+  ): Promise<Revision[]> {
+    return this.fetcher.fetchOk(
+      {
+        method: "GET",
+        path: `identities/${urn}/history`,
+        options,
+      },
+      zod.array(revisionSchema)
+    );
+  }
+
+  // Add the device of a linking request to the local person identity.
+  public async linkDevice(
+    request: LinkingRequest,
+    options?: RequestOptions
+  ): Promise<Link> {
+    return this.fetcher.fetchOk(
+      {
+        method: "POST",
+        path: "identities/devices",
+        body: request,
+        options,
+      },
+      linkSchema
+    );
+  }
+
+  // The request a new device hands to a linked device to be linked.
+  public async linkingRequest(
+    options?: RequestOptions
+  ): Promise<LinkingRequest> {
+    return this.fetcher.fetchOk(
+      {
+        method: "GET",
+        path: "identities/devices/linking-request",
+        options,
+      },
+      linkingRequestSchema
+    );
+  }
+
+  // Ask the network for a person identity and replicate it.
+  public async request(
+    urn: string,
+    options?: RequestOptions
+  ): Promise<RemoteIdentity> {
+    return this.fetcher.fetchOk(
+      {
+        method: "PUT",
+        path: `identities/requests/${urn}`,
+        options,
+      },
+      remoteIdentitySchema
+    );
+  }
+
+  public async revokeDevice(
+    peerId: string,
+    options?: RequestOptions
+  ): Promise<Device[]> {
+    return this.fetcher.fetchOk(
+      {
+        method: "DELETE",
+        path: `identities/devices/${peerId}`,
+        options,
+      },
+      zod.array(deviceSchema)
+    );
+  }
+
+  // Sign the revision of the identity document published by another device.
+  public async signDevice(
+    peerId: string,
+    options?: RequestOptions
+  ): Promise<Device[]> {
+    return this.fetcher.fetchOk(
+      {
+        method: "PUT",
+        path: `identities/devices/${peerId}/sign`,
+        options,
+      },
+      zod.array(deviceSchema)
+    );
+  }
+
   public async update(
     params: Metadata,
     options?: RequestOptions
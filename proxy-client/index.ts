@@ -8,6 +8,7 @@ import * as zod from "zod";
 
 import * as identity from "./identity";
 import * as control from "./control";
+import * as petname from "./petname";
 import * as project from "./project";
 import * as source from "./source";
 import { Fetcher, ResponseError, RequestOptions } from "./fetcher";
@@ -50,6 +51,7 @@ export class ProxyClient {
   private fetcher: Fetcher;
 
   public control: control.Control;
+  public petname: petname.Client;
   public project: project.Client;
   public source: source.Client;
   public identity: identity.Client;
@@ -57,6 +59,7 @@ export class ProxyClient {
   public constructor(baseUrl: string) {
     this.fetcher = new Fetcher(baseUrl);
     this.control = new control.Control(this.fetcher);
+    this.petname = new petname.Client(this.fetcher);
     this.project = new project.Client(this.fetcher);
     this.source = new source.Client(this.fetcher);
     this.identity = new identity.Client(this.fetcher);
@@ -144,4 +147,34 @@ export class ProxyClient {
       options,
     });
   }
+
+  public async blocklistGet(options?: RequestOptions): Promise<string[]> {
+    return this.fetcher.fetchOk(
+      {
+        method: "GET",
+        path: "session/blocklist",
+        options,
+      },
+      zod.array(zod.string())
+    );
+  }
+
+  public async block(peerId: string, options?: RequestOptions): Promise<void> {
+    return this.fetcher.fetchOkNoContent({
+      method: "PUT",
+      path: `session/blocklist/${peerId}`,
+      options,
+    });
+  }
+
+  public async unblock(
+    peerId: string,
+    options?: RequestOptions
+  ): Promise<void> {
+    return this.fetcher.fetchOkNoContent({
+      method: "DELETE",
+      path: `session/blocklist/${peerId}`,
+      options,
+    });
+  }
 }
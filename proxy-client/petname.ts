@@ -0,0 +1,91 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+import * as zod from "zod";
+import type { Fetcher, RequestOptions } from "./fetcher";
+
+export interface Petname {
+  petname: string;
+  note: string | null;
+}
+
+// A petname given to a peer (peer ID) or a person (identity URN).
+export interface Entry extends Petname {
+  subject: string;
+}
+
+const petnameSchema = {
+  petname: zod.string(),
+  note: zod.string().nullable(),
+};
+
+const entrySchema: zod.Schema<Entry> = zod.object({
+  subject: zod.string(),
+  ...petnameSchema,
+});
+
+export class Client {
+  private fetcher: Fetcher;
+
+  public constructor(fetcher: Fetcher) {
+    this.fetcher = fetcher;
+  }
+
+  public async list(options?: RequestOptions): Promise<Entry[]> {
+    return this.fetcher.fetchOk(
+      {
+        method: "GET",
+        path: "petnames",
+        options,
+      },
+      zod.array(entrySchema)
+    );
+  }
+
+  // Resolves an identifier of the form `<handle>@<peerId>` or a petname to
+  // an identifier.
+  public async resolve(
+    input: string,
+    options?: RequestOptions
+  ): Promise<string> {
+    return this.fetcher.fetchOk(
+      {
+        method: "GET",
+        path: "petnames/resolve",
+        query: { input },
+        options,
+      },
+      zod.string()
+    );
+  }
+
+  public async set(
+    subject: string,
+    petname: Petname,
+    options?: RequestOptions
+  ): Promise<Entry> {
+    return this.fetcher.fetchOk(
+      {
+        method: "PUT",
+        path: `petnames/${subject}`,
+        body: petname,
+        options,
+      },
+      entrySchema
+    );
+  }
+
+  public async remove(
+    subject: string,
+    options?: RequestOptions
+  ): Promise<void> {
+    return this.fetcher.fetchOkNoContent({
+      method: "DELETE",
+      path: `petnames/${subject}`,
+      options,
+    });
+  }
+}
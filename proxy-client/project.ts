@@ -6,7 +6,13 @@
 
 import * as zod from "zod";
 import type { Fetcher, RequestOptions } from "./fetcher";
-import { Identity, identitySchema } from "./identity";
+import {
+  Identity,
+  identitySchema,
+  Revision,
+  revisionSchema,
+} from "./identity";
+import type { RevisionSelector } from "./source";
 
 export interface Metadata {
   name: string;
@@ -26,12 +32,187 @@ export interface CreateParams {
   repo: NewRepo | ExistingRepo;
   description?: string;
   defaultBranch: string;
+  template?: Template;
 }
 
+export interface Readme {
+  path: string;
+  html: string;
+}
+
+const readmeSchema: zod.Schema<Readme> = zod.object({
+  path: zod.string(),
+  html: zod.string(),
+});
+
+export interface Annotation {
+  favourite: boolean;
+  labels: string[];
+  note: string | null;
+}
+
+const annotationSchema: zod.Schema<Annotation> = zod.object({
+  favourite: zod.boolean(),
+  labels: zod.array(zod.string()),
+  note: zod.string().nullable(),
+});
+
+export interface AnnotationFilter {
+  favourite?: boolean;
+  label?: string;
+}
+
+export type ActivityKind =
+  | { type: "updated"; provider: string }
+  | { type: "cloned"; peer: string }
+  | { type: "peerAdded"; peer: string }
+  | { type: "patchCreated"; peer: string; id: string };
+
+export type ActivityEvent = ActivityKind & {
+  id: number;
+  timestamp: string;
+};
+
+export interface ActivityPage {
+  events: ActivityEvent[];
+  next: number | null;
+}
+
+const activityEventSchema: zod.Schema<ActivityEvent> = zod.intersection(
+  zod.object({ id: zod.number(), timestamp: zod.string() }),
+  zod.union([
+    zod.object({ type: zod.literal("updated"), provider: zod.string() }),
+    zod.object({ type: zod.literal("cloned"), peer: zod.string() }),
+    zod.object({ type: zod.literal("peerAdded"), peer: zod.string() }),
+    zod.object({
+      type: zod.literal("patchCreated"),
+      peer: zod.string(),
+      id: zod.string(),
+    }),
+  ])
+);
+
+const activityPageSchema: zod.Schema<ActivityPage> = zod.object({
+  events: zod.array(activityEventSchema),
+  next: zod.number().nullable(),
+});
+
+export interface ProjectRequestOptions {
+  // Seconds after which the request is cancelled.
+  timeout?: number;
+  maxQueries?: number;
+  // Peers asked for the project directly before anybody else.
+  preferredPeers?: string[];
+}
+
+export type RequestHistoryKind =
+  | { type: "created" }
+  | { type: "queried"; query: number }
+  | { type: "found"; peer: string }
+  | { type: "askedPreferredPeer"; peer: string }
+  | { type: "cloning"; peer: string }
+  | { type: "cloningFailed"; peer: string; reason: string }
+  | { type: "cloned"; peer: string }
+  | { type: "canceled" }
+  | { type: "timedOut" }
+  | { type: "limitReached"; limit: "timeout" | "maxQueries" };
+
+export type RequestHistoryEntry = RequestHistoryKind & { timestamp: string };
+
+export interface RequestHistory {
+  options: {
+    timeout: number | null;
+    maxQueries: number | null;
+    preferredPeers: string[];
+  };
+  createdAt: string;
+  queries: number;
+  entries: RequestHistoryEntry[];
+}
+
+const requestHistoryEntrySchema: zod.Schema<RequestHistoryEntry> =
+  zod.intersection(
+    zod.object({ timestamp: zod.string() }),
+    zod.union([
+      zod.object({ type: zod.literal("created") }),
+      zod.object({ type: zod.literal("queried"), query: zod.number() }),
+      zod.object({ type: zod.literal("found"), peer: zod.string() }),
+      zod.object({
+        type: zod.literal("askedPreferredPeer"),
+        peer: zod.string(),
+      }),
+      zod.object({ type: zod.literal("cloning"), peer: zod.string() }),
+      zod.object({
+        type: zod.literal("cloningFailed"),
+        peer: zod.string(),
+        reason: zod.string(),
+      }),
+      zod.object({ type: zod.literal("cloned"), peer: zod.string() }),
+      zod.object({ type: zod.literal("canceled") }),
+      zod.object({ type: zod.literal("timedOut") }),
+      zod.object({
+        type: zod.literal("limitReached"),
+        limit: zod.enum(["timeout", "maxQueries"]),
+      }),
+    ])
+  );
+
+const requestHistorySchema: zod.Schema<RequestHistory> = zod.object({
+  options: zod.object({
+    timeout: zod.number().nullable(),
+    maxQueries: zod.number().nullable(),
+    preferredPeers: zod.array(zod.string()),
+  }),
+  createdAt: zod.string(),
+  queries: zod.number(),
+  entries: zod.array(requestHistoryEntrySchema),
+});
+
+export type TrackingPolicy = "manual" | "delegates" | "everyone";
+
+const trackingPolicySchema: zod.Schema<TrackingPolicy> = zod.enum([
+  "manual",
+  "delegates",
+  "everyone",
+]);
+
+export interface TrackingChanges {
+  track?: string[];
+  untrack?: string[];
+}
+
+export type Gitignore = "go" | "node" | "python" | "rust";
+
+export interface Template {
+  license?: string;
+  readme?: boolean;
+  gitignore?: Gitignore;
+  directory?: string;
+}
+
+export interface Templates {
+  licenses: string[];
+  gitignores: Gitignore[];
+  directories: string[];
+}
+
+const templatesSchema: zod.Schema<Templates> = zod.object({
+  licenses: zod.array(zod.string()),
+  gitignores: zod.array(zod.enum(["go", "node", "python", "rust"])),
+  directories: zod.array(zod.string()),
+});
+
 export interface Project {
   urn: string;
   metadata: Metadata;
   stats: Stats;
+  license: string | null;
+  languages: Language[];
+}
+
+export interface Language {
+  name: string;
+  bytes: number;
 }
 
 const projectSchema: zod.Schema<Project> = zod.object({
@@ -42,6 +223,13 @@ const projectSchema: zod.Schema<Project> = zod.object({
     commits: zod.number(),
     contributors: zod.number(),
   }),
+  license: zod.string().nullable(),
+  languages: zod.array(
+    zod.object({
+      name: zod.string(),
+      bytes: zod.number(),
+    })
+  ),
 });
 
 export interface FailedProject {
@@ -54,6 +242,18 @@ const failedProjectSchema: zod.Schema<FailedProject> = zod.object({
   metadata: metadataSchema,
 });
 
+export interface UserProjects {
+  tracked: Project[];
+  contributed: Project[];
+  failures: FailedProject[];
+}
+
+const userProjectsSchema: zod.Schema<UserProjects> = zod.object({
+  tracked: zod.array(projectSchema),
+  contributed: zod.array(projectSchema),
+  failures: zod.array(failedProjectSchema),
+});
+
 export interface Stats {
   branches: number;
   commits: number;
@@ -74,6 +274,9 @@ interface ExistingRepo {
 export interface CheckoutParams {
   peerId?: string;
   path: string;
+  branch?: string;
+  remotes?: string[];
+  depth?: number;
 }
 
 export enum RequestStatus {
@@ -110,6 +313,8 @@ export interface Peer {
   type: PeerType;
   peerId: string;
   status: PeerReplicationStatus;
+  sync?: PeerSyncStatus;
+  petname?: string;
 }
 
 export enum PeerType {
@@ -140,6 +345,46 @@ export interface PeerReplicated {
 
 export type PeerReplicationStatus = PeerNotReplicated | PeerReplicated;
 
+export type BranchComparison =
+  | { type: "even" }
+  | { type: "ahead"; commits: number }
+  | { type: "behind"; commits: number }
+  | { type: "diverged"; ahead: number; behind: number };
+
+export type SignedRefsStatus =
+  | { type: "verified" }
+  | { type: "missing" }
+  | { type: "invalid"; message: string };
+
+export interface PeerSyncStatus {
+  lastFetch: string | null;
+  head: string | null;
+  defaultBranch: BranchComparison | null;
+  signedRefs: SignedRefsStatus;
+}
+
+const peerSyncStatusSchema: zod.Schema<PeerSyncStatus> = zod.object({
+  lastFetch: zod.string().nullable(),
+  head: zod.string().nullable(),
+  defaultBranch: zod
+    .union([
+      zod.object({ type: zod.literal("even") }),
+      zod.object({ type: zod.literal("ahead"), commits: zod.number() }),
+      zod.object({ type: zod.literal("behind"), commits: zod.number() }),
+      zod.object({
+        type: zod.literal("diverged"),
+        ahead: zod.number(),
+        behind: zod.number(),
+      }),
+    ])
+    .nullable(),
+  signedRefs: zod.union([
+    zod.object({ type: zod.literal("verified") }),
+    zod.object({ type: zod.literal("missing") }),
+    zod.object({ type: zod.literal("invalid"), message: zod.string() }),
+  ]),
+});
+
 const peerSchema: zod.Schema<Peer> = zod.object({
   type: zod.enum([PeerType.Local, PeerType.Remote]),
   peerId: zod.string(),
@@ -157,6 +402,8 @@ const peerSchema: zod.Schema<Peer> = zod.object({
       user: identitySchema,
     }),
   ]),
+  sync: peerSyncStatusSchema.optional(),
+  petname: zod.string().optional(),
 });
 
 export interface Patch {
@@ -193,6 +440,16 @@ export class Client {
     );
   }
 
+  public async templates(): Promise<Templates> {
+    return this.fetcher.fetchOk(
+      {
+        method: "GET",
+        path: "projects/templates",
+      },
+      templatesSchema
+    );
+  }
+
   public async get(urn: string): Promise<Project> {
     return this.fetcher.fetchOk(
       {
@@ -203,6 +460,20 @@ export class Client {
     );
   }
 
+  public async readme(
+    urn: string,
+    revision?: RevisionSelector
+  ): Promise<Readme> {
+    return this.fetcher.fetchOk(
+      {
+        method: "GET",
+        path: `projects/${urn}/readme`,
+        query: revision ? { revision } : undefined,
+      },
+      readmeSchema
+    );
+  }
+
   public async listFailed(): Promise<FailedProject[]> {
     return this.fetcher.fetchOk(
       {
@@ -213,35 +484,48 @@ export class Client {
     );
   }
 
-  public async listTracked(): Promise<Project[]> {
+  public async listTracked(filter?: AnnotationFilter): Promise<Project[]> {
     return this.fetcher.fetchOk(
       {
         method: "GET",
         path: "projects/tracked",
+        query: filter,
       },
       zod.array(projectSchema)
     );
   }
 
-  public async listContributed(): Promise<Project[]> {
+  public async listContributed(filter?: AnnotationFilter): Promise<Project[]> {
     return this.fetcher.fetchOk(
       {
         method: "GET",
         path: "projects/contributed",
+        query: filter,
       },
       zod.array(projectSchema)
     );
   }
 
-  public async listForUser(userUrn: string): Promise<Project[]> {
+  public async listForUser(userUrn: string): Promise<UserProjects> {
     return this.fetcher.fetchOk(
       {
         method: "GET",
         path: `projects/user/${userUrn}`,
       },
-      zod.array(projectSchema)
+      userProjectsSchema
+    );
+  }
+  // Projects that have the person `personUrn` as a maintainer.
+  public async listMaintainedBy(personUrn: string): Promise<FailedProject[]> {
+    return this.fetcher.fetchOk(
+      {
+        method: "GET",
+        path: `identities/${personUrn}/projects`,
+      },
+      zod.array(failedProjectSchema)
     );
   }
+
   public async requestsList(): Promise<Request[]> {
     return this.fetcher.fetchOk(
       {
@@ -259,16 +543,30 @@ export class Client {
     });
   }
 
-  public async requestSubmit(projectUrn: string): Promise<Request> {
+  public async requestSubmit(
+    projectUrn: string,
+    requestOptions: ProjectRequestOptions = {}
+  ): Promise<Request> {
     return this.fetcher.fetchOk(
       {
         method: "PUT",
         path: `projects/requests/${projectUrn}`,
+        query: requestOptions,
       },
       requestSchema
     );
   }
 
+  public async requestHistory(projectUrn: string): Promise<RequestHistory> {
+    return this.fetcher.fetchOk(
+      {
+        method: "GET",
+        path: `projects/requests/${projectUrn}/history`,
+      },
+      requestHistorySchema
+    );
+  }
+
   public async listPeers(
     projectUrn: string,
     options?: RequestOptions
@@ -323,4 +621,105 @@ export class Client {
       zod.array(patchSchema)
     );
   }
+
+  public async annotations(): Promise<Record<string, Annotation>> {
+    return this.fetcher.fetchOk(
+      {
+        method: "GET",
+        path: "projects/annotations",
+      },
+      zod.record(annotationSchema)
+    );
+  }
+
+  public async annotation(urn: string): Promise<Annotation> {
+    return this.fetcher.fetchOk(
+      {
+        method: "GET",
+        path: `projects/${urn}/annotation`,
+      },
+      annotationSchema
+    );
+  }
+
+  public async annotationSet(
+    urn: string,
+    annotation: Annotation
+  ): Promise<Annotation> {
+    return this.fetcher.fetchOk(
+      {
+        method: "PUT",
+        path: `projects/${urn}/annotation`,
+        body: annotation,
+      },
+      annotationSchema
+    );
+  }
+
+  public async annotationRemove(urn: string): Promise<void> {
+    return this.fetcher.fetchOkNoContent({
+      method: "DELETE",
+      path: `projects/${urn}/annotation`,
+    });
+  }
+
+  // Revisions of the identity document of a project, oldest first.
+  public async history(urn: string): Promise<Revision[]> {
+    return this.fetcher.fetchOk(
+      {
+        method: "GET",
+        path: `projects/${urn}/history`,
+      },
+      zod.array(revisionSchema)
+    );
+  }
+
+  public async activity(
+    urn: string,
+    page: { before?: number; limit?: number } = {}
+  ): Promise<ActivityPage> {
+    return this.fetcher.fetchOk(
+      {
+        method: "GET",
+        path: `projects/${urn}/activity`,
+        query: page,
+      },
+      activityPageSchema
+    );
+  }
+
+  public async tracking(
+    urn: string,
+    changes: TrackingChanges
+  ): Promise<Peer[]> {
+    return this.fetcher.fetchOk(
+      {
+        method: "PUT",
+        path: `projects/${urn}/tracking`,
+        body: changes,
+      },
+      zod.array(peerSchema)
+    );
+  }
+
+  public async trackingPolicy(urn: string): Promise<TrackingPolicy> {
+    return this.fetcher.fetchOk(
+      {
+        method: "GET",
+        path: `projects/${urn}/tracking/policy`,
+      },
+      trackingPolicySchema
+    );
+  }
+
+  public async trackingPolicySet(
+    urn: string,
+    policy: TrackingPolicy
+  ): Promise<void> {
+    return this.fetcher.fetchOkNoContent({
+      method: "PUT",
+      path: `projects/${urn}/tracking/policy`,
+      body: policy,
+    });
+  }
 }
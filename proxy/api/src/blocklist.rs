@@ -0,0 +1,112 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Ignore misbehaving peers.
+//!
+//! Blocked peers are kept in the session settings, see [`crate::session::blocklist`]. Blocking a
+//! peer untracks it from every project, so nothing is replicated from it anymore. Peers that were
+//! replicated before are left out of project peers and patches, and the gossip of blocked peers is
+//! dropped before the proxy acts on it, see [`filter_events`].
+
+use futures::prelude::*;
+
+use link_crypto::PeerId;
+
+use crate::{error, session};
+
+/// Whether `peer_id` is blocked.
+///
+/// # Errors
+///
+/// Errors if we cannot read from the store.
+pub fn is_blocked(store: &kv::Store, peer_id: &PeerId) -> Result<bool, error::Error> {
+    Ok(session::blocklist(store)?.contains(peer_id))
+}
+
+/// Fail with [`error::Error::PeerBlocked`] if `peer_id` is blocked.
+///
+/// # Errors
+///
+///   * The peer is blocked.
+///   * We cannot read from the store.
+pub fn guard(store: &kv::Store, peer_id: PeerId) -> Result<(), error::Error> {
+    if is_blocked(store, &peer_id)? {
+        Err(error::Error::PeerBlocked(peer_id))
+    } else {
+        Ok(())
+    }
+}
+
+/// Block `peer_id` and untrack it from every project.
+///
+/// # Errors
+///
+///   * `peer_id` is the local peer.
+///   * There is no session to store the blocklist in.
+///   * We cannot read from or write to the store.
+///   * Listing the projects or untracking the peer failed.
+pub async fn block(
+    peer: &crate::peer::Peer,
+    store: &kv::Store,
+    peer_id: PeerId,
+) -> Result<(), error::Error> {
+    if peer_id == peer.librad_peer().peer_id() {
+        return Err(error::Error::BlockLocalPeer);
+    }
+    // The blocklist lives in the session, without one the peer would be untracked but not
+    // blocked.
+    if session::get_current(store)?.is_none() {
+        return Err(error::Error::NoSession);
+    }
+
+    let mut blocklist = session::blocklist(store)?;
+    if !blocklist.contains(&peer_id) {
+        blocklist.push(peer_id);
+        session::update_blocklist(store, blocklist)?;
+    }
+
+    for project in radicle_daemon::state::list_projects(peer.librad_peer()).await? {
+        let urn = project.urn();
+        let tracked = radicle_daemon::state::tracked(peer.librad_peer(), urn.clone())
+            .await?
+            .iter()
+            .any(|project_peer| project_peer.peer_id() == peer_id);
+        if tracked {
+            radicle_daemon::state::untrack(peer.librad_peer(), urn, peer_id).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove `peer_id` from the blocklist. Projects it was untracked from stay untracked.
+///
+/// # Errors
+///
+/// Errors if we cannot read from or write to the store.
+pub fn unblock(store: &kv::Store, peer_id: PeerId) -> Result<(), error::Error> {
+    let mut blocklist = session::blocklist(store)?;
+    blocklist.retain(|blocked| *blocked != peer_id);
+    session::update_blocklist(store, blocklist)
+}
+
+/// Drop the gossip of blocked peers from `events`.
+pub fn filter_events(
+    store: kv::Store,
+    events: impl Stream<Item = radicle_daemon::PeerEvent>,
+) -> impl Stream<Item = radicle_daemon::PeerEvent> {
+    events.filter(move |event| {
+        let blocked = if let radicle_daemon::PeerEvent::GossipFetched { provider, .. } = event {
+            is_blocked(&store, &provider.peer_id).unwrap_or_else(|err| {
+                tracing::warn!(?err, "failed to read blocklist");
+                false
+            })
+        } else {
+            false
+        };
+        future::ready(!blocked)
+    })
+}
@@ -13,7 +13,7 @@ use futures::prelude::*;
 use rand::Rng as _;
 use tokio::sync::RwLock;
 
-use crate::{keystore, service};
+use crate::{keystore, notification, service};
 
 /// Container to pass down dependencies into HTTP filter chains.
 #[derive(Clone)]
@@ -178,6 +178,8 @@ pub struct Sealed {
     pub paths: librad::paths::Paths,
     /// Receives a notification when the server is asked to shut down
     pub shutdown: Arc<tokio::sync::Notify>,
+    /// Notifications that originate in the proxy rather than the peer.
+    pub notifications: notification::Local,
 }
 
 impl Unsealed {
@@ -191,6 +193,19 @@ impl Unsealed {
             .take_until(async move { shutdown.notified().await })
     }
 
+    /// Return a stream that emits notifications sent through [`Sealed::notifications`].
+    ///
+    /// The stream ends when API server is shut down.
+    pub fn local_notifications(
+        &self,
+    ) -> impl Stream<Item = notification::Notification> + Send + 'static {
+        let shutdown = self.rest.shutdown.clone();
+        self.rest
+            .notifications
+            .subscribe()
+            .take_until(async move { shutdown.notified().await })
+    }
+
     /// Initialises a new [`Unsealed`] context with the store and coco state in the given temporary
     /// directory.
     ///
@@ -241,6 +256,7 @@ impl Unsealed {
                     keystore: Arc::new(keystore::memory()),
                     paths,
                     shutdown: Arc::new(tokio::sync::Notify::new()),
+                    notifications: notification::Local::new(),
                 },
             },
             run_handle,
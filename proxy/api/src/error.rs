@@ -27,6 +27,35 @@ pub enum Error {
     #[error(transparent)]
     Peer(#[from] radicle_daemon::peer::Error),
 
+    /// Error interacting with a git repository directly.
+    #[error(transparent)]
+    Git(#[from] radicle_source::surf::vcs::git::git2::Error),
+
+    /// Error accessing the storage of the peer.
+    #[error(transparent)]
+    PeerStorage(#[from] librad::net::peer::error::Storage),
+
+    /// Error reading or updating an identity document.
+    #[error(transparent)]
+    Identities(#[from] librad::git::identities::Error),
+
+    /// Error replicating a project from another peer.
+    #[error(transparent)]
+    Replicate(#[from] librad::net::peer::error::Replicate),
+
+    /// Error loading or updating signed refs.
+    #[error(transparent)]
+    SignedRefs(#[from] librad::git::refs::stored::Error),
+
+    /// A `git` command exited unsuccessfully.
+    #[error("`git {args}` failed: {stderr}")]
+    GitCommand {
+        /// Arguments `git` was invoked with.
+        args: String,
+        /// Error output of the command.
+        stderr: String,
+    },
+
     /// An I/O error occurred.
     #[error(transparent)]
     Io(#[from] io::Error),
@@ -53,6 +82,161 @@ pub enum Error {
     #[error("missing default branch")]
     MissingDefaultBranch,
 
+    /// The branch does not exist in the local peer's view of the project.
+    #[error("branch `{0}` not found")]
+    BranchNotFound(String),
+
+    /// The person identity could not be found in the monorepo.
+    #[error("person not found")]
+    PersonNotFound,
+
+    /// The requested change to the delegations of an identity is not valid.
+    #[error("invalid delegations: {0}")]
+    InvalidDelegations(String),
+
+    /// A bundle to import is malformed or its content could not be verified.
+    #[error("invalid bundle: {0}")]
+    InvalidBundle(String),
+
+    /// The local identity is not allowed to change the identity document.
+    #[error("the local identity is not a delegate of the project")]
+    NotADelegate,
+
+    /// The peer is not a delegate of the project.
+    #[error("peer {0} is not a delegate of the project")]
+    NotADelegatePeer(link_crypto::PeerId),
+
+    /// The peer is not connected and no addresses to reach it were given.
+    #[error("peer {0} is not connected")]
+    PeerNotConnected(link_crypto::PeerId),
+
+    /// The peer is not tracked for the project.
+    #[error("peer {0} is not tracked")]
+    PeerNotTracked(link_crypto::PeerId),
+
+    /// The git remote name of a peer, derived from its handle, can't be used in a refspec.
+    #[error("`{0}` is not a valid remote name")]
+    InvalidRemoteName(String),
+
+    /// The branch name can't be used in a ref.
+    #[error("`{0}` is not a valid branch name")]
+    InvalidBranchName(String),
+
+    /// The path is not registered as a working copy of the project.
+    #[error("`{}` is not a registered working copy", .0.display())]
+    WorkingCopyNotFound(std::path::PathBuf),
+
+    /// Neither branches nor tags were selected for publishing.
+    #[error("no branches or tags to publish")]
+    NothingToPublish,
+
+    /// The branch has diverged from the branch it should be fast-forwarded to.
+    #[error("branch `{0}` cannot be fast-forwarded")]
+    NotFastForward(String),
+
+    /// There already is a file or directory where a working copy should be created.
+    #[error("`{}` already exists", .0.display())]
+    WorkingCopyExists(std::path::PathBuf),
+
+    /// No project name can be derived from the URL to import from.
+    #[error("cannot derive a project name from `{0}`")]
+    InvalidImportUrl(String),
+
+    /// The license of a template is not known.
+    #[error("unknown license `{0}`")]
+    UnknownLicense(String),
+
+    /// There is no user-defined template directory with the given name.
+    #[error("template `{0}` not found")]
+    TemplateNotFound(String),
+
+    /// Templates can only be applied to new repositories.
+    #[error("a template can't be applied to an existing repository")]
+    TemplateForExistingRepository,
+
+    /// The project has no README.
+    #[error("README not found")]
+    ReadmeNotFound,
+
+    /// There is no file at the given path of a project.
+    #[error("`{0}` not found")]
+    PathNotFound(String),
+
+    /// The peer is on the blocklist.
+    #[error("peer {0} is blocked")]
+    PeerBlocked(link_crypto::PeerId),
+
+    /// The local peer can't be put on the blocklist.
+    #[error("the local peer can't be blocked")]
+    BlockLocalPeer,
+
+    /// There is no session to store the setting in.
+    #[error("no session has been created yet")]
+    NoSession,
+
+    /// A petname can only be given to a peer id or a person URN.
+    #[error("`{0}` is neither a peer id nor a person URN")]
+    InvalidPetnameSubject(String),
+
+    /// The petname is empty or could be mistaken for an identifier.
+    #[error("invalid petname `{0}`")]
+    InvalidPetname(String),
+
+    /// Somebody else in the address book already has the petname.
+    #[error("petname `{0}` is already taken")]
+    PetnameTaken(String),
+
+    /// Nobody in the address book has the petname.
+    #[error("no petname `{0}` in the address book")]
+    UnknownPetname(String),
+
+    /// The person with the petname has several devices and none of them is the obvious choice.
+    #[error("`{0}` has more than one device, use `<handle>@<peer_id>` instead")]
+    AmbiguousPetname(String),
+
+    /// A page must hold at least one item.
+    #[error("the limit must be greater than 0")]
+    InvalidLimit,
+
+    /// The identifier is malformed.
+    #[error(transparent)]
+    InvalidIdentifier(#[from] crate::identifier::ParseError),
+
+    /// Person identities could not be read from the monorepo.
+    #[error(transparent)]
+    IdentitiesPerson(#[from] rad_identities::person::Error),
+
+    /// The identity document could not be found in the monorepo.
+    #[error("identity not found")]
+    IdentityNotFound,
+
+    /// The identity document recorded in the commit is malformed.
+    #[error("identity document in commit {0} is malformed")]
+    MalformedIdentityDocument(radicle_git_ext::Oid),
+
+    /// No request for the project was recorded.
+    #[error("no request for the project was recorded")]
+    ProjectRequestNotFound,
+
+    /// The local identity could not be read or set.
+    #[error(transparent)]
+    IdentitiesLocal(#[from] rad_identities::local::Error),
+
+    /// The device is not linked to the person identity.
+    #[error("device {0} is not linked to the identity")]
+    DeviceNotFound(link_crypto::PeerId),
+
+    /// The local device can't revoke itself from the person identity.
+    #[error("the local device can't be revoked")]
+    RevokeLocalDevice,
+
+    /// The remaining devices are no majority of the current ones, so the revision revoking the
+    /// device could only verify with its own signature.
+    #[error(
+        "device {0} can't be revoked, the remaining devices are no majority of the current ones"
+    )]
+    RevokeWithoutQuorum(link_crypto::PeerId),
+
     #[error("Failed to open readonly Git storage")]
     OpenReadOnlyGitStorage(#[from] librad::git::storage::read::error::Init),
 }
@@ -19,6 +19,7 @@ mod error;
 mod identity;
 mod keystore;
 mod notification;
+mod petname;
 mod project;
 mod session;
 mod source;
@@ -57,6 +58,7 @@ pub fn api(ctx: context::Context) -> impl Filter<Extract = impl Reply, Error = R
         .and(control::filters(ctx.clone()));
     let identity_filter = path("identities").and(identity::filters(ctx.clone()));
     let notification_filter = path("notifications").and(notification::filters(ctx.clone()));
+    let petname_filter = path("petnames").and(petname::filters(ctx.clone()));
     let project_filter = path("projects").and(project::filters(ctx.clone()));
     let session_filter = path("session").and(session::filters(ctx.clone()));
     let keystore_filter = path("keystore").and(keystore::filters(ctx.clone()));
@@ -68,6 +70,7 @@ pub fn api(ctx: context::Context) -> impl Filter<Extract = impl Reply, Error = R
         diagnostics_filter,
         identity_filter,
         notification_filter,
+        petname_filter,
         project_filter,
         session_filter,
         keystore_filter,
@@ -78,9 +81,11 @@ pub fn api(ctx: context::Context) -> impl Filter<Extract = impl Reply, Error = R
         .allow_any_origin()
         .allow_credentials(true)
         .allow_headers(&[warp::http::header::CONTENT_TYPE, warp::http::header::COOKIE])
+        .expose_headers(&[project::FRESH_HEADER])
         .allow_methods(&[
             warp::http::Method::DELETE,
             warp::http::Method::GET,
+            warp::http::Method::PATCH,
             warp::http::Method::POST,
             warp::http::Method::PUT,
             warp::http::Method::OPTIONS,
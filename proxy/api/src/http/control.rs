@@ -80,9 +80,10 @@ mod handler {
             radicle_daemon::state::get_branch(ctx.peer.librad_peer(), meta.urn(), None, None)
                 .await
                 .map_err(error::Error::from)?;
+        let contents = project::contents::detect(&ctx.peer, &branch)?;
         let stats = browser::using(&ctx.peer, branch, |browser| Ok(browser.get_stats()?))
             .map_err(error::Error::from)?;
-        let project = project::Full::try_from((meta, stats))?;
+        let project = project::Full::try_from((meta, stats, contents))?;
 
         Ok(reply::with_status(
             reply::json(&project),
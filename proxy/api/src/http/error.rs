@@ -89,6 +89,12 @@ pub async fn recover(err: warp::Rejection) -> Result<impl warp::Reply, Infallibl
         Response::from(err)
     } else if let Some(err) = err.find::<Response>() {
         err.clone()
+    } else if err.find::<warp::reject::PayloadTooLarge>().is_some() {
+        Response {
+            status_code: StatusCode::PAYLOAD_TOO_LARGE,
+            variant: "PAYLOAD_TOO_LARGE",
+            message: "Request body is too large".to_string(),
+        }
     } else {
         Response {
             status_code: StatusCode::INTERNAL_SERVER_ERROR,
@@ -323,7 +329,191 @@ impl From<&error::Error> for Response {
                 variant: "MISSING_DEFAULT_BRANCH",
                 message: "Default branch for project is missing".to_string(),
             },
+            error::Error::BranchNotFound(_) => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "BRANCH_NOT_FOUND",
+                message: err.to_string(),
+            },
+            error::Error::PersonNotFound => Self {
+                status_code: StatusCode::NOT_FOUND,
+                variant: "PERSON_NOT_FOUND",
+                message: "Person not found".to_string(),
+            },
+            error::Error::InvalidDelegations(_) => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "INVALID_DELEGATIONS",
+                message: err.to_string(),
+            },
+            error::Error::InvalidBundle(_) => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "INVALID_BUNDLE",
+                message: err.to_string(),
+            },
+            error::Error::NotADelegate => Self {
+                status_code: StatusCode::FORBIDDEN,
+                variant: "NOT_A_DELEGATE",
+                message: err.to_string(),
+            },
+            error::Error::NotADelegatePeer(_) => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "NOT_A_DELEGATE_PEER",
+                message: err.to_string(),
+            },
+            error::Error::PeerNotConnected(_) => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "PEER_NOT_CONNECTED",
+                message: err.to_string(),
+            },
+            error::Error::PeerNotTracked(_) => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "PEER_NOT_TRACKED",
+                message: err.to_string(),
+            },
+            error::Error::InvalidRemoteName(_) => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "INVALID_REMOTE_NAME",
+                message: err.to_string(),
+            },
+            error::Error::InvalidBranchName(_) => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "INVALID_BRANCH_NAME",
+                message: err.to_string(),
+            },
+            error::Error::WorkingCopyNotFound(_) => Self {
+                status_code: StatusCode::NOT_FOUND,
+                variant: "WORKING_COPY_NOT_FOUND",
+                message: err.to_string(),
+            },
+            error::Error::NothingToPublish => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "NOTHING_TO_PUBLISH",
+                message: err.to_string(),
+            },
+            error::Error::NotFastForward(_) => Self {
+                status_code: StatusCode::CONFLICT,
+                variant: "NOT_FAST_FORWARD",
+                message: err.to_string(),
+            },
+            error::Error::WorkingCopyExists(_) => Self {
+                status_code: StatusCode::CONFLICT,
+                variant: "WORKING_COPY_EXISTS",
+                message: err.to_string(),
+            },
+            error::Error::InvalidImportUrl(_) => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "INVALID_IMPORT_URL",
+                message: err.to_string(),
+            },
+            error::Error::UnknownLicense(_) => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "UNKNOWN_LICENSE",
+                message: err.to_string(),
+            },
+            error::Error::TemplateNotFound(_) => Self {
+                status_code: StatusCode::NOT_FOUND,
+                variant: "TEMPLATE_NOT_FOUND",
+                message: err.to_string(),
+            },
+            error::Error::TemplateForExistingRepository => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "TEMPLATE_FOR_EXISTING_REPOSITORY",
+                message: err.to_string(),
+            },
+            error::Error::ReadmeNotFound => Self {
+                status_code: StatusCode::NOT_FOUND,
+                variant: "README_NOT_FOUND",
+                message: err.to_string(),
+            },
+            error::Error::PathNotFound(_) => Self {
+                status_code: StatusCode::NOT_FOUND,
+                variant: "PATH_NOT_FOUND",
+                message: err.to_string(),
+            },
+            error::Error::PeerBlocked(_) => Self {
+                status_code: StatusCode::CONFLICT,
+                variant: "PEER_BLOCKED",
+                message: err.to_string(),
+            },
+            error::Error::BlockLocalPeer => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "BLOCK_LOCAL_PEER",
+                message: err.to_string(),
+            },
+            error::Error::NoSession => Self {
+                status_code: StatusCode::NOT_FOUND,
+                variant: "NO_SESSION",
+                message: err.to_string(),
+            },
+            error::Error::InvalidPetnameSubject(_) => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "INVALID_PETNAME_SUBJECT",
+                message: err.to_string(),
+            },
+            error::Error::InvalidPetname(_) => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "INVALID_PETNAME",
+                message: err.to_string(),
+            },
+            error::Error::UnknownPetname(_) => Self {
+                status_code: StatusCode::NOT_FOUND,
+                variant: "UNKNOWN_PETNAME",
+                message: err.to_string(),
+            },
+            error::Error::PetnameTaken(_) => Self {
+                status_code: StatusCode::CONFLICT,
+                variant: "PETNAME_TAKEN",
+                message: err.to_string(),
+            },
+            error::Error::AmbiguousPetname(_) => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "AMBIGUOUS_PETNAME",
+                message: err.to_string(),
+            },
+            error::Error::InvalidLimit => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "INVALID_LIMIT",
+                message: err.to_string(),
+            },
+            error::Error::InvalidIdentifier(_) => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "INVALID_IDENTIFIER",
+                message: err.to_string(),
+            },
+            error::Error::IdentityNotFound => Self {
+                status_code: StatusCode::NOT_FOUND,
+                variant: "IDENTITY_NOT_FOUND",
+                message: err.to_string(),
+            },
+            error::Error::ProjectRequestNotFound => Self {
+                status_code: StatusCode::NOT_FOUND,
+                variant: "PROJECT_REQUEST_NOT_FOUND",
+                message: err.to_string(),
+            },
+            error::Error::DeviceNotFound(_) => Self {
+                status_code: StatusCode::NOT_FOUND,
+                variant: "DEVICE_NOT_FOUND",
+                message: err.to_string(),
+            },
+            error::Error::RevokeLocalDevice => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "REVOKE_LOCAL_DEVICE",
+                message: err.to_string(),
+            },
+            error::Error::RevokeWithoutQuorum(_) => Self {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "REVOKE_WITHOUT_QUORUM",
+                message: err.to_string(),
+            },
             error::Error::OpenReadOnlyGitStorage(_)
+            | error::Error::Git(_)
+            | error::Error::GitCommand { .. }
+            | error::Error::PeerStorage(_)
+            | error::Error::Identities(_)
+            | error::Error::IdentitiesPerson(_)
+            | error::Error::IdentitiesLocal(_)
+            | error::Error::MalformedIdentityDocument(_)
+            | error::Error::Replicate(_)
+            | error::Error::SignedRefs(_)
             | error::Error::Peer(_)
             | error::Error::Io(_)
             | error::Error::Store(_)
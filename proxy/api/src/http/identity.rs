@@ -6,20 +6,39 @@
 
 //! Manage the state and stateful interactions with the underlying peer API of librad.
 
+use serde::{Deserialize, Serialize};
 use warp::{filters::BoxedFilter, path, Filter, Rejection, Reply};
 
 use link_identities::git::Urn;
 
 use crate::{context, http};
 
+mod device;
+
 /// Combination of all identity routes.
 pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
-    get_remote_filter(ctx.clone())
+    device::filters(ctx.clone())
+        .or(get_remote_filter(ctx.clone()))
+        .or(request_filter(ctx.clone()))
+        .or(projects_filter(ctx.clone()))
+        .or(history_filter(ctx.clone()))
+        .or(list_filter(ctx.clone()))
         .or(create_filter(ctx.clone()))
         .or(update_filter(ctx))
         .boxed()
 }
 
+/// `GET /?handle=<prefix>&offset=<n>&limit=<n>`
+fn list_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::end()
+        .and(warp::get())
+        .and(http::with_qs_opt::<DirectoryQuery>())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::list)
+}
+
 /// `POST /`
 fn create_filter(
     ctx: context::Context,
@@ -54,13 +73,49 @@ fn get_remote_filter(
         .and_then(handler::get_remote)
 }
 
+/// `PUT /requests/<id>`
+fn request_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("requests")
+        .and(path::param::<Urn>())
+        .and(path::end())
+        .and(warp::put())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::request)
+}
+
+/// `GET /<id>/projects`
+fn projects_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("projects"))
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::projects)
+}
+
+/// `GET /<id>/history`
+fn history_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("history"))
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::history)
+}
+
 /// Identity handlers for conversion between core domain and http request fullfilment.
 mod handler {
     use warp::{http::StatusCode, reply, Rejection, Reply};
 
     use link_identities::git::Urn;
 
-    use crate::{context, http, identity, session};
+    use crate::{context, http, identity, project, session};
 
     /// Create a new [`identity::Identity`].
     pub async fn create(
@@ -80,7 +135,35 @@ mod handler {
 
         session::initialize(&ctx.rest.store, id.clone(), &ctx.rest.default_seeds)?;
 
-        Ok(reply::with_status(reply::json(&id), StatusCode::CREATED))
+        Ok(reply::with_status(
+            reply::json(&id.with_petname(&ctx.rest.store)?),
+            StatusCode::CREATED,
+        ))
+    }
+
+    /// List the persons in the monorepo.
+    pub async fn list(
+        query: Option<super::DirectoryQuery>,
+        ctx: context::Unsealed,
+    ) -> Result<impl Reply, Rejection> {
+        let query = query.unwrap_or_default();
+        let page = identity::directory(
+            &ctx.peer,
+            &ctx.rest.store,
+            query.handle.as_deref(),
+            query.offset.unwrap_or_default(),
+            query.limit.unwrap_or(identity::DEFAULT_DIRECTORY_LIMIT),
+        )
+        .await?;
+
+        Ok(reply::json(&page))
+    }
+
+    /// Walk the revisions of the identity document of the person with the given `id`.
+    pub async fn history(id: Urn, ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        let revisions = identity::history::revisions(&ctx.peer, &id).await?;
+
+        Ok(reply::json(&revisions))
     }
 
     /// Update the [`identity::Identity`] metadata.
@@ -92,7 +175,10 @@ mod handler {
         let id = identity::update(ctx.peer.librad_peer(), metadata).await?;
         session::update_identity(&ctx.rest.store, id.clone())?;
 
-        Ok(reply::with_status(reply::json(&id), StatusCode::OK))
+        Ok(reply::with_status(
+            reply::json(&id.with_petname(&ctx.rest.store)?),
+            StatusCode::OK,
+        ))
     }
 
     /// Get the [`identity::Person`] for the given `id`.
@@ -102,7 +188,9 @@ mod handler {
         let user =
             rad_identities::person::get(&storage, &id).map_err(http::error::Response::from)?;
         match user {
-            Some(user) => Ok(reply::json(&identity::Person::from(user))),
+            Some(user) => Ok(reply::json(
+                &identity::Person::from(user).with_petname(ctx.store())?,
+            )),
             None => Err(http::error::Response {
                 status_code: StatusCode::NOT_FOUND,
                 variant: "NOT_FOUND",
@@ -111,6 +199,33 @@ mod handler {
             .into()),
         }
     }
+
+    /// Ask the network for the [`identity::Person`] with the given `id` and replicate it.
+    pub async fn request(id: Urn, ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        let person = identity::request(ctx.peer.librad_peer(), &ctx.rest.store, id).await?;
+
+        Ok(reply::json(&person.with_petname(&ctx.rest.store)?))
+    }
+
+    /// List the projects maintained by the person with the given `id`.
+    pub async fn projects(id: Urn, ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        let projects = project::list_maintained_by(&ctx.peer, &id).await?;
+
+        Ok(reply::json(&projects))
+    }
+}
+
+/// Query parameters for [`handler::list`].
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectoryQuery {
+    /// Only persons whose handle starts with this prefix, ignoring case.
+    handle: Option<String>,
+    /// Number of persons to skip, as given by [`crate::identity::DirectoryPage::next`].
+    offset: Option<usize>,
+    /// Maximum number of persons to return, capped at
+    /// [`crate::identity::MAX_DIRECTORY_LIMIT`].
+    limit: Option<usize>,
 }
 
 #[allow(clippy::non_ascii_literal, clippy::unwrap_used)]
@@ -118,7 +233,7 @@ mod handler {
 mod test {
     use pretty_assertions::assert_eq;
     use serde_json::json;
-    use std::convert::TryInto;
+    use std::convert::{TryFrom as _, TryInto};
     use warp::{http::StatusCode, test::request};
 
     use crate::{context, error, http, identity, session};
@@ -336,4 +451,204 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn request_unknown() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let urn = link_identities::git::Urn::new(radicle_git_ext::Oid::try_from(
+            "7ab8629dd6da14dcacde7f65b3d58cd291d7e235",
+        )?);
+
+        let res = request()
+            .method("PUT")
+            .path(&format!("/requests/{}", urn))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::NOT_FOUND, |have| {
+            assert_eq!(have["variant"], "PERSON_NOT_FOUND");
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn projects() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let session = session::initialize_test(&ctx, "cloudhead").await;
+        let owner =
+            radicle_daemon::state::get_local(ctx.peer.librad_peer(), session.identity.urn.clone())
+                .await?
+                .unwrap();
+        let project_urn = crate::control::replicate_platinum(
+            &ctx.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}/projects", session.identity.urn))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have.as_array().unwrap().len(), 1);
+            assert_eq!(have[0]["urn"], json!(project_urn));
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let session = session::initialize_test(&ctx, "cloudhead").await;
+        let owner =
+            radicle_daemon::state::get_local(ctx.peer.librad_peer(), session.identity.urn.clone())
+                .await?
+                .unwrap();
+        let project_urn = crate::control::replicate_platinum(
+            &ctx.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+
+        let res = request()
+            .method("GET")
+            .path("/?handle=CLOUD")
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(
+                have,
+                json!({
+                    "persons": [{
+                        "urn": session.identity.urn,
+                        "metadata": { "handle": "cloudhead", "ethereum": null },
+                        "peerIds": [ctx.peer.librad_peer().peer_id()],
+                        "projects": [project_urn],
+                    }],
+                    "total": 1,
+                    "next": null,
+                })
+            );
+        });
+
+        let res = request().method("GET").path("/?handle=x").reply(&api).await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have, json!({ "persons": [], "total": 0, "next": null }));
+        });
+
+        let res = request()
+            .method("GET")
+            .path("/?offset=1&limit=10")
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have, json!({ "persons": [], "total": 1, "next": null }));
+        });
+
+        let res = request().method("GET").path("/?limit=0").reply(&api).await;
+        http::test::assert_response(&res, StatusCode::BAD_REQUEST, |have| {
+            assert_eq!(have["variant"], "INVALID_LIMIT");
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn history() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let session = session::initialize_test(&ctx, "cloudhead").await;
+        let peer_id = ctx.peer.librad_peer().peer_id();
+
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}/history", session.identity.urn))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            let revisions = have.as_array().unwrap();
+            assert_eq!(revisions.len(), 1);
+            assert_eq!(
+                revisions[0]["delegations"],
+                json!([{ "type": "key", "peerId": peer_id }])
+            );
+            assert_eq!(
+                revisions[0]["signatures"],
+                json!([{ "peerId": peer_id, "valid": true }])
+            );
+            assert_eq!(
+                revisions[0]["verification"],
+                json!({ "signatures": true, "quorum": true, "parentQuorum": null })
+            );
+            assert_eq!(revisions[0]["payloadDiff"][0]["type"], "added");
+        });
+
+        identity::update(
+            ctx.peer.librad_peer(),
+            identity::Metadata {
+                handle: "cloudhead_next".into(),
+                ethereum: None,
+            },
+        )
+        .await?;
+
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}/history", session.identity.urn))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            let revisions = have.as_array().unwrap();
+            assert_eq!(revisions.len(), 2);
+            assert_eq!(revisions[1]["parents"], json!([revisions[0]["commit"]]));
+            assert_eq!(
+                revisions[1]["verification"],
+                json!({ "signatures": true, "quorum": true, "parentQuorum": true })
+            );
+            assert!(revisions[1]["payloadDiff"]
+                .as_array()
+                .unwrap()
+                .contains(&json!({
+                    "type": "changed",
+                    "path": ["https://radicle.xyz/link/identities/person/v1", "name"],
+                    "old": "cloudhead",
+                    "new": "cloudhead_next",
+                })));
+        });
+
+        let urn = link_identities::git::Urn::new(radicle_git_ext::Oid::try_from(
+            "7ab8629dd6da14dcacde7f65b3d58cd291d7e235",
+        )?);
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}/history", urn))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::NOT_FOUND, |have| {
+            assert_eq!(have["variant"], "IDENTITY_NOT_FOUND");
+        });
+
+        Ok(())
+    }
 }
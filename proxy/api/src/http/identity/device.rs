@@ -0,0 +1,287 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Endpoints for linking devices to the local person identity, see [`crate::identity::device`].
+
+use warp::{filters::BoxedFilter, path, Filter, Rejection, Reply};
+
+use link_crypto::PeerId;
+
+use crate::{context, http};
+
+/// Combination of all routes.
+pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
+    list_filter(ctx.clone())
+        .or(linking_request_filter(ctx.clone()))
+        .or(link_filter(ctx.clone()))
+        .or(adopt_filter(ctx.clone()))
+        .or(sign_filter(ctx.clone()))
+        .or(revoke_filter(ctx))
+        .boxed()
+}
+
+/// `GET /devices`
+fn list_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("devices")
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_context_unsealed(ctx.clone()))
+        .and(http::with_owner_guard(ctx))
+        .and_then(handler::list)
+}
+
+/// `GET /devices/linking-request`
+fn linking_request_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("devices")
+        .and(path("linking-request"))
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::linking_request)
+}
+
+/// `POST /devices`
+fn link_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("devices")
+        .and(path::end())
+        .and(warp::post())
+        .and(http::with_context_unsealed(ctx.clone()))
+        .and(http::with_owner_guard(ctx))
+        .and(warp::body::json())
+        .and_then(handler::link)
+}
+
+/// `PUT /devices/adopt`
+fn adopt_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("devices")
+        .and(path("adopt"))
+        .and(path::end())
+        .and(warp::put())
+        .and(http::with_context_unsealed(ctx))
+        .and(warp::body::json())
+        .and_then(handler::adopt)
+}
+
+/// `PUT /devices/<peer_id>/sign`
+fn sign_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("devices")
+        .and(path::param::<PeerId>())
+        .and(path("sign"))
+        .and(path::end())
+        .and(warp::put())
+        .and(http::with_context_unsealed(ctx.clone()))
+        .and(http::with_owner_guard(ctx))
+        .and_then(handler::sign)
+}
+
+/// `DELETE /devices/<peer_id>`
+fn revoke_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("devices")
+        .and(path::param::<PeerId>())
+        .and(path::end())
+        .and(warp::delete())
+        .and(http::with_context_unsealed(ctx.clone()))
+        .and(http::with_owner_guard(ctx))
+        .and_then(handler::revoke)
+}
+
+/// Device handlers for conversion between core domain and http request fullfilment.
+mod handler {
+    use warp::{http::StatusCode, reply, Rejection, Reply};
+
+    use link_crypto::PeerId;
+
+    use crate::{context, http, identity::device, session};
+
+    /// List the devices of the local person.
+    pub async fn list(
+        ctx: context::Unsealed,
+        owner: radicle_daemon::LocalIdentity,
+    ) -> Result<impl Reply, Rejection> {
+        let devices = device::list(&ctx.peer, owner.urn()).await?;
+
+        Ok(reply::json(&devices))
+    }
+
+    /// Get the linking request of the local device.
+    pub async fn linking_request(mut ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        let request = device::linking_request(&mut ctx.peer).await;
+
+        Ok(reply::json(&request))
+    }
+
+    /// Add the device of a linking request to the local person.
+    pub async fn link(
+        mut ctx: context::Unsealed,
+        owner: radicle_daemon::LocalIdentity,
+        request: device::LinkingRequest,
+    ) -> Result<impl Reply, Rejection> {
+        let link = device::link(&mut ctx.peer, &owner, request).await?;
+
+        Ok(reply::with_status(reply::json(&link), StatusCode::CREATED))
+    }
+
+    /// Adopt the person identity of a [`device::Link`] as the local identity.
+    pub async fn adopt(
+        ctx: context::Unsealed,
+        link: device::Link,
+    ) -> Result<impl Reply, Rejection> {
+        if session::get_current(&ctx.rest.store)?.is_some() {
+            return Err(http::error::Response {
+                status_code: StatusCode::BAD_REQUEST,
+                variant: "SESSION_IN_USE",
+                message: "A session already exists".to_string(),
+            }
+            .into());
+        }
+
+        let id = device::adopt(&ctx.peer, link).await?;
+
+        session::initialize(&ctx.rest.store, id.clone(), &ctx.rest.default_seeds)?;
+
+        Ok(reply::with_status(
+            reply::json(&id.with_petname(&ctx.rest.store)?),
+            StatusCode::CREATED,
+        ))
+    }
+
+    /// Sign the revision of the identity document published by `device`.
+    pub async fn sign(
+        device: PeerId,
+        ctx: context::Unsealed,
+        owner: radicle_daemon::LocalIdentity,
+    ) -> Result<impl Reply, Rejection> {
+        let devices = device::sign(&ctx.peer, owner.urn(), device).await?;
+
+        Ok(reply::json(&devices))
+    }
+
+    /// Revoke `device` from the local person.
+    pub async fn revoke(
+        device: PeerId,
+        ctx: context::Unsealed,
+        owner: radicle_daemon::LocalIdentity,
+    ) -> Result<impl Reply, Rejection> {
+        let devices = device::revoke(&ctx.peer, &owner, device).await?;
+
+        Ok(reply::json(&devices))
+    }
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use warp::{http::StatusCode, test::request};
+
+    use crate::{context, http, session};
+
+    #[tokio::test]
+    async fn link() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, run) = context::Unsealed::tmp(&tmp_dir)?;
+        let handle = tokio::spawn(run);
+        let api = super::filters(ctx.clone().into());
+
+        let session = session::initialize_test(&ctx, "cloudhead").await;
+        let peer_id = ctx.peer.librad_peer().peer_id();
+        let other = crate::control::generate_peer_id();
+
+        let res = request().method("GET").path("/devices").reply(&api).await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(
+                have,
+                json!([{ "peerId": peer_id, "local": true, "status": "linked" }])
+            );
+        });
+
+        let res = request()
+            .method("POST")
+            .path("/devices")
+            .json(&json!({ "peerId": other }))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::CREATED, |have| {
+            assert_eq!(have["urn"], json!(session.identity.urn));
+            assert_eq!(have["peerId"], json!(peer_id));
+        });
+
+        let res = request().method("GET").path("/devices").reply(&api).await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            let devices = have.as_array().unwrap();
+            assert_eq!(devices.len(), 2);
+            assert!(devices.contains(&json!({
+                "peerId": other,
+                "local": false,
+                "status": "linking",
+            })));
+        });
+
+        let res = request()
+            .method("DELETE")
+            .path(&format!("/devices/{}", peer_id))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::BAD_REQUEST, |have| {
+            assert_eq!(have["variant"], "REVOKE_LOCAL_DEVICE");
+        });
+
+        let res = request()
+            .method("DELETE")
+            .path(&format!("/devices/{}", other))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::BAD_REQUEST, |have| {
+            assert_eq!(have["variant"], "REVOKE_WITHOUT_QUORUM");
+        });
+
+        let res = request()
+            .method("PUT")
+            .path("/devices/adopt")
+            .json(&json!({ "urn": session.identity.urn, "peerId": other }))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::BAD_REQUEST, |have| {
+            assert_eq!(have["variant"], "SESSION_IN_USE");
+        });
+        handle.abort();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn linking_request() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, run) = context::Unsealed::tmp(&tmp_dir)?;
+        let handle = tokio::spawn(run);
+        let api = super::filters(ctx.clone().into());
+
+        let res = request()
+            .method("GET")
+            .path("/devices/linking-request")
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have["peerId"], json!(ctx.peer.librad_peer().peer_id()));
+        });
+        handle.abort();
+
+        Ok(())
+    }
+}
@@ -29,7 +29,7 @@ mod handler {
     use futures::prelude::*;
     use warp::{sse, Rejection, Reply};
 
-    use crate::{context, notification::Notification};
+    use crate::{blocklist, context, notification::Notification};
 
     /// Sets up local peer events notification stream.
     pub async fn local_peer_events(mut ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
@@ -40,9 +40,11 @@ mod handler {
             new: current_status,
         }]);
 
-        let notifications = ctx
-            .peer_events()
-            .filter_map(|event| future::ready(crate::notification::from_peer_event(event)));
+        let notifications = stream::select(
+            blocklist::filter_events(ctx.rest.store.clone(), ctx.peer_events())
+                .filter_map(|event| future::ready(crate::notification::from_peer_event(event))),
+            ctx.local_notifications(),
+        );
 
         Ok(sse::reply(
             sse::keep_alive().stream(
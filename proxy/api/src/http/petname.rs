@@ -0,0 +1,188 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Endpoints for the local address book of petnames, see [`crate::petname`].
+
+use serde::{Deserialize, Serialize};
+use warp::{filters::BoxedFilter, path, Filter, Rejection, Reply};
+
+use crate::{context, http, petname::Subject};
+
+/// Combination of all petname filters.
+pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
+    list_filter(ctx.clone())
+        .or(resolve_filter(ctx.clone()))
+        .or(set_filter(ctx.clone()))
+        .or(delete_filter(ctx))
+        .boxed()
+}
+
+/// `GET /`
+fn list_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::end()
+        .and(warp::get())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::list)
+}
+
+/// `GET /resolve?input=<identifier or petname>`
+fn resolve_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("resolve")
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_qs::<ResolveQuery>())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::resolve)
+}
+
+/// `PUT /<peer_id or person urn>`
+fn set_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Subject>()
+        .and(path::end())
+        .and(warp::put())
+        .and(http::with_context_unsealed(ctx))
+        .and(warp::body::json())
+        .and_then(handler::set)
+}
+
+/// `DELETE /<peer_id or person urn>`
+fn delete_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Subject>()
+        .and(path::end())
+        .and(warp::delete())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::delete)
+}
+
+/// Petname handlers for conversion between core domain and http request fullfilment.
+mod handler {
+    use warp::{http::StatusCode, reply, Rejection, Reply};
+
+    use crate::{context, identifier::Identifier, petname};
+
+    /// List the address book.
+    #[allow(clippy::unused_async)]
+    pub async fn list(ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        let entries = petname::list(&ctx.rest.store)?;
+
+        Ok(reply::json(&entries))
+    }
+
+    /// Resolve an identifier or petname to an [`Identifier`].
+    pub async fn resolve(
+        query: super::ResolveQuery,
+        ctx: context::Unsealed,
+    ) -> Result<impl Reply, Rejection> {
+        let identifier = Identifier::resolve(&ctx.peer, &ctx.rest.store, &query.input).await?;
+
+        Ok(reply::json(&identifier))
+    }
+
+    /// Give a peer or person a petname.
+    #[allow(clippy::unused_async)]
+    pub async fn set(
+        subject: petname::Subject,
+        ctx: context::Unsealed,
+        petname: petname::Petname,
+    ) -> Result<impl Reply, Rejection> {
+        let petname = petname::set(&ctx.rest.store, &subject, petname)?;
+
+        Ok(reply::json(&petname::Entry { subject, petname }))
+    }
+
+    /// Remove the petname of a peer or person.
+    #[allow(clippy::unused_async)]
+    pub async fn delete(
+        subject: petname::Subject,
+        ctx: context::Unsealed,
+    ) -> Result<impl Reply, Rejection> {
+        petname::remove(&ctx.rest.store, &subject)?;
+
+        Ok(reply::with_status(reply(), StatusCode::NO_CONTENT))
+    }
+}
+
+/// Query parameters for [`handler::resolve`].
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolveQuery {
+    /// Identifier of the form `<handle>@<peer_id>` or petname.
+    input: String,
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use warp::{http::StatusCode, test::request};
+
+    use crate::{context, http};
+
+    #[tokio::test]
+    async fn petnames() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+        let alice = crate::control::generate_peer_id();
+
+        let res = request()
+            .method("PUT")
+            .path(&format!("/{}", alice))
+            .json(&json!({ "petname": "alice from the meetup", "note": "knows git" }))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(
+                have,
+                json!({
+                    "subject": alice,
+                    "petname": "alice from the meetup",
+                    "note": "knows git",
+                })
+            );
+        });
+
+        let res = request()
+            .method("GET")
+            .path("/resolve?input=alice%20from%20the%20meetup")
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have, json!(format!("alice from the meetup@{}", alice)));
+        });
+
+        let res = request()
+            .method("GET")
+            .path("/resolve?input=bob")
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::NOT_FOUND, |have| {
+            assert_eq!(have["variant"], "UNKNOWN_PETNAME");
+        });
+
+        let res = request()
+            .method("DELETE")
+            .path(&format!("/{}", alice))
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let res = request().method("GET").path("/").reply(&api).await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have, json!([]));
+        });
+
+        Ok(())
+    }
+}
@@ -16,25 +16,74 @@ use link_identities::git::Urn;
 
 use crate::{context, http};
 
+mod annotation;
+mod maintainer;
+mod repair;
 mod request;
+mod tracking;
+mod working_copy;
+
+/// Response header of project listings that is `false` if some stats were served from an outdated
+/// cache entry and are being recomputed.
+pub const FRESH_HEADER: &str = "x-stats-fresh";
 
 /// Combination of all routes.
 pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
-    checkout_filter(ctx.clone())
+    annotation::filters(ctx.clone())
+        .or(activity_filter(ctx.clone()))
+        .or(bundle_filter(ctx.clone()))
+        .or(checkout_filter(ctx.clone()))
         .or(create_filter(ctx.clone()))
+        .or(delete_filter(ctx.clone()))
         .or(failed_filter(ctx.clone()))
         .or(get_filter(ctx.clone()))
+        .or(history_filter(ctx.clone()))
+        .or(import_filter(ctx.clone()))
+        .or(import_bundle_filter(ctx.clone()))
         .or(owner_contributed_filter(ctx.clone()))
         .or(owner_tracked_filter(ctx.clone()))
+        .or(maintainer::filters(ctx.clone()))
         .or(peers_filter(ctx.clone()))
+        .or(readme_filter(ctx.clone()))
+        .or(repair::filters(ctx.clone()))
         .or(path("requests").and(request::filters(ctx.clone())))
+        .or(templates_filter())
         .or(track_filter(ctx.clone()))
+        .or(tracking::filters(ctx.clone()))
         .or(patches_filter(ctx.clone()))
         .or(untrack_filter(ctx.clone()))
+        .or(update_filter(ctx.clone()))
+        .or(working_copy::filters(ctx.clone()))
         .or(user_filter(ctx))
         .boxed()
 }
 
+/// `GET /<urn>/activity?before=<id>&limit=<limit>`
+fn activity_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("activity"))
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_qs_opt::<ActivityQuery>())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::activity)
+}
+
+/// `GET /<urn>/bundle?peers[]=<peer_id>`
+fn bundle_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("bundle"))
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_qs_opt::<BundleQuery>())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::bundle)
+}
+
 /// `POST /<urn>/checkout`
 fn checkout_filter(
     ctx: context::Context,
@@ -60,6 +109,18 @@ fn create_filter(
         .and_then(handler::create)
 }
 
+/// `DELETE /<urn>?removeWorkingCopies=<bool>`
+fn delete_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path::end())
+        .and(warp::delete())
+        .and(http::with_qs_opt::<DeleteQuery>())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::delete)
+}
+
 /// `GET /failed`
 fn failed_filter(
     ctx: context::Context,
@@ -82,7 +143,47 @@ fn get_filter(
         .and_then(handler::get)
 }
 
-/// `GET /contributed`
+/// `GET /<urn>/history`
+fn history_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("history"))
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::history)
+}
+
+/// `POST /import`
+fn import_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("import")
+        .and(path::end())
+        .and(warp::post())
+        .and(http::with_context_unsealed(ctx.clone()))
+        .and(http::with_owner_guard(ctx))
+        .and(warp::body::json())
+        .and_then(handler::import)
+}
+
+/// `POST /import-bundle`
+fn import_bundle_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("import-bundle")
+        .and(path::end())
+        .and(warp::post())
+        .and(http::with_context_unsealed(ctx))
+        .and(warp::body::content_length_limit(
+            crate::project::bundle::MAX_SIZE,
+        ))
+        .and(warp::body::bytes())
+        .and_then(handler::import_bundle)
+}
+
+/// `GET /contributed?favourite=<bool>&label=<label>`
 fn owner_contributed_filter(
     ctx: context::Context,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
@@ -90,10 +191,11 @@ fn owner_contributed_filter(
         .and(warp::get())
         .and(http::with_context_unsealed(ctx))
         .and(path::end())
+        .and(http::with_qs_opt::<crate::project::annotation::Filter>())
         .and_then(handler::list_owner_contributed)
 }
 
-/// `GET /tracked`
+/// `GET /tracked?favourite=<bool>&label=<label>`
 fn owner_tracked_filter(
     ctx: context::Context,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
@@ -101,6 +203,7 @@ fn owner_tracked_filter(
         .and(warp::get())
         .and(http::with_context_unsealed(ctx))
         .and(path::end())
+        .and(http::with_qs_opt::<crate::project::annotation::Filter>())
         .and_then(handler::list_owner_tracked)
 }
 
@@ -116,6 +219,28 @@ fn peers_filter(
         .and_then(handler::peers)
 }
 
+/// `GET /<urn>/readme?revision=<revision>`
+fn readme_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("readme"))
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_qs_opt::<ReadmeQuery>())
+        .and(warp::host::optional())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::readme)
+}
+
+/// `GET /templates`
+fn templates_filter() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("templates")
+        .and(path::end())
+        .and(warp::get())
+        .and_then(handler::templates)
+}
+
 /// `PUT /<urn>/track/<peer_id>`
 fn track_filter(
     ctx: context::Context,
@@ -142,6 +267,19 @@ fn untrack_filter(
         .and_then(handler::untrack)
 }
 
+/// `PATCH /<urn>`
+fn update_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path::end())
+        .and(warp::patch())
+        .and(http::with_context_unsealed(ctx.clone()))
+        .and(http::with_owner_guard(ctx))
+        .and(warp::body::json())
+        .and_then(handler::update)
+}
+
 /// `GET /user/<urn>`
 fn user_filter(
     ctx: context::Context,
@@ -178,18 +316,91 @@ mod handler {
     use link_crypto::PeerId;
     use link_identities::git::Urn;
 
-    use crate::{browser, context, error::Error, http, patch, project};
+    use crate::{blocklist, browser, context, error::Error, http, identity, patch, project};
+
+    /// Export a [`project::Project`] and the refs of the requested peers as a git bundle.
+    pub async fn bundle(
+        urn: Urn,
+        query: Option<super::BundleQuery>,
+        ctx: context::Unsealed,
+    ) -> Result<impl Reply, Rejection> {
+        let peers = query.map(|query| query.peers).unwrap_or_default();
+        let file_name = format!("{}.bundle", urn.encode_id());
+        let bundle = project::bundle::export(&ctx.peer, urn, peers).await?;
+
+        Ok(reply::with_header(
+            reply::with_header(bundle, "content-type", "application/octet-stream"),
+            "content-disposition",
+            format!("attachment; filename=\"{}\"", file_name),
+        ))
+    }
+
+    /// Import a repository from a git URL as a new [`project::Project`].
+    pub async fn import(
+        ctx: context::Unsealed,
+        owner: radicle_daemon::LocalIdentity,
+        input: project::import::Import,
+    ) -> Result<impl Reply, Rejection> {
+        let (urn, working_copy) = project::import::import(&ctx.peer, &owner, input).await?;
+        project::working_copy::register(
+            &ctx.rest.store,
+            &urn,
+            working_copy,
+            project::working_copy::Origin::Created,
+        )?;
+        let project = project::get(&ctx.peer, urn).await?;
+
+        Ok(reply::with_status(
+            reply::json(&project),
+            StatusCode::CREATED,
+        ))
+    }
+
+    /// Import a [`project::Project`] from a git bundle created by [`bundle`].
+    pub async fn import_bundle(
+        ctx: context::Unsealed,
+        bundle: warp::hyper::body::Bytes,
+    ) -> Result<impl Reply, Rejection> {
+        let urn = project::bundle::import(&ctx.peer, &ctx.rest.store, &bundle).await?;
+        let project = project::get(&ctx.peer, urn).await?;
+
+        Ok(reply::with_status(
+            reply::json(&project),
+            StatusCode::CREATED,
+        ))
+    }
 
     /// Checkout a [`project::Project`]'s source code.
     pub async fn checkout(
         urn: Urn,
         ctx: context::Unsealed,
-        super::CheckoutInput { path, peer_id }: super::CheckoutInput,
+        super::CheckoutInput {
+            path,
+            peer_id,
+            options,
+        }: super::CheckoutInput,
     ) -> Result<impl Reply, Rejection> {
         let peer_id = http::guard_self_peer_id(&ctx.peer, peer_id);
-        let path = radicle_daemon::state::checkout(ctx.peer.librad_peer(), urn, peer_id, path)
-            .await
-            .map_err(Error::from)?;
+        // The checkout adds remotes for these peers, which must not pull in blocked ones.
+        for peer_id in peer_id.iter().chain(&options.remotes) {
+            blocklist::guard(&ctx.rest.store, *peer_id)?;
+        }
+        let path = project::checkout::checkout(
+            &ctx.peer,
+            &ctx.rest.paths,
+            &ctx.rest.notifications,
+            urn.clone(),
+            peer_id,
+            path,
+            options,
+        )
+        .await?;
+        project::working_copy::register(
+            &ctx.rest.store,
+            &urn,
+            path.clone(),
+            project::working_copy::Origin::Checkout,
+        )?;
         Ok(reply::with_status(reply::json(&path), StatusCode::CREATED))
     }
 
@@ -197,12 +408,62 @@ mod handler {
     pub async fn create(
         ctx: context::Unsealed,
         owner: radicle_daemon::LocalIdentity,
-        input: radicle_daemon::project::Create,
+        input: super::CreateInput,
     ) -> Result<impl Reply, Rejection> {
-        let project = radicle_daemon::state::init_project(ctx.peer.librad_peer(), &owner, input)
-            .await
-            .map_err(Error::from)?;
+        let super::CreateInput {
+            project: mut input,
+            template,
+        } = input;
+        let scaffolded = template.is_some();
+        if let Some(template) = template {
+            let repo_path = match &input.repo {
+                radicle_daemon::project::Repo::New { path, name } => path.join(name),
+                radicle_daemon::project::Repo::Existing { .. } => {
+                    return Err(Rejection::from(Error::TemplateForExistingRepository))
+                },
+            };
+            project::template::scaffold(
+                &owner,
+                &template,
+                &input.description,
+                &input.default_branch,
+                &repo_path,
+            )?;
+            input.repo = radicle_daemon::project::Repo::Existing { path: repo_path };
+        }
+
+        let (working_copy, origin) = match &input.repo {
+            radicle_daemon::project::Repo::New { path, name } => {
+                (path.join(name), project::working_copy::Origin::Created)
+            },
+            radicle_daemon::project::Repo::Existing { path } if scaffolded => {
+                (path.clone(), project::working_copy::Origin::Created)
+            },
+            radicle_daemon::project::Repo::Existing { path } => {
+                (path.clone(), project::working_copy::Origin::Existing)
+            },
+        };
+        let project = match radicle_daemon::state::init_project(
+            ctx.peer.librad_peer(),
+            &owner,
+            input,
+        )
+        .await
+        {
+            Ok(project) => project,
+            Err(err) => {
+                // Don't leave the scaffolded repository behind, a retry would fail with
+                // `WorkingCopyExists`.
+                if scaffolded {
+                    tokio::fs::remove_dir_all(&working_copy)
+                        .await
+                        .map_err(Error::from)?;
+                }
+                return Err(Rejection::from(Error::from(err)));
+            },
+        };
         let urn = project.urn();
+        project::working_copy::register(&ctx.rest.store, &urn, working_copy, origin)?;
 
         let branch = radicle_daemon::state::get_branch(
             ctx.peer.librad_peer(),
@@ -212,11 +473,12 @@ mod handler {
         )
         .await
         .map_err(Error::from)?;
+        let contents = project::contents::detect(&ctx.peer, &branch)?;
         let stats = browser::using(&ctx.peer, branch, |browser| {
             browser.get_stats().map_err(radicle_source::Error::from)
         })
         .map_err(Error::from)?;
-        let project = project::Full::try_from((project, stats))?;
+        let project = project::Full::try_from((project, stats, contents))?;
 
         Ok(reply::with_status(
             reply::json(&project),
@@ -224,51 +486,130 @@ mod handler {
         ))
     }
 
+    /// Render the README of a project, with images pointing at the host the request was sent to.
+    pub async fn readme(
+        urn: Urn,
+        query: Option<super::ReadmeQuery>,
+        host: Option<warp::host::Authority>,
+        ctx: context::Unsealed,
+    ) -> Result<impl Reply, Rejection> {
+        let revision = http::guard_self_revision(&ctx.peer, query.and_then(|query| query.revision));
+        // HTTP/1.1 requires the host header, without it images stay relative to the origin.
+        let origin = host.map_or_else(String::new, |host| format!("http://{}", host));
+        let readme = project::readme::get(&ctx.peer, urn, revision, &origin).await?;
+
+        Ok(reply::json(&readme))
+    }
+
+    /// List the licenses, `.gitignore` presets and template directories new projects can start
+    /// with.
+    pub async fn templates() -> Result<impl Reply, Rejection> {
+        Ok(reply::json(&project::template::available()?))
+    }
+
+    /// Forget the [`project::Project`] for the given `urn` by removing it from the monorepo.
+    pub async fn delete(
+        urn: Urn,
+        query: Option<super::DeleteQuery>,
+        ctx: context::Unsealed,
+    ) -> Result<impl Reply, Rejection> {
+        let remove_working_copies = query.map_or(false, |query| query.remove_working_copies);
+        project::forget(&ctx.peer, &ctx.rest.store, urn, remove_working_copies).await?;
+
+        Ok(reply::with_status(reply(), StatusCode::NO_CONTENT))
+    }
+
+    /// Get a page of the activity feed of a project, newest first.
+    pub async fn activity(
+        urn: Urn,
+        query: Option<super::ActivityQuery>,
+        ctx: context::Unsealed,
+    ) -> Result<impl Reply, Rejection> {
+        let (before, limit) = query.map_or((None, None), |query| (query.before, query.limit));
+        let page = project::activity::page(
+            &ctx.rest.store,
+            &urn,
+            before,
+            limit.unwrap_or(project::activity::DEFAULT_LIMIT),
+        )?;
+
+        Ok(reply::json(&page))
+    }
+
     /// Get the [`project::Project`] for the given `id`.
     pub async fn get(urn: Urn, ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
         Ok(reply::json(&project::get(&ctx.peer, urn).await?))
     }
 
+    /// Walk the revisions of the identity document of the project with the given `id`.
+    pub async fn history(urn: Urn, ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        let revisions = identity::history::revisions(&ctx.peer, &urn).await?;
+
+        Ok(reply::json(&revisions))
+    }
+
     /// List all failed projects.
     pub async fn list_failed(ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
-        let projects = project::Projects::list(&ctx.peer).await?;
+        let projects = project::Projects::list(&ctx.peer, &ctx.rest.store).await?;
 
-        Ok(reply::json(&projects.failures))
+        Ok(with_fresh_header(
+            reply::json(&projects.failures),
+            projects.fresh,
+        ))
     }
 
     /// List all projects the current user has contributed to.
-    pub async fn list_owner_contributed(ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
-        let projects = project::Projects::list(&ctx.peer).await?;
-
-        Ok(reply::json(&projects.contributed))
+    pub async fn list_owner_contributed(
+        ctx: context::Unsealed,
+        filter: Option<project::annotation::Filter>,
+    ) -> Result<impl Reply, Rejection> {
+        let mut projects = project::Projects::list(&ctx.peer, &ctx.rest.store).await?;
+        if let Some(filter) = filter {
+            projects.retain_annotated(&ctx.rest.store, &filter)?;
+        }
+
+        Ok(with_fresh_header(
+            reply::json(&projects.contributed),
+            projects.fresh,
+        ))
     }
 
     /// List all projects tracked by the current user.
-    pub async fn list_owner_tracked(ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
-        let projects = project::Projects::list(&ctx.peer).await?.tracked;
+    pub async fn list_owner_tracked(
+        ctx: context::Unsealed,
+        filter: Option<project::annotation::Filter>,
+    ) -> Result<impl Reply, Rejection> {
+        let mut projects = project::Projects::list(&ctx.peer, &ctx.rest.store).await?;
+        if let Some(filter) = filter {
+            projects.retain_annotated(&ctx.rest.store, &filter)?;
+        }
+
+        Ok(with_fresh_header(
+            reply::json(&projects.tracked),
+            projects.fresh,
+        ))
+    }
 
-        Ok(reply::json(&projects))
+    /// Attach the [`super::FRESH_HEADER`] to a project listing to tell clients whether the
+    /// project stats are up to date.
+    fn with_fresh_header(reply: impl Reply, fresh: bool) -> impl Reply {
+        reply::with_header(reply, super::FRESH_HEADER, fresh.to_string())
     }
 
-    /// This lists all the projects for a given `user`. This `user` should not be your particular
-    /// `user` (i.e. the "default user"), but rather should be another user that you are tracking.
+    /// This lists all the projects for a given `user` split into tracked and contributed
+    /// projects. This `user` should not be your particular `user` (i.e. the "default user"), but
+    /// rather should be another user that you are tracking.
     ///
     /// See [`project::list_for_user`] for more information.
     pub async fn list_user(user_id: Urn, ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
-        let projects = project::list_for_user(&ctx.peer, &user_id).await?;
+        let projects = project::list_for_user(&ctx.peer, &ctx.rest.store, &user_id).await?;
 
-        Ok(reply::json(&projects))
+        Ok(with_fresh_header(reply::json(&projects), projects.fresh))
     }
 
     /// List the remote peers for a project.
     pub async fn peers(ctx: context::Unsealed, urn: Urn) -> Result<impl Reply, Rejection> {
-        let peers: Vec<project::Peer> =
-            radicle_daemon::state::list_project_peers(ctx.peer.librad_peer(), urn)
-                .await
-                .map_err(Error::from)?
-                .into_iter()
-                .map(project::Peer::from)
-                .collect::<Vec<_>>();
+        let peers = project::peers(&ctx.peer, &ctx.rest.store, urn).await?;
 
         Ok(reply::json(&peers))
     }
@@ -279,6 +620,8 @@ mod handler {
         peer_id: PeerId,
         ctx: context::Unsealed,
     ) -> Result<impl Reply, Rejection> {
+        blocklist::guard(&ctx.rest.store, peer_id)?;
+        project::tracking::guard(&ctx.peer, &ctx.rest.store, &urn, peer_id).await?;
         radicle_daemon::state::track(ctx.peer.librad_peer(), urn, peer_id)
             .await
             .map_err(Error::from)?;
@@ -297,12 +640,24 @@ mod handler {
         Ok(reply::json(&true))
     }
 
+    /// Update the metadata of a [`project::Project`].
+    pub async fn update(
+        urn: Urn,
+        ctx: context::Unsealed,
+        owner: radicle_daemon::LocalIdentity,
+        input: project::MetadataUpdate,
+    ) -> Result<impl Reply, Rejection> {
+        let project = project::update(&ctx.peer, &owner, urn, input).await?;
+
+        Ok(reply::json(&project))
+    }
+
     /// Get the list of patches for a project
     pub async fn patches(
         project_urn: Urn,
         ctx: context::Unsealed,
     ) -> Result<impl Reply, Rejection> {
-        let patches = patch::list(&ctx.peer, project_urn)
+        let patches = patch::list(&ctx.peer, &ctx.rest.store, project_urn)
             .await
             .map_err(Error::from)?;
 
@@ -310,15 +665,52 @@ mod handler {
     }
 }
 
+/// Query parameters for [`handler::activity`].
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityQuery {
+    /// Only events older than the event with this ID, as given by
+    /// [`crate::project::activity::Page::next`].
+    before: Option<u64>,
+    /// Maximum number of events to return.
+    limit: Option<usize>,
+}
+
+/// Query parameters for [`handler::readme`].
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadmeQuery {
+    /// Revision to render the README at. Defaults to the default branch.
+    revision: Option<radicle_source::Revision<PeerId>>,
+}
+
+/// Query parameters for [`handler::bundle`].
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleQuery {
+    /// Remote peers whose refs are included in the bundle in addition to the local peer's.
+    #[serde(default)]
+    peers: Vec<PeerId>,
+}
+
+/// Query parameters for [`handler::delete`].
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteQuery {
+    /// Delete the working copies the proxy checked out from disk as well.
+    #[serde(default)]
+    remove_working_copies: bool,
+}
+
 /// Bundled input data for project creation.
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateInput {
-    /// Location on the filesystem of the project, an empty directory means we set up a fresh git
-    /// repo at the path before initialising the project.
-    path: PathBuf,
-    /// User provided metadata for the project.
-    metadata: MetadataInput,
+    /// Location, description and default branch of the project.
+    #[serde(flatten)]
+    project: radicle_daemon::project::Create,
+    /// Files the initial commit of a new repository starts with.
+    template: Option<crate::project::template::Template>,
 }
 
 /// Bundled input data for project checkout.
@@ -329,23 +721,15 @@ pub struct CheckoutInput {
     path: PathBuf,
     /// Which peer are we checking out from. If it's `None`, we're checking out our own project.
     peer_id: Option<PeerId>,
-}
-
-/// User provided metadata for project manipulation.
-#[derive(Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct MetadataInput {
-    /// Name of the project.
-    name: String,
-    /// Long form outline.
-    description: String,
-    /// Configured default branch.
-    default_branch: String,
+    /// Branch, remotes and history depth of the working copy.
+    #[serde(flatten)]
+    options: crate::project::checkout::Options,
 }
 
 #[allow(clippy::panic, clippy::unwrap_used)]
 #[cfg(test)]
 mod test {
+    use futures::prelude::*;
     use pretty_assertions::assert_eq;
     use serde_json::{json, Value};
     use warp::{http::StatusCode, test::request};
@@ -396,6 +780,7 @@ mod test {
         let input = super::CheckoutInput {
             path: dir.path().to_path_buf(),
             peer_id: None,
+            options: project::checkout::Options::default(),
         };
         let res = request()
             .method("POST")
@@ -450,6 +835,135 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn checkout_shallow() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let repos_dir = tempfile::tempdir_in(tmp_dir.path())?;
+        let dir = tempfile::tempdir_in(repos_dir.path())?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let owner = radicle_daemon::state::init_owner(
+            ctx.peer.librad_peer(),
+            Person {
+                name: "cloudhead".into(),
+            },
+        )
+        .await?;
+        let urn = crate::control::replicate_platinum(
+            &ctx.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+
+        let notifications = ctx.local_notifications();
+        let res = request()
+            .method("POST")
+            .path(&format!("/{}/checkout", urn))
+            .json(&json!({
+                "path": dir.path(),
+                "branch": "dev",
+                "depth": 1,
+            }))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::CREATED, |_| {});
+
+        let repo = git2::Repository::open(dir.path().join("git-platinum"))?;
+        assert!(repo.is_shallow());
+        assert_eq!(repo.head()?.shorthand(), Some("dev"));
+
+        let stages = notifications
+            .filter_map(|notification| {
+                future::ready(match notification {
+                    crate::notification::Notification::CheckoutProgress { progress, .. } => {
+                        Some(serde_json::to_value(progress).unwrap()["type"].clone())
+                    },
+                    _ => None,
+                })
+            })
+            .take(3)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(stages, vec!["cloning", "checkingOut", "done"]);
+
+        let res = request()
+            .method("POST")
+            .path(&format!("/{}/checkout", urn))
+            .json(&json!({
+                "path": dir.path(),
+                "branch": "--orphan",
+                "depth": 1,
+            }))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::BAD_REQUEST, |have| {
+            assert_eq!(have["variant"], "INVALID_BRANCH_NAME");
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn import_from_url() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let repos_dir = tempfile::tempdir_in(tmp_dir.path())?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        radicle_daemon::state::init_owner(
+            ctx.peer.librad_peer(),
+            Person {
+                name: "cloudhead".into(),
+            },
+        )
+        .await?;
+
+        let url = format!(
+            "file://{}/../../fixtures/git-platinum",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        let res = request()
+            .method("POST")
+            .path("/import")
+            .json(&json!({
+                "url": url,
+                "path": repos_dir.path(),
+                "name": "platinum",
+                "defaultBranch": "master",
+            }))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::CREATED, |have| {
+            assert_eq!(have["metadata"]["name"], "platinum");
+            assert_eq!(have["metadata"]["defaultBranch"], "master");
+        });
+
+        let working_copy = repos_dir.path().join("platinum");
+        let repo = git2::Repository::open(&working_copy)?;
+        assert!(repo.find_branch("dev", git2::BranchType::Local).is_ok());
+
+        let res = request()
+            .method("POST")
+            .path("/import")
+            .json(&json!({
+                "url": url,
+                "path": repos_dir.path(),
+                "name": "platinum",
+            }))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::CONFLICT, |have| {
+            assert_eq!(have["variant"], "WORKING_COPY_EXISTS");
+        });
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn create_new() -> Result<(), Box<dyn std::error::Error>> {
         tracing_subscriber::fmt().with_test_writer();
@@ -485,7 +999,7 @@ mod test {
             .reply(&api)
             .await;
 
-        let projects = project::Projects::list(&ctx.peer).await?;
+        let projects = project::Projects::list(&ctx.peer, &ctx.rest.store).await?;
         let meta = projects.into_iter().next().unwrap();
         let maintainer = meta.metadata.maintainers.iter().next().unwrap();
 
@@ -505,6 +1019,8 @@ mod test {
                 "commits": 1,
                 "contributors": 1,
             },
+            "license": null,
+            "languages": [],
         });
 
         assert_eq!(res.status(), StatusCode::CREATED);
@@ -513,6 +1029,137 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn create_from_template() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let repos_dir = tempfile::tempdir_in(tmp_dir.path())?;
+        let dir = tempfile::tempdir_in(repos_dir.path())?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        {
+            let metadata = identity::Metadata {
+                handle: "cloudhead".to_string(),
+                ethereum: None,
+            };
+            let id = identity::create(ctx.peer.librad_peer(), metadata).await?;
+            session::initialize(&ctx.rest.store, id, &ctx.rest.default_seeds)?;
+        };
+
+        let res = request()
+            .method("POST")
+            .path("/")
+            .json(&json!({
+                "repo": {
+                    "type": "new",
+                    "path": dir.path(),
+                    "name": "Upstream",
+                },
+                "description": "Desktop client for radicle.",
+                "defaultBranch": "master",
+                "template": {
+                    "license": "MIT",
+                    "readme": true,
+                    "gitignore": "rust",
+                },
+            }))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::CREATED, |have| {
+            assert_eq!(have["metadata"]["name"], "Upstream");
+            assert_eq!(have["stats"]["commits"], 1);
+        });
+
+        let repo = git2::Repository::open(dir.path().join("Upstream"))?;
+        let tree = repo.head()?.peel_to_tree()?;
+        let mut files = tree
+            .iter()
+            .filter_map(|entry| entry.name().map(ToString::to_string))
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, vec![".gitignore", "LICENSE", "README.md"]);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("Upstream/README.md"))?,
+            "# Upstream\n\nDesktop client for radicle.\n"
+        );
+
+        let res = request()
+            .method("POST")
+            .path("/")
+            .json(&json!({
+                "repo": {
+                    "type": "new",
+                    "path": dir.path(),
+                    "name": "Other",
+                },
+                "description": "",
+                "defaultBranch": "master",
+                "template": { "license": "WTFPL" },
+            }))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::BAD_REQUEST, |have| {
+            assert_eq!(have["variant"], "UNKNOWN_LICENSE");
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn readme() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let repos_dir = tempfile::tempdir_in(tmp_dir.path())?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let owner = radicle_daemon::state::init_owner(
+            ctx.peer.librad_peer(),
+            Person {
+                name: "cloudhead".into(),
+            },
+        )
+        .await?;
+        let repo_path = repos_dir.path().join("Upstream");
+        project::template::scaffold(
+            &owner,
+            &project::template::Template {
+                readme: true,
+                ..project::template::Template::default()
+            },
+            "Desktop client for radicle.",
+            &crate::control::default_branch(),
+            &repo_path,
+        )?;
+        let urn = radicle_daemon::state::init_project(
+            ctx.peer.librad_peer(),
+            &owner,
+            radicle_daemon::project::Create {
+                repo: radicle_daemon::project::Repo::Existing { path: repo_path },
+                description: "Desktop client for radicle.".into(),
+                default_branch: crate::control::default_branch(),
+            },
+        )
+        .await?
+        .urn();
+
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}/readme", urn))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(
+                have,
+                json!({
+                    "path": "README.md",
+                    "html": "<h1>Upstream</h1>\n<p>Desktop client for radicle.</p>\n",
+                })
+            );
+        });
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn create_existing() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir()?;
@@ -573,7 +1220,7 @@ mod test {
             .reply(&api)
             .await;
 
-        let projects = project::Projects::list(&ctx.peer).await?;
+        let projects = project::Projects::list(&ctx.peer, &ctx.rest.store).await?;
         let meta = projects.into_iter().next().unwrap();
         let maintainer = meta.metadata.maintainers.iter().next().unwrap();
 
@@ -593,10 +1240,99 @@ mod test {
                 "commits": 15,
                 "contributors": 4,
             },
+            "license": null,
+            "languages": have["languages"],
         });
 
         assert_eq!(res.status(), StatusCode::CREATED);
         assert_eq!(have, want);
+        assert!(have["languages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|language| language["name"] == "Rust"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn activity() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let owner = radicle_daemon::state::init_owner(
+            ctx.peer.librad_peer(),
+            Person {
+                name: "cloudhead".into(),
+            },
+        )
+        .await?;
+        let urn = crate::control::replicate_platinum(
+            &ctx.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}/activity?limit=10", urn))
+            .reply(&api)
+            .await;
+
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have, json!({ "events": [], "next": null }));
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn history() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let owner = radicle_daemon::state::init_owner(
+            ctx.peer.librad_peer(),
+            Person {
+                name: "cloudhead".into(),
+            },
+        )
+        .await?;
+        let urn = crate::control::replicate_platinum(
+            &ctx.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+        let peer_id = ctx.peer.librad_peer().peer_id();
+
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}/history", urn))
+            .reply(&api)
+            .await;
+
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            let revisions = have.as_array().expect("revisions are not an array");
+            assert_eq!(revisions.len(), 1);
+            assert_eq!(
+                revisions[0]["delegations"],
+                json!([{ "type": "person", "urn": owner.urn(), "peerIds": [peer_id] }])
+            );
+            assert_eq!(
+                revisions[0]["verification"],
+                json!({ "signatures": true, "quorum": true, "parentQuorum": null })
+            );
+        });
 
         Ok(())
     }
@@ -641,6 +1377,161 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn update() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let session = session::initialize_test(&ctx, "cloudhead").await;
+        let owner = radicle_daemon::state::get_local(ctx.peer.librad_peer(), session.identity.urn)
+            .await?
+            .unwrap();
+        let urn = crate::control::replicate_platinum(
+            &ctx.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+
+        let res = request()
+            .method("PATCH")
+            .path(&format!("/{}", urn))
+            .json(&project::MetadataUpdate {
+                description: Some("better fixture data".to_string()),
+                default_branch: Some("dev".to_string()),
+                ..project::MetadataUpdate::default()
+            })
+            .reply(&api)
+            .await;
+
+        let project = project::get(&ctx.peer, urn.clone()).await?;
+        assert_eq!(project.metadata.description, "better fixture data");
+        assert_eq!(project.metadata.default_branch, "dev");
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have, json!(project));
+        });
+
+        let res = request()
+            .method("PATCH")
+            .path(&format!("/{}", urn))
+            .json(&project::MetadataUpdate {
+                default_branch: Some("does-not-exist".to_string()),
+                ..project::MetadataUpdate::default()
+            })
+            .reply(&api)
+            .await;
+
+        http::test::assert_response(&res, StatusCode::BAD_REQUEST, |have| {
+            assert_eq!(have["variant"], "BRANCH_NOT_FOUND");
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn delete() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let owner = radicle_daemon::state::init_owner(
+            ctx.peer.librad_peer(),
+            Person {
+                name: "cloudhead".into(),
+            },
+        )
+        .await?;
+        crate::control::setup_fixtures(&ctx.peer, &owner).await?;
+        let projects = project::Projects::list(&ctx.peer, &ctx.rest.store).await?;
+        let urn = projects
+            .contributed
+            .first()
+            .expect("no projects setup")
+            .urn
+            .clone();
+
+        let res = request()
+            .method("DELETE")
+            .path(&format!("/{}", urn))
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let projects = project::Projects::list(&ctx.peer, &ctx.rest.store).await?;
+        assert!(projects.into_iter().all(|project| project.urn != urn));
+
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}", urn))
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn delete_working_copies() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let repos_dir = tempfile::tempdir_in(tmp_dir.path())?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let owner = radicle_daemon::state::init_owner(
+            ctx.peer.librad_peer(),
+            Person {
+                name: "cloudhead".into(),
+            },
+        )
+        .await?;
+        let urn = crate::control::replicate_platinum(
+            &ctx.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+
+        let checkout = radicle_daemon::state::checkout(
+            ctx.peer.librad_peer(),
+            urn.clone(),
+            None,
+            repos_dir.path().to_path_buf(),
+        )
+        .await?;
+        let existing = repos_dir.path().join("existing");
+        git2::Repository::init(&existing)?;
+        let unrelated = repos_dir.path().join("unrelated");
+        git2::Repository::init(&unrelated)?;
+        for (path, origin) in [
+            (&checkout, project::working_copy::Origin::Checkout),
+            (&existing, project::working_copy::Origin::Existing),
+            (&unrelated, project::working_copy::Origin::Checkout),
+        ] {
+            project::working_copy::register(&ctx.rest.store, &urn, path.clone(), origin)?;
+        }
+
+        let res = request()
+            .method("DELETE")
+            .path(&format!("/{}?removeWorkingCopies=true", urn))
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        assert!(!checkout.exists());
+        assert!(existing.exists());
+        assert!(unrelated.exists());
+        assert!(project::working_copy::paths(&ctx.rest.store, &urn)?.is_empty());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn list_contributed() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir()?;
@@ -663,7 +1554,7 @@ mod test {
             .reply(&api)
             .await;
 
-        let projects = project::Projects::list(&ctx.peer).await?;
+        let projects = project::Projects::list(&ctx.peer, &ctx.rest.store).await?;
 
         http::test::assert_response(&res, StatusCode::OK, |have| {
             assert_eq!(have, json!(projects.contributed));
@@ -686,7 +1577,7 @@ mod test {
         )
         .await?;
         crate::control::setup_fixtures(&ctx.peer, &owner).await?;
-        let projects = project::Projects::list(&ctx.peer).await?;
+        let projects = project::Projects::list(&ctx.peer, &ctx.rest.store).await?;
         let project = projects.contributed.first().expect("no projects setup");
 
         let res = request()
@@ -720,7 +1611,7 @@ mod test {
         )
         .await?;
         crate::control::setup_fixtures(&ctx.peer, &owner).await?;
-        let projects = project::Projects::list(&ctx.peer).await?;
+        let projects = project::Projects::list(&ctx.peer, &ctx.rest.store).await?;
         let project = projects.contributed.first().expect("no projects setup");
 
         let res = request()
@@ -754,7 +1645,7 @@ mod test {
         )
         .await?;
         crate::control::setup_fixtures(&ctx.peer, &owner).await?;
-        let projects = project::Projects::list(&ctx.peer).await?;
+        let projects = project::Projects::list(&ctx.peer, &ctx.rest.store).await?;
         let project = projects.contributed.first().expect("no projects setup");
 
         let res = request()
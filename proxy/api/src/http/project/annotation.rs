@@ -0,0 +1,207 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Endpoints for the private favourites, labels and notes of projects.
+
+use warp::{filters::BoxedFilter, path, Filter, Rejection, Reply};
+
+use link_identities::git::Urn;
+
+use crate::{context, http};
+
+/// Combination of all routes.
+pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
+    list_filter(ctx.clone())
+        .or(get_filter(ctx.clone()))
+        .or(set_filter(ctx.clone()))
+        .or(delete_filter(ctx))
+        .boxed()
+}
+
+/// `GET /annotations`
+fn list_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("annotations")
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::list)
+}
+
+/// `GET /<urn>/annotation`
+fn get_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("annotation"))
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::get)
+}
+
+/// `PUT /<urn>/annotation`
+fn set_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("annotation"))
+        .and(path::end())
+        .and(warp::put())
+        .and(http::with_context_unsealed(ctx))
+        .and(warp::body::json())
+        .and_then(handler::set)
+}
+
+/// `DELETE /<urn>/annotation`
+fn delete_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("annotation"))
+        .and(path::end())
+        .and(warp::delete())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::delete)
+}
+
+/// Annotation handlers for conversion between core domain and http request fullfilment.
+mod handler {
+    use warp::{http::StatusCode, reply, Rejection, Reply};
+
+    use link_identities::git::Urn;
+
+    use crate::{context, error, project::annotation};
+
+    /// List the annotations of all annotated projects by URN.
+    pub async fn list(ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        let annotations = annotation::list(&ctx.rest.store)?;
+
+        Ok(reply::json(&annotations))
+    }
+
+    /// Get the annotation of a project.
+    pub async fn get(urn: Urn, ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        let annotation = annotation::get(&ctx.rest.store, &urn)?;
+
+        Ok(reply::json(&annotation))
+    }
+
+    /// Replace the annotation of a project.
+    pub async fn set(
+        urn: Urn,
+        ctx: context::Unsealed,
+        annotation: annotation::Annotation,
+    ) -> Result<impl Reply, Rejection> {
+        radicle_daemon::state::get_project(ctx.peer.librad_peer(), urn.clone())
+            .await
+            .map_err(error::Error::from)?
+            .ok_or(error::Error::ProjectNotFound)?;
+        let annotation = annotation::set(&ctx.rest.store, &urn, annotation)?;
+
+        Ok(reply::json(&annotation))
+    }
+
+    /// Remove the annotation of a project.
+    pub async fn delete(urn: Urn, ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        annotation::remove(&ctx.rest.store, &urn)?;
+
+        Ok(reply::with_status(reply(), StatusCode::NO_CONTENT))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use warp::{http::StatusCode, test::request};
+
+    use link_identities::payload::Person;
+
+    use crate::{context, http};
+
+    #[tokio::test]
+    async fn annotate() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let owner = radicle_daemon::state::init_owner(
+            ctx.peer.librad_peer(),
+            Person {
+                name: "cloudhead".into(),
+            },
+        )
+        .await?;
+        let urn = crate::control::replicate_platinum(
+            &ctx.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}/annotation", urn))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(
+                have,
+                json!({ "favourite": false, "labels": [], "note": null })
+            );
+        });
+
+        let res = request()
+            .method("PUT")
+            .path(&format!("/{}/annotation", urn))
+            .json(&json!({ "favourite": true, "labels": ["work ", "fixtures"] }))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(
+                have,
+                json!({ "favourite": true, "labels": ["fixtures", "work"], "note": null })
+            );
+        });
+
+        let res = request()
+            .method("GET")
+            .path("/annotations")
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(
+                have,
+                json!({
+                    urn.to_string(): { "favourite": true, "labels": ["fixtures", "work"], "note": null }
+                })
+            );
+        });
+
+        let res = request()
+            .method("DELETE")
+            .path(&format!("/{}/annotation", urn))
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let res = request()
+            .method("GET")
+            .path("/annotations")
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have, json!({}));
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,311 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Endpoints for proposing and signing changes to project maintainers.
+
+use warp::{filters::BoxedFilter, path, Filter, Rejection, Reply};
+
+use link_crypto::PeerId;
+use link_identities::git::Urn;
+
+use crate::{context, http};
+
+/// Combination of all routes.
+pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
+    propose_filter(ctx.clone())
+        .or(proposals_filter(ctx.clone()))
+        .or(sign_filter(ctx))
+        .boxed()
+}
+
+/// `POST /<urn>/maintainers`
+fn propose_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("maintainers"))
+        .and(path::end())
+        .and(warp::post())
+        .and(http::with_context_unsealed(ctx.clone()))
+        .and(http::with_owner_guard(ctx))
+        .and(warp::body::json())
+        .and_then(handler::propose)
+}
+
+/// `GET /<urn>/maintainers/proposals`
+fn proposals_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("maintainers"))
+        .and(path("proposals"))
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::proposals)
+}
+
+/// `PUT /<urn>/maintainers/proposals/<peer_id>`
+fn sign_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("maintainers"))
+        .and(path("proposals"))
+        .and(path::param::<PeerId>())
+        .and(path::end())
+        .and(warp::put())
+        .and(http::with_context_unsealed(ctx.clone()))
+        .and(http::with_owner_guard(ctx))
+        .and_then(handler::sign)
+}
+
+/// Maintainer handlers for conversion between core domain and http request fullfilment.
+mod handler {
+    use warp::{http::StatusCode, reply, Rejection, Reply};
+
+    use link_crypto::PeerId;
+    use link_identities::git::Urn;
+
+    use crate::{context, project::maintainer};
+
+    /// Propose a change to the maintainers of a project.
+    pub async fn propose(
+        urn: Urn,
+        ctx: context::Unsealed,
+        owner: radicle_daemon::LocalIdentity,
+        change: maintainer::Change,
+    ) -> Result<impl Reply, Rejection> {
+        let proposal = maintainer::propose(&ctx.peer, &owner, urn, change).await?;
+
+        Ok(reply::with_status(
+            reply::json(&proposal),
+            StatusCode::CREATED,
+        ))
+    }
+
+    /// List the pending changes to the identity document of a project.
+    pub async fn proposals(urn: Urn, ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        let proposals = maintainer::proposals(&ctx.peer, urn).await?;
+
+        Ok(reply::json(&proposals))
+    }
+
+    /// Sign the revision of the identity document published by `delegate`.
+    pub async fn sign(
+        urn: Urn,
+        delegate: PeerId,
+        ctx: context::Unsealed,
+        owner: radicle_daemon::LocalIdentity,
+    ) -> Result<impl Reply, Rejection> {
+        let proposals = maintainer::sign(&ctx.peer, &owner, urn, delegate).await?;
+
+        Ok(reply::json(&proposals))
+    }
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use warp::{http::StatusCode, test::request};
+
+    use link_identities::git::Urn;
+
+    use crate::{context, http, project, session};
+
+    #[tokio::test]
+    async fn propose() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let session = session::initialize_test(&ctx, "cloudhead").await;
+        let owner =
+            radicle_daemon::state::get_local(ctx.peer.librad_peer(), session.identity.urn.clone())
+                .await?
+                .unwrap();
+        let urn = crate::control::replicate_platinum(
+            &ctx.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+
+        let res = request()
+            .method("POST")
+            .path(&format!("/{}/maintainers", urn))
+            .json(&project::maintainer::Change::Remove {
+                urn: session.identity.urn.clone(),
+            })
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::BAD_REQUEST, |have| {
+            assert_eq!(have["variant"], "INVALID_DELEGATIONS");
+        });
+
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}/maintainers/proposals", urn))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have, json!([]));
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn add_and_sign() -> Result<(), Box<dyn std::error::Error>> {
+        let alice_dir = tempfile::tempdir()?;
+        let (alice, alice_run) = context::Unsealed::tmp(&alice_dir)?;
+        let alice_handle = tokio::spawn(alice_run);
+        let bob_dir = tempfile::tempdir()?;
+        let (bob, bob_run) = context::Unsealed::tmp(&bob_dir)?;
+        let bob_handle = tokio::spawn(bob_run);
+        let carol_dir = tempfile::tempdir()?;
+        let (carol, carol_run) = context::Unsealed::tmp(&carol_dir)?;
+        let carol_handle = tokio::spawn(carol_run);
+        let alice_api = super::filters(alice.clone().into());
+        let bob_api = super::filters(bob.clone().into());
+
+        let alice_session = session::initialize_test(&alice, "alice").await;
+        let bob_session = session::initialize_test(&bob, "bob").await;
+        let carol_session = session::initialize_test(&carol, "carol").await;
+        let owner = radicle_daemon::state::get_local(
+            alice.peer.librad_peer(),
+            alice_session.identity.urn.clone(),
+        )
+        .await?
+        .unwrap();
+        let urn = crate::control::replicate_platinum(
+            &alice.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+        replicate(&bob, &alice, bob_session.identity.urn.clone()).await?;
+        replicate(&carol, &alice, carol_session.identity.urn.clone()).await?;
+
+        // Alice is the only maintainer, so her signature alone accepts the change.
+        let res = request()
+            .method("POST")
+            .path(&format!("/{}/maintainers", urn))
+            .json(&project::maintainer::Change::Add {
+                urn: bob_session.identity.urn.clone(),
+            })
+            .reply(&alice_api)
+            .await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(
+            maintainers(&alice, &urn).await?,
+            vec![
+                alice_session.identity.urn.clone(),
+                bob_session.identity.urn.clone()
+            ]
+            .into_iter()
+            .collect::<HashSet<_>>()
+        );
+
+        // With Bob as a second maintainer, adding Carol needs his signature as well.
+        let res = request()
+            .method("POST")
+            .path(&format!("/{}/maintainers", urn))
+            .json(&project::maintainer::Change::Add {
+                urn: carol_session.identity.urn.clone(),
+            })
+            .reply(&alice_api)
+            .await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}/maintainers/proposals", urn))
+            .reply(&alice_api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            let proposals = have.as_array().unwrap();
+            assert_eq!(proposals.len(), 1);
+            assert_eq!(
+                proposals[0]["proposedBy"],
+                json!([alice.peer.librad_peer().peer_id()])
+            );
+            assert_eq!(proposals[0]["maintainers"].as_array().unwrap().len(), 3);
+        });
+        assert_eq!(maintainers(&alice, &urn).await?.len(), 2);
+
+        replicate(&alice, &bob, urn.clone()).await?;
+        let res = request()
+            .method("PUT")
+            .path(&format!(
+                "/{}/maintainers/proposals/{}",
+                urn,
+                alice.peer.librad_peer().peer_id()
+            ))
+            .reply(&bob_api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have, json!([]));
+        });
+        assert_eq!(
+            maintainers(&bob, &urn).await?,
+            vec![
+                alice_session.identity.urn,
+                bob_session.identity.urn,
+                carol_session.identity.urn
+            ]
+            .into_iter()
+            .collect::<HashSet<_>>()
+        );
+
+        alice_handle.abort();
+        bob_handle.abort();
+        carol_handle.abort();
+
+        Ok(())
+    }
+
+    /// Track `from` and replicate `urn` from it into `to`.
+    async fn replicate(
+        from: &context::Unsealed,
+        to: &context::Unsealed,
+        urn: Urn,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let peer_id = from.peer.librad_peer().peer_id();
+        let addrs = from.peer.clone().daemon_control().listen_addrs().await;
+        radicle_daemon::state::track(to.peer.librad_peer(), urn.clone(), peer_id).await?;
+        to.peer
+            .librad_peer()
+            .replicate((peer_id, addrs), urn, None)
+            .await?;
+        Ok(())
+    }
+
+    /// The maintainers of the verified revision of project `urn` in the storage of `ctx`.
+    async fn maintainers(
+        ctx: &context::Unsealed,
+        urn: &Urn,
+    ) -> Result<HashSet<Urn>, Box<dyn std::error::Error>> {
+        let project = radicle_daemon::state::get_project(ctx.peer.librad_peer(), urn.clone())
+            .await?
+            .unwrap();
+        Ok(project
+            .delegations()
+            .iter()
+            .filter_map(|delegation| delegation.as_ref().right().map(|person| person.urn()))
+            .collect())
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Endpoints for diagnosing and repairing failed projects.
+
+use warp::{filters::BoxedFilter, path, Filter, Rejection, Reply};
+
+use link_identities::git::Urn;
+
+use crate::{context, http};
+
+/// Combination of all routes.
+pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
+    diagnose_filter(ctx.clone()).or(repair_filter(ctx)).boxed()
+}
+
+/// `GET /<urn>/diagnosis`
+fn diagnose_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("diagnosis"))
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::diagnose)
+}
+
+/// `POST /<urn>/repair`
+fn repair_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("repair"))
+        .and(path::end())
+        .and(warp::post())
+        .and(http::with_context_unsealed(ctx))
+        .and(warp::body::json())
+        .and_then(handler::repair)
+}
+
+/// Repair handlers for conversion between core domain and http request fullfilment.
+mod handler {
+    use std::time::SystemTime;
+
+    use warp::{http::StatusCode, reply, Rejection, Reply};
+
+    use link_identities::git::Urn;
+
+    use crate::{context, project::repair};
+
+    /// Find out why a project fails.
+    pub async fn diagnose(urn: Urn, ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        let diagnosis = repair::diagnose(&ctx.peer, urn).await?;
+
+        Ok(reply::json(&diagnosis))
+    }
+
+    /// Run a repair `action` on a project.
+    ///
+    /// Requesting the project from the network responds with `202` and the waiting room request.
+    /// All other actions respond with the diagnosis of the project after the repair.
+    pub async fn repair(
+        urn: Urn,
+        mut ctx: context::Unsealed,
+        action: repair::Action,
+    ) -> Result<impl Reply, Rejection> {
+        match action {
+            repair::Action::Request => {
+                let request = ctx
+                    .peer
+                    .daemon_control()
+                    .request_project(&urn, SystemTime::now())
+                    .await;
+
+                return Ok(reply::with_status(
+                    reply::json(&request),
+                    StatusCode::ACCEPTED,
+                ));
+            },
+            repair::Action::Fetch { peer_id, addrs } => {
+                repair::fetch(&ctx.peer, &ctx.rest.store, urn.clone(), peer_id, addrs).await?;
+            },
+            repair::Action::ResetDefaultBranch { peer_id } => {
+                repair::reset_default_branch(&ctx.peer, urn.clone(), peer_id).await?;
+            },
+        }
+
+        let diagnosis = repair::diagnose(&ctx.peer, urn).await?;
+
+        Ok(reply::with_status(reply::json(&diagnosis), StatusCode::OK))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use warp::{http::StatusCode, test::request};
+
+    use link_identities::payload::Person;
+    use radicle_source::surf::vcs::git::git2;
+
+    use crate::{context, http, project};
+
+    #[tokio::test]
+    async fn diagnose() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let owner = radicle_daemon::state::init_owner(
+            ctx.peer.librad_peer(),
+            Person {
+                name: "cloudhead".into(),
+            },
+        )
+        .await?;
+        let urn = crate::control::replicate_platinum(
+            &ctx.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}/diagnosis", urn))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have["problems"], json!([]));
+        });
+
+        let repo = git2::Repository::open(radicle_daemon::state::monorepo(ctx.peer.librad_peer()))?;
+        repo.find_reference(&format!(
+            "refs/namespaces/{}/refs/heads/{}",
+            urn.encode_id(),
+            crate::control::default_branch()
+        ))?
+        .delete()?;
+
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}/diagnosis", urn))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(
+                have["problems"],
+                json!([{
+                    "type": "missingRef",
+                    "reference": format!("refs/heads/{}", crate::control::default_branch()),
+                }])
+            );
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reset_to_non_delegate() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let owner = radicle_daemon::state::init_owner(
+            ctx.peer.librad_peer(),
+            Person {
+                name: "cloudhead".into(),
+            },
+        )
+        .await?;
+        let urn = crate::control::replicate_platinum(
+            &ctx.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+
+        let stranger = link_crypto::PeerId::from(link_crypto::SecretKey::new());
+        let res = request()
+            .method("POST")
+            .path(&format!("/{}/repair", urn))
+            .json(&project::repair::Action::ResetDefaultBranch { peer_id: stranger })
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::BAD_REQUEST, |have| {
+            assert_eq!(have["variant"], "NOT_A_DELEGATE_PEER");
+        });
+
+        Ok(())
+    }
+}
@@ -10,12 +10,13 @@ use warp::{filters::BoxedFilter, path, Filter, Rejection, Reply};
 
 use link_identities::git::Urn;
 
-use crate::{context, http};
+use crate::{context, http, project};
 
 /// Combination of all routes.
 pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
     cancel_filter(ctx.clone())
         .or(create_filter(ctx.clone()))
+        .or(history_filter(ctx.clone()))
         .or(list_filter(ctx))
         .boxed()
 }
@@ -31,7 +32,7 @@ fn cancel_filter(
         .and_then(handler::cancel)
 }
 
-/// `PUT /<urn>`
+/// `PUT /<urn>?timeout=<seconds>&maxQueries=<n>&preferredPeers[0]=<peer_id>`
 fn create_filter(
     ctx: context::Context,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
@@ -39,9 +40,22 @@ fn create_filter(
         .and(path::end())
         .and(warp::put())
         .and(http::with_context_unsealed(ctx))
+        .and(http::with_qs_opt::<project::request::Options>())
         .and_then(handler::create)
 }
 
+/// `GET /<urn>/history`
+fn history_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("history"))
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::history)
+}
+
 /// `GET /`
 fn list_filter(
     ctx: context::Context,
@@ -60,7 +74,7 @@ mod handler {
 
     use link_identities::git::Urn;
 
-    use crate::{context, error};
+    use crate::{context, error, project};
 
     /// Abort search for an ongoing request.
     pub async fn cancel(urn: Urn, mut ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
@@ -77,16 +91,31 @@ mod handler {
     ///
     /// FIXME(xla): Endpoint ought to return `201` if the request was newly created, otherwise
     /// `200` if there was a request present for the urn.
-    pub async fn create(urn: Urn, mut ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
-        let request = ctx
-            .peer
-            .daemon_control()
-            .request_project(&urn, SystemTime::now())
-            .await;
+    pub async fn create(
+        urn: Urn,
+        mut ctx: context::Unsealed,
+        options: Option<project::request::Options>,
+    ) -> Result<impl Reply, Rejection> {
+        let request = project::request::create(
+            &mut ctx.peer,
+            &ctx.rest.store,
+            urn,
+            options.unwrap_or_default(),
+        )
+        .await?;
 
         Ok(reply::json(&request))
     }
 
+    /// Show what happened to the latest request for a project.
+    #[allow(clippy::unused_async)]
+    pub async fn history(urn: Urn, ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        let history = project::request::history(&ctx.rest.store, &urn)?
+            .ok_or(error::Error::ProjectRequestNotFound)?;
+
+        Ok(reply::json(&history))
+    }
+
     /// List all project requests the current user has issued.
     pub async fn list(mut ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
         let requests = ctx.peer.daemon_control().get_project_requests().await;
@@ -161,6 +190,49 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn history() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, run) = context::Unsealed::tmp(&tmp_dir)?;
+        let handle = tokio::spawn(run);
+        let api = super::filters(ctx.clone().into());
+
+        let urn = Urn::new(radicle_git_ext::Oid::try_from(
+            "7ab8629dd6da14dcacde7f65b3d58cd291d7e235",
+        )?);
+
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}/history", urn))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::NOT_FOUND, |have| {
+            assert_eq!(have["variant"], "PROJECT_REQUEST_NOT_FOUND");
+        });
+
+        let res = request()
+            .method("PUT")
+            .path(&format!("/{}?timeout=30&maxQueries=2", urn))
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}/history", urn))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(
+                have["options"],
+                json!({ "timeout": 30, "maxQueries": 2, "preferredPeers": [] })
+            );
+        });
+        handle.abort();
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn list() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir()?;
@@ -0,0 +1,243 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Endpoints for tracking several peers of a project at once and for tracking policies.
+
+use warp::{filters::BoxedFilter, path, Filter, Rejection, Reply};
+
+use link_identities::git::Urn;
+
+use crate::{context, http};
+
+/// Combination of all routes.
+pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
+    bulk_filter(ctx.clone())
+        .or(get_policy_filter(ctx.clone()))
+        .or(set_policy_filter(ctx))
+        .boxed()
+}
+
+/// `PUT /<urn>/tracking`
+fn bulk_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("tracking"))
+        .and(path::end())
+        .and(warp::put())
+        .and(http::with_context_unsealed(ctx))
+        .and(warp::body::json())
+        .and_then(handler::bulk)
+}
+
+/// `GET /<urn>/tracking/policy`
+fn get_policy_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("tracking"))
+        .and(path("policy"))
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::get_policy)
+}
+
+/// `PUT /<urn>/tracking/policy`
+fn set_policy_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("tracking"))
+        .and(path("policy"))
+        .and(path::end())
+        .and(warp::put())
+        .and(http::with_context_unsealed(ctx.clone()))
+        .and(http::with_owner_guard(ctx))
+        .and(warp::body::json())
+        .and_then(handler::set_policy)
+}
+
+/// Tracking handlers for conversion between core domain and http request fullfilment.
+mod handler {
+    use warp::{http::StatusCode, reply, Rejection, Reply};
+
+    use link_identities::git::Urn;
+
+    use crate::{context, project, session};
+
+    /// Track and untrack several peers of a project and list the resulting peers.
+    pub async fn bulk(
+        urn: Urn,
+        ctx: context::Unsealed,
+        bulk: project::tracking::Bulk,
+    ) -> Result<impl Reply, Rejection> {
+        project::tracking::bulk(&ctx.peer, &ctx.rest.store, urn.clone(), bulk).await?;
+        let peers = project::peers(&ctx.peer, &ctx.rest.store, urn).await?;
+
+        Ok(reply::json(&peers))
+    }
+
+    /// Get the tracking policy of a project.
+    #[allow(clippy::unused_async)]
+    pub async fn get_policy(urn: Urn, ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        let policy = session::tracking_policy(&ctx.rest.store, &urn)?;
+
+        Ok(reply::json(&policy))
+    }
+
+    /// Set the tracking policy of a project.
+    pub async fn set_policy(
+        urn: Urn,
+        ctx: context::Unsealed,
+        _owner: radicle_daemon::LocalIdentity,
+        policy: session::settings::TrackingPolicy,
+    ) -> Result<impl Reply, Rejection> {
+        project::tracking::set_policy(&ctx.peer, &ctx.rest.store, urn, policy).await?;
+
+        Ok(reply::with_status(reply(), StatusCode::NO_CONTENT))
+    }
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use warp::{http::StatusCode, test::request};
+
+    use crate::{context, http, session};
+
+    #[tokio::test]
+    async fn policy() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let session = session::initialize_test(&ctx, "cloudhead").await;
+        let owner =
+            radicle_daemon::state::get_local(ctx.peer.librad_peer(), session.identity.urn.clone())
+                .await?
+                .unwrap();
+        let urn = crate::control::replicate_platinum(
+            &ctx.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+        let stranger = crate::control::generate_peer_id();
+        radicle_daemon::state::track(ctx.peer.librad_peer(), urn.clone(), stranger).await?;
+
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}/tracking/policy", urn))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have, json!("manual"));
+        });
+
+        let res = request()
+            .method("PUT")
+            .path(&format!("/{}/tracking/policy", urn))
+            .json(&json!("delegates"))
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}/tracking/policy", urn))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have, json!("delegates"));
+        });
+        assert_eq!(
+            session::tracking_policy(&ctx.rest.store, &urn)?,
+            session::settings::TrackingPolicy::Delegates
+        );
+
+        // Only delegates may be tracked under the policy.
+        let tracked = radicle_daemon::state::tracked(ctx.peer.librad_peer(), urn.clone())
+            .await?
+            .into_iter()
+            .map(|peer| peer.peer_id())
+            .collect::<Vec<_>>();
+        assert!(!tracked.contains(&stranger));
+
+        let res = request()
+            .method("PUT")
+            .path(&format!("/{}/tracking", urn))
+            .json(&json!({ "track": [stranger] }))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::BAD_REQUEST, |have| {
+            assert_eq!(have["variant"], "NOT_A_DELEGATE_PEER");
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bulk() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let session = session::initialize_test(&ctx, "cloudhead").await;
+        let owner =
+            radicle_daemon::state::get_local(ctx.peer.librad_peer(), session.identity.urn.clone())
+                .await?
+                .unwrap();
+        let urn = crate::control::replicate_platinum(
+            &ctx.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+        let alice = crate::control::generate_peer_id();
+        let bob = crate::control::generate_peer_id();
+
+        let res = request()
+            .method("PUT")
+            .path(&format!("/{}/tracking", urn))
+            .json(&json!({ "track": [alice, bob] }))
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let tracked = radicle_daemon::state::tracked(ctx.peer.librad_peer(), urn.clone())
+            .await?
+            .into_iter()
+            .map(|peer| peer.peer_id())
+            .collect::<Vec<_>>();
+        assert!(tracked.contains(&alice));
+        assert!(tracked.contains(&bob));
+
+        let res = request()
+            .method("PUT")
+            .path(&format!("/{}/tracking", urn))
+            .json(&json!({ "untrack": [alice] }))
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let tracked = radicle_daemon::state::tracked(ctx.peer.librad_peer(), urn.clone())
+            .await?
+            .into_iter()
+            .map(|peer| peer.peer_id())
+            .collect::<Vec<_>>();
+        assert!(!tracked.contains(&alice));
+        assert!(tracked.contains(&bob));
+
+        Ok(())
+    }
+}
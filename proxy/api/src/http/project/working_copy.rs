@@ -0,0 +1,323 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Endpoints for the working copies registered for a project.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use warp::{filters::BoxedFilter, path, Filter, Rejection, Reply};
+
+use link_identities::git::Urn;
+
+use crate::{context, http};
+
+/// Combination of all routes.
+pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
+    list_filter(ctx.clone())
+        .or(publish_filter(ctx.clone()))
+        .or(pull_filter(ctx.clone()))
+        .or(unregister_filter(ctx))
+        .boxed()
+}
+
+/// `GET /<urn>/working-copies`
+fn list_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("working-copies"))
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::list)
+}
+
+/// `POST /<urn>/working-copies/publish`
+fn publish_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("working-copies"))
+        .and(path("publish"))
+        .and(path::end())
+        .and(warp::post())
+        .and(http::with_context_unsealed(ctx))
+        .and(warp::body::json())
+        .and_then(handler::publish)
+}
+
+/// `POST /<urn>/working-copies/pull`
+fn pull_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("working-copies"))
+        .and(path("pull"))
+        .and(path::end())
+        .and(warp::post())
+        .and(http::with_context_unsealed(ctx))
+        .and(warp::body::json())
+        .and_then(handler::pull)
+}
+
+/// `DELETE /<urn>/working-copies?path=<path>`
+fn unregister_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path::param::<Urn>()
+        .and(path("working-copies"))
+        .and(path::end())
+        .and(warp::delete())
+        .and(http::with_qs::<UnregisterQuery>())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::unregister)
+}
+
+/// Query parameters for [`handler::unregister`].
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnregisterQuery {
+    /// Location of the working copy to forget.
+    path: PathBuf,
+}
+
+/// Working copy handlers for conversion between core domain and http request fullfilment.
+mod handler {
+    use warp::{http::StatusCode, reply, Rejection, Reply};
+
+    use link_identities::git::Urn;
+
+    use crate::{context, error, project::working_copy};
+
+    /// List the registered working copies of a project with their state on disk.
+    pub async fn list(urn: Urn, ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        let working_copies = working_copy::list(&ctx.rest.store, &urn).await?;
+
+        Ok(reply::json(&working_copies))
+    }
+
+    /// Push branches and tags of a working copy to the monorepo.
+    pub async fn publish(
+        urn: Urn,
+        ctx: context::Unsealed,
+        input: working_copy::Publish,
+    ) -> Result<impl Reply, Rejection> {
+        let pushed = working_copy::publish(&ctx.peer, &ctx.rest.store, urn, input).await?;
+
+        Ok(reply::json(&pushed))
+    }
+
+    /// Fetch the branches of all peers into a working copy and fast-forward a branch.
+    pub async fn pull(
+        urn: Urn,
+        ctx: context::Unsealed,
+        input: working_copy::Pull,
+    ) -> Result<impl Reply, Rejection> {
+        let pulled = working_copy::pull(&ctx.peer, &ctx.rest.store, urn, input).await?;
+
+        Ok(reply::json(&pulled))
+    }
+
+    /// Forget a working copy of a project without touching it on disk.
+    pub async fn unregister(
+        urn: Urn,
+        query: super::UnregisterQuery,
+        ctx: context::Unsealed,
+    ) -> Result<impl Reply, Rejection> {
+        if !working_copy::unregister(&ctx.rest.store, &urn, &query.path)? {
+            return Err(Rejection::from(error::Error::WorkingCopyNotFound(
+                query.path,
+            )));
+        }
+
+        Ok(reply::with_status(reply(), StatusCode::NO_CONTENT))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use warp::{http::StatusCode, test::request};
+
+    use link_identities::payload::Person;
+    use radicle_source::surf::vcs::git::git2;
+
+    use crate::{context, http, project};
+
+    #[tokio::test]
+    async fn list_and_unregister() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let owner = radicle_daemon::state::init_owner(
+            ctx.peer.librad_peer(),
+            Person {
+                name: "cloudhead".into(),
+            },
+        )
+        .await?;
+        let urn = crate::control::replicate_platinum(
+            &ctx.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+        let missing = tmp_dir.path().join("missing");
+        project::working_copy::register(
+            &ctx.rest.store,
+            &urn,
+            missing.clone(),
+            project::working_copy::Origin::Existing,
+        )?;
+
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}/working-copies", urn))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(
+                have,
+                json!([{
+                    "path": missing,
+                    "exists": false,
+                    "branch": null,
+                    "dirty": false,
+                    "ahead": null,
+                    "behind": null,
+                }])
+            );
+        });
+
+        let res = request()
+            .method("DELETE")
+            .path(&format!(
+                "/{}/working-copies?path={}",
+                urn,
+                missing.display()
+            ))
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert!(project::working_copy::paths(&ctx.rest.store, &urn)?.is_empty());
+
+        let res = request()
+            .method("DELETE")
+            .path(&format!(
+                "/{}/working-copies?path={}",
+                urn,
+                missing.display()
+            ))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::NOT_FOUND, |have| {
+            assert_eq!(have["variant"], "WORKING_COPY_NOT_FOUND");
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn publish_and_pull() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let repos_dir = tempfile::tempdir_in(tmp_dir.path())?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let owner = radicle_daemon::state::init_owner(
+            ctx.peer.librad_peer(),
+            Person {
+                name: "cloudhead".into(),
+            },
+        )
+        .await?;
+        let urn = crate::control::replicate_platinum(
+            &ctx.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+        let path = radicle_daemon::state::checkout(
+            ctx.peer.librad_peer(),
+            urn.clone(),
+            None,
+            repos_dir.path().to_path_buf(),
+        )
+        .await?;
+        project::working_copy::register(
+            &ctx.rest.store,
+            &urn,
+            path.clone(),
+            project::working_copy::Origin::Checkout,
+        )?;
+
+        let commit = {
+            let repo = git2::Repository::open(&path)?;
+            let signature = git2::Signature::now("cloudhead", "cloudhead@radicle.xyz")?;
+            let parent = repo.head()?.peel_to_commit()?;
+            repo.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                "Publish from the proxy",
+                &parent.tree()?,
+                &[&parent],
+            )?
+        };
+
+        let res = request()
+            .method("POST")
+            .path(&format!("/{}/working-copies/publish", urn))
+            .json(&json!({ "path": path }))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::BAD_REQUEST, |have| {
+            assert_eq!(have["variant"], "NOTHING_TO_PUBLISH");
+        });
+
+        let res = request()
+            .method("POST")
+            .path(&format!("/{}/working-copies/publish", urn))
+            .json(&json!({ "path": path, "branches": ["master"] }))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have, json!(["refs/heads/master"]));
+        });
+
+        let monorepo =
+            git2::Repository::open(radicle_daemon::state::monorepo(ctx.peer.librad_peer()))?;
+        assert_eq!(
+            monorepo.refname_to_id(&format!(
+                "refs/namespaces/{}/refs/heads/master",
+                urn.encode_id()
+            ))?,
+            commit
+        );
+
+        let res = request()
+            .method("POST")
+            .path(&format!("/{}/working-copies/pull", urn))
+            .json(&json!({ "path": path, "branch": "master" }))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have["branch"], "master");
+            assert_eq!(have["head"], commit.to_string());
+        });
+
+        Ok(())
+    }
+}
@@ -8,11 +8,16 @@
 
 use warp::{filters::BoxedFilter, path, Filter, Rejection, Reply};
 
+use link_crypto::PeerId;
+
 use crate::{context, http};
 
 /// Combination of all session filters.
 pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
     get_filter(ctx.clone())
+        .or(get_blocklist_filter(ctx.clone()))
+        .or(block_filter(ctx.clone()))
+        .or(unblock_filter(ctx.clone()))
         .or(get_seeds_filter(ctx.clone()))
         .or(put_seeds_filter(ctx))
         .boxed()
@@ -28,6 +33,41 @@ fn get_filter(
         .and_then(handler::get)
 }
 
+/// `GET /blocklist`
+fn get_blocklist_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("blocklist")
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::get_blocklist)
+}
+
+/// `PUT /blocklist/<peer_id>`
+fn block_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("blocklist")
+        .and(path::param::<PeerId>())
+        .and(path::end())
+        .and(warp::put())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::block)
+}
+
+/// `DELETE /blocklist/<peer_id>`
+fn unblock_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("blocklist")
+        .and(path::param::<PeerId>())
+        .and(path::end())
+        .and(warp::delete())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::unblock)
+}
+
 /// `GET /seeds`
 fn get_seeds_filter(
     ctx: context::Context,
@@ -55,19 +95,45 @@ fn put_seeds_filter(
 mod handler {
     use warp::{http::StatusCode, reply, Rejection, Reply};
 
-    use crate::{context, error, http, session};
+    use link_crypto::PeerId;
+
+    use crate::{blocklist, context, error, http, session};
 
     /// Fetch the [`session::Session`].
     #[allow(clippy::unused_async)]
     pub async fn get(ctx: context::Context) -> Result<impl Reply, Rejection> {
-        let sess =
+        let mut sess =
             crate::session::get_current(ctx.store())?.ok_or(http::error::Routing::NoSession)?;
+        sess.identity = sess.identity.with_petname(ctx.store())?;
         match ctx {
             context::Context::Unsealed(_) => Ok(reply::json(&sess)),
             context::Context::Sealed(_) => Err(Rejection::from(error::Error::KeystoreSealed)),
         }
     }
 
+    /// List the blocked peers.
+    #[allow(clippy::unused_async)]
+    pub async fn get_blocklist(ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        let blocklist = session::blocklist(&ctx.rest.store)?;
+
+        Ok(reply::json(&blocklist))
+    }
+
+    /// Block a peer and untrack it from every project.
+    pub async fn block(peer_id: PeerId, ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        blocklist::block(&ctx.peer, &ctx.rest.store, peer_id).await?;
+
+        Ok(warp::reply::with_status(reply(), StatusCode::NO_CONTENT))
+    }
+
+    /// Remove a peer from the blocklist.
+    #[allow(clippy::unused_async)]
+    pub async fn unblock(peer_id: PeerId, ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        blocklist::unblock(&ctx.rest.store, peer_id)?;
+
+        Ok(warp::reply::with_status(reply(), StatusCode::NO_CONTENT))
+    }
+
     #[allow(clippy::unused_async)]
     pub async fn get_seeds(ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
         let seeds = if let Some(seeds) = ctx.rest.seeds {
@@ -104,9 +170,10 @@ mod handler {
 #[cfg(test)]
 mod test {
     use pretty_assertions::assert_eq;
+    use serde_json::json;
     use warp::{http::StatusCode, test::request};
 
-    use crate::{context, session};
+    use crate::{context, http, session};
 
     #[tokio::test]
     async fn get() -> Result<(), Box<dyn std::error::Error>> {
@@ -124,4 +191,62 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn blocklist() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+        session::initialize_test(&ctx, "xla").await;
+        let spammer = crate::control::generate_peer_id();
+
+        let res = request()
+            .method("PUT")
+            .path(&format!("/blocklist/{}", spammer))
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let res = request().method("GET").path("/blocklist").reply(&api).await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have, json!([spammer]));
+        });
+
+        let res = request()
+            .method("PUT")
+            .path(&format!("/blocklist/{}", ctx.peer.librad_peer().peer_id()))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::BAD_REQUEST, |have| {
+            assert_eq!(have["variant"], "BLOCK_LOCAL_PEER");
+        });
+
+        let res = request()
+            .method("DELETE")
+            .path(&format!("/blocklist/{}", spammer))
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert!(session::blocklist(&ctx.rest.store)?.is_empty());
+
+        Ok(())
+    }
+    #[tokio::test]
+    async fn block_without_session() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+        let spammer = crate::control::generate_peer_id();
+
+        let res = request()
+            .method("PUT")
+            .path(&format!("/blocklist/{}", spammer))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::NOT_FOUND, |have| {
+            assert_eq!(have["variant"], "NO_SESSION");
+        });
+
+        Ok(())
+    }
 }
@@ -22,6 +22,7 @@ pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
         .or(commit_filter(ctx.clone()))
         .or(commits_filter(ctx.clone()))
         .or(local_state_filter())
+        .or(raw_filter(ctx.clone()))
         .or(tags_filter(ctx.clone()))
         .or(tree_filter(ctx))
         .boxed()
@@ -85,6 +86,19 @@ fn local_state_filter() -> impl Filter<Extract = impl Reply, Error = Rejection>
         .and_then(handler::local_state)
 }
 
+/// `GET /raw/<project_urn>?revision=<revision>&path=<path>`
+fn raw_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("raw")
+        .and(path::param::<Urn>())
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_qs::<RawQuery>())
+        .and(http::with_context_unsealed(ctx))
+        .and_then(handler::raw)
+}
+
 /// `GET /tags/<project_urn>?peer_id=<peer_id>`
 fn tags_filter(
     ctx: context::Context,
@@ -119,7 +133,7 @@ mod handler {
     use radicle_git_ext::Oid;
     use radicle_source::surf::vcs::git::RefScope;
 
-    use crate::{browser, context, error};
+    use crate::{browser, context, error, project};
 
     /// Fetch a [`radicle_source::Blob`].
     pub async fn blob(
@@ -153,6 +167,28 @@ mod handler {
         Ok(reply::json(&blob))
     }
 
+    /// Serve the contents of a file with a content type derived from its extension.
+    pub async fn raw(
+        project_urn: Urn,
+        super::RawQuery { path, revision }: super::RawQuery,
+        ctx: context::Unsealed,
+    ) -> Result<impl Reply, Rejection> {
+        let revision = super::http::guard_self_revision(&ctx.peer, revision);
+        let content_type = super::content_type(&path);
+        let content = project::raw::blob(&ctx.peer, project_urn, revision, path).await?;
+
+        // Files of a project are untrusted, scripts in SVGs must not run.
+        Ok(reply::with_header(
+            reply::with_header(
+                reply::with_header(content, "content-type", content_type),
+                "content-security-policy",
+                "default-src 'none'; style-src 'unsafe-inline'; sandbox",
+            ),
+            "x-content-type-options",
+            "nosniff",
+        ))
+    }
+
     /// Fetch the list [`radicle_source::Branch`].
     pub async fn branches(
         project_urn: Urn,
@@ -292,6 +328,35 @@ pub struct BlobQuery {
     highlight: Option<HighlightTheme>,
 }
 
+/// Bundled query params to pass to the raw handler.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawQuery {
+    /// Location of the file in the tree.
+    path: String,
+    /// Revision to query at. Defaults to the default branch.
+    revision: Option<radicle_source::Revision<PeerId>>,
+}
+
+/// Content type of the file at `path` for the raw handler.
+fn content_type(path: &str) -> &'static str {
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "gif" => "image/gif",
+        "ico" => "image/x-icon",
+        "jpeg" | "jpg" => "image/jpeg",
+        "pdf" => "application/pdf",
+        "png" => "image/png",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "md" | "txt" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
 /// A query param for [`handler::branches`].
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -467,6 +532,51 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn raw() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+        let api = super::filters(ctx.clone().into());
+
+        let urn = replicate_platinum(&ctx).await?;
+        let query = super::RawQuery {
+            path: "text/arrows.txt".to_string(),
+            revision: None,
+        };
+        let res = request()
+            .method("GET")
+            .path(&format!(
+                "/raw/{}?{}",
+                urn,
+                serde_qs::to_string(&query).unwrap()
+            ))
+            .reply(&api)
+            .await;
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "text/plain; charset=utf-8");
+        assert!(res.body().starts_with(b"  ;;;;;        ;;;;;"));
+
+        let query = super::RawQuery {
+            path: "text/missing.txt".to_string(),
+            revision: None,
+        };
+        let res = request()
+            .method("GET")
+            .path(&format!(
+                "/raw/{}?{}",
+                urn,
+                serde_qs::to_string(&query).unwrap()
+            ))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::NOT_FOUND, |have| {
+            assert_eq!(have["variant"], "PATH_NOT_FOUND");
+        });
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn blob_dev_branch() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir()?;
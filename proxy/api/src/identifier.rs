@@ -14,6 +14,8 @@ use serde::{de::Visitor, Deserialize, Deserializer, Serialize, Serializer};
 use link_crypto::{peer::conversion, PeerId};
 use link_identities::Person;
 
+use crate::{error, petname};
+
 /// Errors captured when parsing a shareable identifier of the form `<handle>@<urn>`.
 #[derive(Debug, thiserror::Error)]
 pub enum ParseError {
@@ -70,6 +72,65 @@ impl FromStr for Identifier {
     }
 }
 
+impl Identifier {
+    /// Resolve `input` to an [`Identifier`]. `input` is either of the form `<handle>@<peer_id>` or
+    /// a petname from the address book, see [`petname`].
+    ///
+    /// A petname given to a peer resolves to the petname and the peer, a petname given to a person
+    /// to the handle of the person and their device. If the person has several devices, the one
+    /// we are connected to is picked.
+    ///
+    /// # Errors
+    ///
+    ///   * `input` is a malformed identifier.
+    ///   * Nobody in the address book has the petname.
+    ///   * The person has several devices and we are connected to none or more than one of them.
+    ///   * The person with the petname could not be read from the monorepo.
+    pub async fn resolve(
+        peer: &crate::peer::Peer,
+        store: &kv::Store,
+        input: &str,
+    ) -> Result<Self, error::Error> {
+        if input.contains('@') {
+            return Ok(input.parse()?);
+        }
+
+        let entry = petname::find(store, input)?
+            .ok_or_else(|| error::Error::UnknownPetname(input.to_string()))?;
+        match entry.subject {
+            petname::Subject::Peer(peer_id) => Ok(Self {
+                handle: entry.petname.petname,
+                peer_id,
+            }),
+            petname::Subject::Person(urn) => {
+                let person = radicle_daemon::state::get_user(peer.librad_peer(), urn)
+                    .await?
+                    .ok_or_else(|| error::Error::UnknownPetname(input.to_string()))?;
+                let devices = person
+                    .delegations()
+                    .iter()
+                    .map(|key| PeerId::from(*key))
+                    .collect::<Vec<_>>();
+                let peer_id = match devices.as_slice() {
+                    [] => return Err(error::Error::UnknownPetname(input.to_string())),
+                    [device] => *device,
+                    _ => {
+                        let connected = peer.librad_peer().stats().await.connected_peers;
+                        let mut reachable = devices
+                            .into_iter()
+                            .filter(|device| connected.contains_key(device));
+                        match (reachable.next(), reachable.next()) {
+                            (Some(device), None) => device,
+                            _ => return Err(error::Error::AmbiguousPetname(input.to_string())),
+                        }
+                    },
+                };
+                Ok(Self::from((peer_id, person)))
+            },
+        }
+    }
+}
+
 impl fmt::Display for Identifier {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}@{}", self.handle, self.peer_id)
@@ -8,6 +8,7 @@
 
 use chrono::{DateTime, Utc};
 
+use futures::{prelude::*, stream::FuturesUnordered};
 use serde::{Deserialize, Serialize};
 
 use link_crypto::{BoxedSigner, PeerId};
@@ -20,9 +21,18 @@ use link_identities::{
 use crate::{
     error,
     ethereum::{address::Address, claim_ext::V1 as EthereumClaimExtV1},
+    petname, session,
 };
 
-use std::convert::TryFrom;
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    net::SocketAddr,
+    time::Duration,
+};
+
+pub mod device;
+pub mod history;
 
 /// The users personal identifying metadata and keys.
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
@@ -34,6 +44,21 @@ pub struct Identity {
     pub urn: Urn,
     /// Bundle of user provided data.
     pub metadata: Metadata,
+    /// Name the local user gave this identity, see [`crate::petname`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub petname: Option<String>,
+}
+
+impl Identity {
+    /// Attach the petname of the identity from the address book in `store`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if we cannot read from the store.
+    pub fn with_petname(self, store: &kv::Store) -> Result<Self, error::Error> {
+        let petname = petname::lookup(store, Some(&self.urn), &[self.peer_id])?;
+        Ok(Self { petname, ..self })
+    }
 }
 
 impl From<(PeerId, LinkPerson)> for Identity {
@@ -43,6 +68,7 @@ impl From<(PeerId, LinkPerson)> for Identity {
             peer_id,
             urn: identity.urn,
             metadata: identity.metadata,
+            petname: None,
         }
     }
 }
@@ -57,6 +83,21 @@ pub struct Person {
     pub metadata: Metadata,
     /// The user's PeerIds.
     pub peer_ids: Vec<PeerId>,
+    /// Name the local user gave this person, see [`crate::petname`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub petname: Option<String>,
+}
+
+impl Person {
+    /// Attach the petname of the person from the address book in `store`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if we cannot read from the store.
+    pub fn with_petname(self, store: &kv::Store) -> Result<Self, error::Error> {
+        let petname = petname::lookup(store, Some(&self.urn), &self.peer_ids)?;
+        Ok(Self { petname, ..self })
+    }
 }
 
 impl From<LinkPerson> for Person {
@@ -80,10 +121,43 @@ impl From<LinkPerson> for Person {
             urn,
             peer_ids,
             metadata: Metadata { handle, ethereum },
+            petname: None,
         }
     }
 }
 
+/// A person of the directory, see [`directory`].
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectoryEntry {
+    /// The person.
+    #[serde(flatten)]
+    pub person: Person,
+    /// Projects the person maintains or was replicated for.
+    pub projects: Vec<Urn>,
+}
+
+/// A slice of the persons in the directory, ordered by handle.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectoryPage {
+    /// The persons of the page.
+    pub persons: Vec<DirectoryEntry>,
+    /// Number of persons matching the filter across all pages.
+    pub total: usize,
+    /// Offset to get the next page with. `None` if this is the last page.
+    pub next: Option<usize>,
+}
+
+/// Number of persons in a [`DirectoryPage`] if the client doesn't ask for a specific number.
+pub const DEFAULT_DIRECTORY_LIMIT: usize = 50;
+
+/// Maximum number of persons in a [`DirectoryPage`], larger limits are capped.
+pub const MAX_DIRECTORY_LIMIT: usize = 500;
+
+/// Time a peer gets to serve a person identity before we give up on it.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// User maintained information for an identity, which can evolve over time.
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -181,3 +255,176 @@ pub async fn update(
         .ok_or(radicle_daemon::state::Error::MissingOwner)?;
     Ok((peer.peer_id(), user.into_inner().into_inner()).into())
 }
+
+/// Ask the connected peers for the person identity `urn` and replicate it from the peers that
+/// have it, like the seed node does for the identities it is configured with. All peers are asked
+/// at the same time and we return as soon as one of them served the identity. The others keep
+/// replicating in the background. Blocked peers are not asked and peers that don't have the
+/// identity or don't answer within [`FETCH_TIMEOUT`] are untracked again.
+///
+/// # Errors
+///
+///   * None of the connected peers has the identity and we didn't have it before.
+///   * We cannot read from the store or the monorepo.
+pub async fn request(
+    peer: &radicle_daemon::net::peer::Peer<BoxedSigner>,
+    store: &kv::Store,
+    urn: Urn,
+) -> Result<Person, error::Error> {
+    let blocklist = session::blocklist(store)?;
+    let mut fetches = peer
+        .stats()
+        .await
+        .connected_peers
+        .into_iter()
+        .filter(|(peer_id, _)| !blocklist.contains(peer_id))
+        .map(|(peer_id, addrs)| {
+            let peer = peer.clone();
+            let urn = urn.clone();
+            tokio::spawn(async move {
+                match fetch_from_peer(&peer, urn.clone(), peer_id, addrs).await {
+                    Ok(true) => {
+                        tracing::info!(%urn, %peer_id, "replicated identity");
+                        true
+                    },
+                    Ok(false) => {
+                        tracing::debug!(%urn, %peer_id, "peer does not have identity");
+                        false
+                    },
+                    Err(err) => {
+                        tracing::warn!(%urn, %peer_id, ?err, "failed to fetch identity");
+                        false
+                    },
+                }
+            })
+        })
+        .collect::<FuturesUnordered<_>>();
+    while let Some(replicated) = fetches.next().await {
+        if let Ok(true) = replicated {
+            break;
+        }
+    }
+
+    let person = radicle_daemon::state::get_user(peer, urn)
+        .await?
+        .ok_or(error::Error::PersonNotFound)?;
+    Ok(Person::from(person))
+}
+
+/// Track `peer_id` for the person identity `urn` and replicate the identity from it. Returns
+/// `false` if the peer doesn't have the identity or doesn't serve it within [`FETCH_TIMEOUT`].
+/// The peer stays tracked only if replication succeeded.
+async fn fetch_from_peer(
+    peer: &radicle_daemon::net::peer::Peer<BoxedSigner>,
+    urn: Urn,
+    peer_id: PeerId,
+    addrs: Vec<SocketAddr>,
+) -> Result<bool, error::Error> {
+    radicle_daemon::state::track(peer, urn.clone(), peer_id).await?;
+    let replicate = peer.replicate((peer_id, addrs), urn.clone(), None);
+    let result = match tokio::time::timeout(FETCH_TIMEOUT, replicate).await {
+        Ok(Ok(_)) => return Ok(true),
+        Ok(Err(librad::net::peer::error::Replicate::Replicate(
+            librad::net::replication::error::Replicate::Replication(
+                librad::git::replication::Error::MissingIdentity,
+            ),
+        ))) => Ok(false),
+        Ok(Err(err)) => Err(err.into()),
+        Err(_) => {
+            tracing::debug!(%urn, %peer_id, "peer did not serve identity in time");
+            Ok(false)
+        },
+    };
+    radicle_daemon::state::untrack(peer, urn, peer_id).await?;
+    result
+}
+
+/// List the person identities in the monorepo whose handle starts with `handle_prefix`, ignoring
+/// case. Persons are ordered by handle and the page starts at `offset` with at most `limit`
+/// persons, but no more than [`MAX_DIRECTORY_LIMIT`].
+///
+/// # Errors
+///
+///   * `limit` is zero.
+///   * The persons or projects could not be read from the monorepo.
+///   * We cannot read from the store.
+#[allow(clippy::redundant_closure_for_method_calls)]
+pub async fn directory(
+    peer: &crate::peer::Peer,
+    store: &kv::Store,
+    handle_prefix: Option<&str>,
+    offset: usize,
+    limit: usize,
+) -> Result<DirectoryPage, error::Error> {
+    // An empty page would point at itself as the next one.
+    if limit == 0 {
+        return Err(error::Error::InvalidLimit);
+    }
+    let limit = limit.min(MAX_DIRECTORY_LIMIT);
+    let handle_prefix = handle_prefix.map(str::to_lowercase);
+    let mut persons = peer
+        .librad_peer()
+        .using_storage(move |storage| rad_identities::person::list(storage))
+        .await??
+        .into_iter()
+        .map(Person::from)
+        .filter(|person| {
+            handle_prefix.as_ref().map_or(true, |prefix| {
+                person.metadata.handle.to_lowercase().starts_with(prefix)
+            })
+        })
+        .collect::<Vec<_>>();
+    persons.sort_by(|a, b| {
+        (&a.metadata.handle, a.urn.to_string()).cmp(&(&b.metadata.handle, b.urn.to_string()))
+    });
+
+    let total = persons.len();
+    let persons = persons
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .collect::<Vec<_>>();
+    let next = Some(offset + persons.len()).filter(|next| *next < total);
+
+    let mut projects = HashMap::<Urn, Vec<Urn>>::new();
+    if !persons.is_empty() {
+        for project in radicle_daemon::state::list_projects(peer.librad_peer()).await? {
+            let project_urn = project.urn();
+            let mut members = project
+                .delegations()
+                .iter()
+                .indirect()
+                .map(|person| person.urn())
+                .collect::<HashSet<_>>();
+            members.extend(
+                radicle_daemon::state::tracked(peer.librad_peer(), project_urn.clone())
+                    .await?
+                    .into_iter()
+                    .filter_map(radicle_daemon::project::Peer::replicated_remote)
+                    .map(|(_, user)| user.urn()),
+            );
+            for member in members {
+                projects
+                    .entry(member)
+                    .or_default()
+                    .push(project_urn.clone());
+            }
+        }
+    }
+
+    let persons = persons
+        .into_iter()
+        .map(|person| {
+            Ok(DirectoryEntry {
+                projects: projects.remove(&person.urn).unwrap_or_default(),
+                person: person.with_petname(store)?,
+            })
+        })
+        .collect::<Result<Vec<_>, error::Error>>()?;
+
+    Ok(DirectoryPage {
+        persons,
+        total,
+        next,
+    })
+}
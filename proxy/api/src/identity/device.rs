@@ -0,0 +1,361 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Link several devices to one person identity.
+//!
+//! Every device has its own key and peer id. A person identity delegates directly to the keys of
+//! all devices of the person, so each of them acts on behalf of the same person. Linking a new
+//! device takes three steps:
+//!
+//!   1. The new device, which has no identity yet, exports a [`LinkingRequest`] with its peer id
+//!      and addresses, see [`linking_request`].
+//!   2. A linked device signs a new revision of the identity document that adds the key of the
+//!      new device, see [`link`]. It hands back a [`Link`] with the URN of the person and its own
+//!      peer id and addresses.
+//!   3. The new device replicates the identity from the linked device, signs the new revision and
+//!      adopts the person as its local identity, see [`adopt`].
+//!
+//! Like changes to project maintainers (see [`crate::project::maintainer`]), a revision only
+//! verifies once more than half of the current devices signed it. Every device publishes the
+//! revision it signed last under its own `rad/id` ref and the other devices sign it with
+//! [`sign`]. [`list`] shows which devices are linked and which are still being linked or revoked.
+
+use std::{collections::BTreeSet, convert::TryFrom as _, net::SocketAddr};
+
+use serde::{Deserialize, Serialize};
+
+use librad::git::identities;
+use link_crypto::{PeerId, PublicKey};
+use link_identities::{delegation::Direct, git::Urn};
+use radicle_git_ext::RefLike;
+
+use crate::error;
+
+/// What a new device hands to a linked device to be linked to its person identity.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkingRequest {
+    /// Peer id of the new device, which is its public key.
+    pub peer_id: PeerId,
+    /// Addresses the new device listens on.
+    #[serde(default)]
+    pub addrs: Vec<SocketAddr>,
+}
+
+/// What a linked device hands back to the new device, so it can adopt the person identity.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Link {
+    /// URN of the person identity.
+    pub urn: Urn,
+    /// Peer id of the linked device that signed the revision adding the new device.
+    pub peer_id: PeerId,
+    /// Addresses the linked device listens on. If empty, the linked device must be connected.
+    #[serde(default)]
+    pub addrs: Vec<SocketAddr>,
+}
+
+/// A device of the local person.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Device {
+    /// Peer id of the device.
+    pub peer_id: PeerId,
+    /// Whether this is the local device.
+    pub local: bool,
+    /// Whether the device is part of the verified identity.
+    pub status: Status,
+}
+
+/// Whether a device is part of the verified identity document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Status {
+    /// The device is a delegate of the verified revision and of the local one.
+    Linked,
+    /// The local revision adds the device, but it is not verified yet.
+    Linking,
+    /// The local revision removes the device, but it is not verified yet.
+    Revoking,
+}
+
+/// The linking request of the local device.
+pub async fn linking_request(peer: &mut crate::peer::Peer) -> LinkingRequest {
+    LinkingRequest {
+        peer_id: peer.librad_peer().peer_id(),
+        addrs: peer.daemon_control().listen_addrs().await,
+    }
+}
+
+/// List the devices of the person `urn`, comparing the verified revision of the identity
+/// document with the one the local device signed last.
+///
+/// # Errors
+///
+///   * The identity could not be found.
+///   * The identity documents could not be read.
+pub async fn list(peer: &crate::peer::Peer, urn: Urn) -> Result<Vec<Device>, error::Error> {
+    let local_peer_id = peer.librad_peer().peer_id();
+    peer.librad_peer()
+        .using_storage(move |storage| {
+            let verified =
+                identities::person::verify(storage, &urn)?.ok_or(error::Error::IdentityNotFound)?;
+            let current =
+                identities::person::get(storage, &urn)?.ok_or(error::Error::IdentityNotFound)?;
+
+            let verified = device_keys(&verified);
+            let current = device_keys(&current);
+            Ok(verified
+                .union(&current)
+                .map(|key| {
+                    let status = match (verified.contains(key), current.contains(key)) {
+                        (true, false) => Status::Revoking,
+                        (false, true) => Status::Linking,
+                        (true, true) | (false, false) => Status::Linked,
+                    };
+                    let peer_id = PeerId::from(*key);
+                    Device {
+                        peer_id,
+                        local: peer_id == local_peer_id,
+                        status,
+                    }
+                })
+                .collect())
+        })
+        .await?
+}
+
+/// Track the device of `request` and sign a new revision of the identity document of `owner`
+/// that adds it, so we replicate its signature once it adopted the identity.
+///
+/// The device is tracked first and untracked again if signing fails, so we never leave behind
+/// a revision adding a device we don't replicate from.
+///
+/// # Errors
+///
+///   * The device is already linked.
+///   * Tracking the device failed.
+///   * Signing or storing the new revision failed.
+pub async fn link(
+    peer: &mut crate::peer::Peer,
+    owner: &radicle_daemon::LocalIdentity,
+    request: LinkingRequest,
+) -> Result<Link, error::Error> {
+    let urn = owner.urn();
+    let already_linked = list(peer, urn.clone())
+        .await?
+        .iter()
+        .any(|device| device.peer_id == request.peer_id && device.status != Status::Revoking);
+    if already_linked {
+        return Err(error::Error::InvalidDelegations(format!(
+            "{} is already linked",
+            request.peer_id
+        )));
+    }
+
+    radicle_daemon::state::track(peer.librad_peer(), urn.clone(), request.peer_id).await?;
+    let key = *request.peer_id.as_public_key();
+    let updated = update_devices(peer, urn.clone(), move |keys| {
+        if keys.insert(key) {
+            Ok(())
+        } else {
+            Err(error::Error::InvalidDelegations(format!(
+                "{} is already linked",
+                request.peer_id
+            )))
+        }
+    })
+    .await;
+    if let Err(err) = updated {
+        if let Err(untrack_err) =
+            radicle_daemon::state::untrack(peer.librad_peer(), urn.clone(), request.peer_id).await
+        {
+            tracing::warn!(
+                %urn,
+                peer_id = %request.peer_id,
+                ?untrack_err,
+                "failed to untrack device"
+            );
+        }
+        return Err(err);
+    }
+
+    let LinkingRequest { peer_id, addrs } = linking_request(peer).await;
+    Ok(Link {
+        urn,
+        peer_id,
+        addrs,
+    })
+}
+
+/// Replicate the person identity of `link` from the linked device, sign the revision that adds
+/// the local device and make the person the local identity.
+///
+/// # Errors
+///
+///   * The linked device is not connected and no addresses were given.
+///   * The linked device doesn't have the identity or didn't add the local device.
+///   * Replicating, signing or storing the identity failed.
+pub async fn adopt(peer: &crate::peer::Peer, link: Link) -> Result<super::Identity, error::Error> {
+    let Link {
+        urn,
+        peer_id: linked,
+        addrs,
+    } = link;
+    let local_peer_id = peer.librad_peer().peer_id();
+    let addrs = if addrs.is_empty() {
+        peer.librad_peer()
+            .stats()
+            .await
+            .connected_peers
+            .get(&linked)
+            .cloned()
+            .ok_or(error::Error::PeerNotConnected(linked))?
+    } else {
+        addrs
+    };
+    if !super::fetch_from_peer(peer.librad_peer(), urn.clone(), linked, addrs).await? {
+        return Err(error::Error::IdentityNotFound);
+    }
+
+    let person = peer
+        .librad_peer()
+        .using_storage(move |storage| {
+            let theirs = identities::person::get(storage, &remote_urn(&urn, linked))?
+                .ok_or(error::Error::IdentityNotFound)?;
+            if !device_keys(&theirs).contains(local_peer_id.as_public_key()) {
+                return Err(error::Error::DeviceNotFound(local_peer_id));
+            }
+
+            let person = identities::person::merge(storage, &urn, linked)?;
+            let local =
+                rad_identities::local::get(storage, urn)?.ok_or(error::Error::IdentityNotFound)?;
+            rad_identities::local::set(storage, local)?;
+            Ok::<_, error::Error>(person)
+        })
+        .await??;
+
+    Ok(super::Identity::from((local_peer_id, person)))
+}
+
+/// Sign the revision of the identity document of person `urn` that `device` published.
+///
+/// Returns the devices afterwards. A device is no longer linking or revoking if our signature
+/// completed the quorum.
+///
+/// # Errors
+///
+///   * `device` is not a device of the person.
+///   * Merging and signing the revision failed.
+pub async fn sign(
+    peer: &crate::peer::Peer,
+    urn: Urn,
+    device: PeerId,
+) -> Result<Vec<Device>, error::Error> {
+    if !list(peer, urn.clone())
+        .await?
+        .iter()
+        .any(|known| known.peer_id == device)
+    {
+        return Err(error::Error::DeviceNotFound(device));
+    }
+
+    peer.librad_peer()
+        .using_storage({
+            let urn = urn.clone();
+            move |storage| identities::person::merge(storage, &urn, device)
+        })
+        .await??;
+
+    list(peer, urn).await
+}
+
+/// Sign a new revision of the identity document of `owner` that removes `device` and stop
+/// tracking it.
+///
+/// Returns the devices afterwards. The device is revoking until enough devices signed the
+/// revision.
+///
+/// Like every revision, the one removing `device` needs the signatures of more than half of the
+/// devices of the previous revision, which still include `device`. If the remaining devices are
+/// no such majority, for example when a person has only two devices, the revision could never
+/// verify without the signature of the revoked device. We refuse to revoke in that case, so a
+/// lost device has to be revoked while the person has at least three devices.
+///
+/// # Errors
+///
+///   * `device` is the local device or not a device of the person.
+///   * The remaining devices are no majority of the current ones.
+///   * Signing or storing the new revision failed.
+///   * Untracking the device failed.
+pub async fn revoke(
+    peer: &crate::peer::Peer,
+    owner: &radicle_daemon::LocalIdentity,
+    device: PeerId,
+) -> Result<Vec<Device>, error::Error> {
+    if device == peer.librad_peer().peer_id() {
+        return Err(error::Error::RevokeLocalDevice);
+    }
+
+    let urn = owner.urn();
+    update_devices(peer, urn.clone(), move |keys| {
+        let previous = keys.len();
+        if !keys.remove(device.as_public_key()) {
+            return Err(error::Error::DeviceNotFound(device));
+        }
+        if keys.len() * 2 <= previous {
+            return Err(error::Error::RevokeWithoutQuorum(device));
+        }
+        Ok(())
+    })
+    .await?;
+    radicle_daemon::state::untrack(peer.librad_peer(), urn.clone(), device).await?;
+
+    list(peer, urn).await
+}
+
+/// Sign a new revision of the identity document of person `urn` with the device keys changed by
+/// `change`.
+async fn update_devices<F>(
+    peer: &crate::peer::Peer,
+    urn: Urn,
+    change: F,
+) -> Result<link_identities::git::Person, error::Error>
+where
+    F: FnOnce(&mut BTreeSet<PublicKey>) -> Result<(), error::Error> + Send + 'static,
+{
+    peer.librad_peer()
+        .using_storage(move |storage| {
+            let person =
+                identities::person::get(storage, &urn)?.ok_or(error::Error::IdentityNotFound)?;
+            let mut keys = device_keys(&person);
+            change(&mut keys)?;
+
+            let delegations = Direct::try_from_iter(keys.into_iter())
+                .map_err(|err| error::Error::InvalidDelegations(err.to_string()))?;
+            Ok(identities::person::update(
+                storage,
+                &urn,
+                None,
+                None,
+                Some(delegations),
+            )?)
+        })
+        .await?
+}
+
+/// Keys of the devices `person` delegates to.
+fn device_keys(person: &link_identities::git::Person) -> BTreeSet<PublicKey> {
+    person.delegations().iter().copied().collect()
+}
+
+/// URN pointing at the `rad/id` ref `peer_id` published for `urn`.
+fn remote_urn(urn: &Urn, peer_id: PeerId) -> Urn {
+    urn.with_path(
+        RefLike::try_from(format!("refs/remotes/{}/rad/id", peer_id).as_str())
+            .expect("peer id is a valid ref component"),
+    )
+}
@@ -0,0 +1,434 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Revisions of identity documents and whether they verify.
+//!
+//! [`crate::identity::Person`] and [`crate::project::Metadata`] only show the latest state of an
+//! identity. To decide whether to trust an identity, [`revisions`] walks the history of the
+//! document of a person or project in the monorepo. Every [`Revision`] shows what changed in the
+//! payload, the delegations, who signed it and whether the signatures and the quorum of
+//! delegations verify.
+//!
+//! Delegations to persons are resolved with the latest revision of the person we have, so the
+//! quorum of a project revision is judged by the keys its delegates have now.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use chrono::{DateTime, TimeZone as _, Utc};
+use serde::Serialize;
+use serde_json::Value;
+
+use link_crypto::PeerId;
+use link_identities::{git::Urn, sign::Signatures};
+use radicle_git_ext::Oid;
+use radicle_source::surf::vcs::git::git2;
+
+use crate::error;
+
+/// A revision of an identity document.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Revision {
+    /// Commit the revision was recorded in.
+    pub commit: Oid,
+    /// Hash of the document, which is what delegates sign.
+    pub revision: Oid,
+    /// Parent commits, the previous revisions.
+    pub parents: Vec<Oid>,
+    /// When the revision was committed.
+    pub timestamp: DateTime<Utc>,
+    /// Changes to the payload compared to the first parent.
+    pub payload_diff: Vec<Change>,
+    /// Delegations of the revision.
+    pub delegations: Vec<Delegation>,
+    /// Keys that signed the revision.
+    pub signatures: Vec<Signature>,
+    /// Whether the revision verifies.
+    pub verification: Verification,
+}
+
+/// A change to the payload of a document. `path` is made up of the payload namespace followed by
+/// the keys of nested objects.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Change {
+    /// A field was added.
+    #[serde(rename_all = "camelCase")]
+    Added {
+        /// Path of the field.
+        path: Vec<String>,
+        /// Value of the field.
+        value: Value,
+    },
+    /// A field was removed.
+    #[serde(rename_all = "camelCase")]
+    Removed {
+        /// Path of the field.
+        path: Vec<String>,
+        /// Previous value of the field.
+        value: Value,
+    },
+    /// A field changed its value.
+    #[serde(rename_all = "camelCase")]
+    Changed {
+        /// Path of the field.
+        path: Vec<String>,
+        /// Previous value of the field.
+        old: Value,
+        /// New value of the field.
+        new: Value,
+    },
+}
+
+/// A delegation of an identity document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Delegation {
+    /// A key delegates directly.
+    #[serde(rename_all = "camelCase")]
+    Key {
+        /// The peer of the key.
+        peer_id: PeerId,
+    },
+    /// A person delegates with all their keys. Only project documents delegate to persons.
+    #[serde(rename_all = "camelCase")]
+    Person {
+        /// URN of the person.
+        urn: Urn,
+        /// Peers of the keys of the person, empty if we don't have the person.
+        peer_ids: Vec<PeerId>,
+    },
+}
+
+/// A signature of a revision.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Signature {
+    /// Peer of the signing key.
+    pub peer_id: PeerId,
+    /// Whether the signature is valid for the revision.
+    pub valid: bool,
+}
+
+/// Verification result of a [`Revision`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Verification {
+    /// The revision is signed, and all signatures are valid and made by keys of the delegations.
+    pub signatures: bool,
+    /// A majority of the delegations signed the revision.
+    pub quorum: bool,
+    /// A majority of the delegations of the first parent signed the revision. `None` for the
+    /// first revision.
+    pub parent_quorum: Option<bool>,
+}
+
+/// Walk the revisions of the identity document of `urn`, oldest first.
+///
+/// # Errors
+///
+///   * We don't have the identity.
+///   * A revision is malformed.
+///   * The monorepo could not be read.
+pub async fn revisions(peer: &crate::peer::Peer, urn: &Urn) -> Result<Vec<Revision>, error::Error> {
+    let monorepo = radicle_daemon::state::monorepo(peer.librad_peer());
+    let documents = tokio::task::spawn_blocking({
+        let urn = urn.clone();
+        move || documents(&monorepo, &urn)
+    })
+    .await
+    .expect("task to read identity documents was aborted")?;
+
+    let mut persons = HashMap::new();
+    for document in &documents {
+        for delegation in &document.delegations {
+            if let Delegation::Person { urn, .. } = delegation {
+                if !persons.contains_key(urn) {
+                    let peer_ids = radicle_daemon::state::get_user(peer.librad_peer(), urn.clone())
+                        .await?
+                        .map(|person| crate::identity::Person::from(person).peer_ids)
+                        .unwrap_or_default();
+                    persons.insert(urn.clone(), peer_ids);
+                }
+            }
+        }
+    }
+
+    let mut revisions = Vec::<Revision>::new();
+    for document in documents {
+        let delegations = document
+            .delegations
+            .into_iter()
+            .map(|delegation| match delegation {
+                Delegation::Person { urn, .. } => Delegation::Person {
+                    peer_ids: persons.get(&urn).cloned().unwrap_or_default(),
+                    urn,
+                },
+                delegation @ Delegation::Key { .. } => delegation,
+            })
+            .collect::<Vec<_>>();
+        let parent = document
+            .parents
+            .first()
+            .and_then(|parent| revisions.iter().find(|revision| revision.commit == *parent));
+
+        let payload_diff = diff(&document.parent_payload, &document.payload);
+        let signers = document
+            .signatures
+            .iter()
+            .filter(|signature| signature.valid)
+            .map(|signature| signature.peer_id)
+            .collect::<HashSet<_>>();
+        let verification = Verification {
+            signatures: !document.signatures.is_empty()
+                && document.signatures.iter().all(|signature| {
+                    signature.valid && is_delegate(&delegations, &signature.peer_id)
+                }),
+            quorum: quorum(&delegations, &signers),
+            parent_quorum: parent.map(|parent| quorum(&parent.delegations, &signers)),
+        };
+
+        revisions.push(Revision {
+            commit: document.commit,
+            revision: document.revision,
+            parents: document.parents,
+            timestamp: document.timestamp,
+            payload_diff,
+            delegations,
+            signatures: document.signatures,
+            verification,
+        });
+    }
+
+    Ok(revisions)
+}
+
+/// A revision as read from the monorepo, before delegations to persons are resolved.
+struct Document {
+    commit: Oid,
+    revision: Oid,
+    parents: Vec<Oid>,
+    timestamp: DateTime<Utc>,
+    payload: Value,
+    parent_payload: Value,
+    delegations: Vec<Delegation>,
+    signatures: Vec<Signature>,
+}
+
+/// Read the revisions of the identity document of `urn` from the monorepo at `path`, oldest
+/// first.
+fn documents(path: &std::path::Path, urn: &Urn) -> Result<Vec<Document>, error::Error> {
+    let repo = git2::Repository::open(path)?;
+    let tip = match repo.refname_to_id(&format!("refs/namespaces/{}/refs/rad/id", urn.encode_id()))
+    {
+        Ok(tip) => tip,
+        Err(err) if err.code() == git2::ErrorCode::NotFound => {
+            return Err(error::Error::IdentityNotFound)
+        },
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+    walk.push(tip)?;
+
+    let mut documents = Vec::<Document>::new();
+    for oid in walk {
+        let commit = repo.find_commit(oid?)?;
+        let doc = document(&repo, &commit)?;
+        // Parents come before their children, so the payload of the first parent is known.
+        let parent_payload = commit
+            .parent_ids()
+            .next()
+            .and_then(|parent| {
+                documents
+                    .iter()
+                    .find(|document| document.commit == Oid::from(parent))
+            })
+            .map_or(Value::Null, |parent| parent.payload.clone());
+        let revision = commit.tree_id();
+
+        let signatures = Signatures::from_trailers(commit.message().unwrap_or_default())
+            .map_err(|_| error::Error::MalformedIdentityDocument(commit.id().into()))?
+            .iter()
+            .map(|(key, signature)| Signature {
+                peer_id: PeerId::from(*key),
+                valid: signature.verify(revision.as_bytes(), key),
+            })
+            .collect();
+        let delegations = doc
+            .get("delegations")
+            .and_then(Value::as_array)
+            .ok_or_else(|| error::Error::MalformedIdentityDocument(commit.id().into()))?
+            .iter()
+            .map(|delegation| {
+                let delegation = delegation
+                    .as_str()
+                    .ok_or_else(|| error::Error::MalformedIdentityDocument(commit.id().into()))?;
+                if let Ok(urn) = delegation.parse::<Urn>() {
+                    Ok(Delegation::Person {
+                        urn,
+                        peer_ids: vec![],
+                    })
+                } else if let Ok(peer_id) = delegation.parse::<PeerId>() {
+                    Ok(Delegation::Key { peer_id })
+                } else {
+                    Err(error::Error::MalformedIdentityDocument(commit.id().into()))
+                }
+            })
+            .collect::<Result<Vec<_>, error::Error>>()?;
+
+        documents.push(Document {
+            commit: commit.id().into(),
+            revision: revision.into(),
+            parents: commit.parent_ids().map(Oid::from).collect(),
+            timestamp: Utc.timestamp(commit.time().seconds(), 0),
+            payload: doc.get("payload").cloned().unwrap_or(Value::Null),
+            parent_payload,
+            delegations,
+            signatures,
+        });
+    }
+
+    Ok(documents)
+}
+
+/// The identity document stored in the tree of `commit`.
+fn document(repo: &git2::Repository, commit: &git2::Commit) -> Result<Value, error::Error> {
+    let malformed = || error::Error::MalformedIdentityDocument(commit.id().into());
+    let tree = commit.tree()?;
+    let entry = tree
+        .iter()
+        .find(|entry| entry.kind() == Some(git2::ObjectType::Blob))
+        .ok_or_else(malformed)?;
+    let blob = repo.find_blob(entry.id())?;
+    serde_json::from_slice(blob.content()).map_err(|_| malformed())
+}
+
+/// Whether `peer_id` is one of the keys of `delegations`.
+fn is_delegate(delegations: &[Delegation], peer_id: &PeerId) -> bool {
+    delegations.iter().any(|delegation| match delegation {
+        Delegation::Key { peer_id: key } => key == peer_id,
+        Delegation::Person { peer_ids, .. } => peer_ids.contains(peer_id),
+    })
+}
+
+/// Whether more than half of `delegations` are among `signers`. A person counts as one delegation
+/// that signed if any of their keys did.
+fn quorum(delegations: &[Delegation], signers: &HashSet<PeerId>) -> bool {
+    let signed = delegations
+        .iter()
+        .filter(|delegation| match delegation {
+            Delegation::Key { peer_id } => signers.contains(peer_id),
+            Delegation::Person { peer_ids, .. } => {
+                peer_ids.iter().any(|peer_id| signers.contains(peer_id))
+            },
+        })
+        .count();
+    signed > delegations.len() / 2
+}
+
+/// Changes between the payloads `old` and `new`.
+fn diff(old: &Value, new: &Value) -> Vec<Change> {
+    let mut changes = vec![];
+    diff_at(&mut vec![], old, new, &mut changes);
+    changes
+}
+
+/// Collect the changes between `old` and `new` at `path` into `changes`.
+fn diff_at(path: &mut Vec<String>, old: &Value, new: &Value, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let keys = old.keys().chain(new.keys()).collect::<BTreeSet<_>>();
+            for key in keys {
+                path.push(key.clone());
+                match (old.get(key), new.get(key)) {
+                    (Some(old), Some(new)) => diff_at(path, old, new, changes),
+                    (Some(value), None) => changes.push(Change::Removed {
+                        path: path.clone(),
+                        value: value.clone(),
+                    }),
+                    (None, Some(value)) => changes.push(Change::Added {
+                        path: path.clone(),
+                        value: value.clone(),
+                    }),
+                    (None, None) => {},
+                }
+                path.pop();
+            }
+        },
+        (Value::Null, Value::Object(_)) if path.is_empty() => {
+            diff_at(path, &Value::Object(serde_json::Map::new()), new, changes);
+        },
+        (old, new) if old != new => changes.push(Change::Changed {
+            path: path.clone(),
+            old: old.clone(),
+            new: new.clone(),
+        }),
+        _ => {},
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{Change, Delegation};
+
+    #[test]
+    fn diff() {
+        let ns = "https://radicle.xyz/link/identities/person/v1";
+        assert_eq!(
+            super::diff(&json!(null), &json!({ ns: { "name": "cloudhead" } })),
+            vec![Change::Added {
+                path: vec![ns.into()],
+                value: json!({ "name": "cloudhead" }),
+            }]
+        );
+        assert_eq!(
+            super::diff(
+                &json!({ ns: { "name": "cloudhead" }, "ext": { "a": 1 } }),
+                &json!({ ns: { "name": "cloudhead_next" } })
+            ),
+            vec![
+                Change::Removed {
+                    path: vec!["ext".into()],
+                    value: json!({ "a": 1 }),
+                },
+                Change::Changed {
+                    path: vec![ns.into(), "name".into()],
+                    old: json!("cloudhead"),
+                    new: json!("cloudhead_next"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn quorum() {
+        let alice = crate::control::generate_peer_id();
+        let bob = crate::control::generate_peer_id();
+        let carol = crate::control::generate_peer_id();
+        let delegations = vec![
+            Delegation::Key { peer_id: alice },
+            Delegation::Key { peer_id: bob },
+            Delegation::Key { peer_id: carol },
+        ];
+
+        assert!(!super::quorum(
+            &delegations,
+            &vec![alice].into_iter().collect::<HashSet<_>>()
+        ));
+        assert!(super::quorum(
+            &delegations,
+            &vec![alice, carol].into_iter().collect::<HashSet<_>>()
+        ));
+        assert!(super::is_delegate(&delegations, &bob));
+    }
+}
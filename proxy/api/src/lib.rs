@@ -15,6 +15,7 @@
 #![cfg_attr(not(test), warn(clippy::unwrap_used))]
 #![allow(clippy::multiple_crate_versions)]
 
+mod blocklist;
 mod browser;
 mod cli;
 mod config;
@@ -36,6 +37,7 @@ pub mod init_bin;
 mod keystore;
 mod notification;
 mod patch;
+mod petname;
 mod process;
 mod project;
 mod service;
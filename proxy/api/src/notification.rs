@@ -6,8 +6,9 @@
 
 //! Machinery to signal significant events to clients.
 
+use futures::prelude::*;
 use serde::Serialize;
-use std::{collections::HashMap, time::SystemTime};
+use std::{collections::HashMap, path::PathBuf, time::SystemTime};
 
 use link_crypto::PeerId;
 use link_identities::git::Urn;
@@ -57,6 +58,16 @@ pub enum Notification {
         /// The new [`radicle_daemon::PeerStatus`].
         new: radicle_daemon::PeerStatus,
     },
+    /// A working copy checkout made progress.
+    #[serde(rename_all = "camelCase")]
+    CheckoutProgress {
+        /// Urn of the checked out project.
+        urn: Urn,
+        /// Location the working copy is created in.
+        path: PathBuf,
+        /// The stage the checkout reached.
+        progress: crate::project::checkout::Progress,
+    },
     WaitingRoomTransition {
         event: radicle_daemon::peer::WaitingRoomEvent,
         state_before: SerializableWaitingRoomState,
@@ -65,6 +76,40 @@ pub enum Notification {
     },
 }
 
+/// Fans out notifications that originate in the proxy itself rather than in the peer.
+#[derive(Clone)]
+pub struct Local(tokio::sync::broadcast::Sender<Notification>);
+
+impl Local {
+    /// Number of notifications a subscriber can lag behind before it misses some.
+    const CAPACITY: usize = 64;
+
+    /// Create a new broadcaster without subscribers.
+    #[must_use]
+    pub fn new() -> Self {
+        Self(tokio::sync::broadcast::channel(Self::CAPACITY).0)
+    }
+
+    /// Send `notification` to all current subscribers.
+    pub fn send(&self, notification: Notification) {
+        // Sending only fails if nobody is listening, in which case nobody misses anything.
+        self.0.send(notification).ok();
+    }
+
+    /// Return a stream of all notifications sent after subscribing. Notifications a slow
+    /// subscriber lagged behind on are skipped.
+    pub fn subscribe(&self) -> impl Stream<Item = Notification> + Send + 'static {
+        tokio_stream::wrappers::BroadcastStream::new(self.0.subscribe())
+            .filter_map(|notification| future::ready(notification.ok()))
+    }
+}
+
+impl Default for Local {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[allow(clippy::wildcard_enum_match_arm)]
 pub fn from_peer_event(event: radicle_daemon::PeerEvent) -> Option<Notification> {
     match event {
@@ -16,7 +16,8 @@ use link_identities::git::Urn;
 
 use crate::project;
 
-const TAG_PREFIX: &str = "radicle-patch/";
+/// Prefix of the tags that make up patches.
+pub(crate) const TAG_PREFIX: &str = "radicle-patch/";
 
 /// A patch is a change set that a user wants the maintainer to merge into a projects default
 /// branch.
@@ -38,15 +39,18 @@ pub struct Patch {
     pub merge_base: Option<Oid>,
 }
 
-/// List all patches for the given project.
+/// List all patches for the given project. Patches of blocked peers are left out.
 ///
 /// # Errors
 /// * Cannot access the monorepo
 /// * Cannot find references within the monorepo
+/// * Cannot read the blocklist from the store
 pub async fn list(
     peer: &crate::peer::Peer,
+    store: &kv::Store,
     project_urn: Urn,
 ) -> Result<Vec<Patch>, crate::error::Error> {
+    let blocklist = crate::session::blocklist(store)?;
     let mut patches = Vec::new();
 
     let default_branch_head_commit_id = {
@@ -82,6 +86,10 @@ pub async fn list(
             radicle_daemon::project::Peer::Local { .. } => None,
             radicle_daemon::project::Peer::Remote { peer_id, .. } => Some(*peer_id),
         };
+        if remote.map_or(false, |peer_id| blocklist.contains(&peer_id)) {
+            continue;
+        }
+        let patch_peer = project::Peer::from(project_peer).with_petnames(store)?;
 
         let ref_scope = match remote {
             Some(remote) => RefScope::Remote {
@@ -131,7 +139,7 @@ pub async fn list(
                                 .map(Oid::from);
                             patches.push(Patch {
                                 id,
-                                peer: project_peer.clone().into(),
+                                peer: patch_peer.clone(),
                                 message,
                                 commit: Oid::from(target_id),
                                 merge_base,
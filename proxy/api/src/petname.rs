@@ -0,0 +1,295 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Local address book of petnames for peers and persons.
+//!
+//! Handles are chosen by their owners and are not unique. A petname is the name the user chose
+//! for a peer or person, together with a private note. Petnames are kept in the [`kv::Store`] and
+//! never leave the device. When a person has a petname and one of their peers has another, the
+//! petname of the person wins.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use link_crypto::PeerId;
+use link_identities::git::Urn;
+
+use crate::error;
+
+/// Name for the storage bucket used for petnames.
+const BUCKET_NAME: &str = "petnames";
+
+/// Who a petname is given to.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Subject {
+    /// A person identified by the URN of their identity.
+    Person(Urn),
+    /// A single peer.
+    Peer(PeerId),
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Person(urn) => write!(f, "{}", urn),
+            Self::Peer(peer_id) => write!(f, "{}", peer_id),
+        }
+    }
+}
+
+impl FromStr for Subject {
+    type Err = error::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(urn) = s.parse() {
+            Ok(Self::Person(urn))
+        } else if let Ok(peer_id) = s.parse() {
+            Ok(Self::Peer(peer_id))
+        } else {
+            Err(error::Error::InvalidPetnameSubject(s.to_string()))
+        }
+    }
+}
+
+/// Petname and note for a [`Subject`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Petname {
+    /// Name the user knows the subject by.
+    pub petname: String,
+    /// Private note about the subject.
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// An entry of the address book.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    /// Who the petname is given to.
+    pub subject: Subject,
+    /// The petname and note.
+    #[serde(flatten)]
+    pub petname: Petname,
+}
+
+/// Petname of `subject`, if any.
+///
+/// # Errors
+///
+/// Errors if we cannot read from the store.
+pub fn get(store: &kv::Store, subject: &Subject) -> Result<Option<Petname>, error::Error> {
+    Ok(store
+        .bucket::<&str, kv::Json<Petname>>(Some(BUCKET_NAME))?
+        .get(subject.to_string().as_str())?
+        .map(|json| json.0))
+}
+
+/// All entries of the address book, sorted by petname.
+///
+/// # Errors
+///
+/// Errors if we cannot read from the store or an entry is malformed.
+pub fn list(store: &kv::Store) -> Result<Vec<Entry>, error::Error> {
+    let bucket = store.bucket::<&str, kv::Json<Petname>>(Some(BUCKET_NAME))?;
+    let mut entries = vec![];
+    for item in bucket.iter() {
+        let item = item?;
+        let subject = match item.key::<&str>()?.parse() {
+            Ok(subject) => subject,
+            Err(err) => {
+                tracing::warn!(?err, "ignoring petname with malformed subject");
+                continue;
+            },
+        };
+        entries.push(Entry {
+            subject,
+            petname: item.value::<kv::Json<Petname>>()?.0,
+        });
+    }
+    entries.sort_by(|a, b| a.petname.petname.cmp(&b.petname.petname));
+    Ok(entries)
+}
+
+/// Give `subject` the petname `petname`. Petnames are trimmed and must not be empty or contain
+/// `@`, so they can't be confused with identifiers. No two subjects can have the same petname,
+/// ignoring case, so that [`find`] is unambiguous.
+///
+/// # Errors
+///
+///   * The petname is invalid.
+///   * Another subject already has the petname.
+///   * We cannot read from or write to the store.
+pub fn set(
+    store: &kv::Store,
+    subject: &Subject,
+    petname: Petname,
+) -> Result<Petname, error::Error> {
+    let petname = Petname {
+        petname: petname.petname.trim().to_string(),
+        note: petname
+            .note
+            .map(|note| note.trim().to_string())
+            .filter(|note| !note.is_empty()),
+    };
+    if petname.petname.is_empty() || petname.petname.contains('@') {
+        return Err(error::Error::InvalidPetname(petname.petname));
+    }
+    if let Some(entry) = find(store, &petname.petname)? {
+        if entry.subject != *subject {
+            return Err(error::Error::PetnameTaken(petname.petname));
+        }
+    }
+
+    store
+        .bucket::<&str, kv::Json<Petname>>(Some(BUCKET_NAME))?
+        .set(subject.to_string().as_str(), kv::Json(petname.clone()))?;
+    Ok(petname)
+}
+
+/// Remove the petname of `subject`.
+///
+/// # Errors
+///
+/// Errors if we cannot write to the store.
+pub fn remove(store: &kv::Store, subject: &Subject) -> Result<(), error::Error> {
+    store
+        .bucket::<&str, kv::Json<Petname>>(Some(BUCKET_NAME))?
+        .remove(subject.to_string().as_str())?;
+    Ok(())
+}
+
+/// Find the entry with petname `petname`. Petnames are compared case-insensitively. Should the
+/// store contain duplicates from before petnames were unique, persons take precedence over peers.
+///
+/// # Errors
+///
+/// Errors if we cannot read from the store.
+pub fn find(store: &kv::Store, petname: &str) -> Result<Option<Entry>, error::Error> {
+    let mut found = list(store)?
+        .into_iter()
+        .filter(|entry| entry.petname.petname.eq_ignore_ascii_case(petname.trim()))
+        .collect::<Vec<_>>();
+    found.sort_by_key(|entry| matches!(entry.subject, Subject::Peer(_)));
+    Ok(found.into_iter().next())
+}
+
+/// Petname of the person `urn`, or if they have none, of the first of `peer_ids` with one.
+///
+/// # Errors
+///
+/// Errors if we cannot read from the store.
+pub fn lookup(
+    store: &kv::Store,
+    urn: Option<&Urn>,
+    peer_ids: &[PeerId],
+) -> Result<Option<String>, error::Error> {
+    if let Some(urn) = urn {
+        if let Some(petname) = get(store, &Subject::Person(urn.clone()))? {
+            return Ok(Some(petname.petname));
+        }
+    }
+    for peer_id in peer_ids {
+        if let Some(petname) = get(store, &Subject::Peer(*peer_id))? {
+            return Ok(Some(petname.petname));
+        }
+    }
+    Ok(None)
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::{Petname, Subject};
+
+    #[test]
+    fn address_book() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let alice = crate::control::generate_peer_id();
+        let bob = crate::control::generate_peer_id();
+
+        super::set(
+            &store,
+            &Subject::Peer(alice),
+            Petname {
+                petname: " Alice (work) ".into(),
+                note: Some("  ".into()),
+            },
+        )?;
+        assert_eq!(
+            super::get(&store, &Subject::Peer(alice))?,
+            Some(Petname {
+                petname: "Alice (work)".into(),
+                note: None,
+            })
+        );
+        assert!(super::set(
+            &store,
+            &Subject::Peer(bob),
+            Petname {
+                petname: "bob@home".into(),
+                note: None,
+            },
+        )
+        .is_err());
+
+        assert_eq!(
+            super::find(&store, "alice (WORK)")?.map(|entry| entry.subject),
+            Some(Subject::Peer(alice))
+        );
+        assert!(super::find(&store, "bob")?.is_none());
+        assert!(matches!(
+            super::set(
+                &store,
+                &Subject::Peer(bob),
+                Petname {
+                    petname: "ALICE (work)".into(),
+                    note: None,
+                },
+            ),
+            Err(crate::error::Error::PetnameTaken(_))
+        ));
+        super::set(
+            &store,
+            &Subject::Peer(alice),
+            Petname {
+                petname: "alice (Work)".into(),
+                note: Some("renamed".into()),
+            },
+        )?;
+        assert_eq!(
+            super::lookup(&store, None, &[bob, alice])?,
+            Some("alice (Work)".into())
+        );
+        assert_eq!(
+            super::list(&store)?
+                .into_iter()
+                .map(|entry| entry.subject)
+                .collect::<Vec<_>>(),
+            vec![Subject::Peer(alice)]
+        );
+
+        super::remove(&store, &Subject::Peer(alice))?;
+        assert_eq!(super::lookup(&store, None, &[alice])?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn subject() {
+        let peer_id = crate::control::generate_peer_id();
+        assert_eq!(
+            peer_id.to_string().parse::<Subject>().unwrap(),
+            Subject::Peer(peer_id)
+        );
+        assert!("alice".parse::<Subject>().is_err());
+    }
+}
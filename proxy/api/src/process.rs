@@ -14,7 +14,10 @@ use anyhow::Context;
 use futures::prelude::*;
 use tokio::sync::{watch, RwLock};
 
-use crate::{cli::Args, config, context, git_helper, http, service, session};
+use crate::{
+    blocklist, cli::Args, config, context, git_helper, http, notification, project, service,
+    session,
+};
 
 /// Run the proxy process
 ///
@@ -106,6 +109,7 @@ async fn run_session(
         keystore: environment.keystore.clone(),
         paths: paths.clone(),
         shutdown: Arc::new(tokio::sync::Notify::new()),
+        notifications: notification::Local::new(),
     };
 
     let mut shutdown_runner = crate::shutdown_runner::ShutdownRunner::new();
@@ -129,12 +133,34 @@ async fn run_session(
         let (peer, peer_runner) = crate::peer::create(crate::peer::Config {
             paths: paths.clone(),
             key,
-            store,
+            store: store.clone(),
             discovery,
             listen: args.peer_listen,
         })?;
 
         tokio::task::spawn(log_daemon_peer_events(peer.events()));
+        // Everything acting on peer events ignores the gossip of blocked peers.
+        let events = || blocklist::filter_events(store.clone(), peer.events());
+        tokio::task::spawn(project::cache::invalidate_on_updates(
+            store.clone(),
+            events(),
+        ));
+        tokio::task::spawn(project::sync::record_fetches(store.clone(), events()));
+        tokio::task::spawn(project::activity::record(
+            peer.clone(),
+            store.clone(),
+            events(),
+        ));
+        tokio::task::spawn(project::tracking::enforce(
+            peer.clone(),
+            store.clone(),
+            events(),
+        ));
+        tokio::task::spawn(project::request::supervise(
+            peer.clone(),
+            store.clone(),
+            events(),
+        ));
 
         shutdown_runner.add_with_shutdown(|shutdown| {
             peer_runner
@@ -7,8 +7,9 @@
 //! Combine the domain `CoCo` domain specific understanding of a Project into a single
 //! abstraction.
 
-use std::{collections::HashSet, convert::TryFrom, ops::Deref};
+use std::{collections::HashSet, convert::TryFrom, ops::Deref, path::Path};
 
+use either::Either;
 use serde::{Deserialize, Serialize};
 
 use link_identities::{git::Urn, Person, Project as LinkProject};
@@ -16,6 +17,23 @@ use radicle_source::surf::vcs::git::Stats;
 
 use crate::{browser, error, identity};
 
+pub mod activity;
+pub mod annotation;
+pub mod bundle;
+pub mod cache;
+pub mod checkout;
+pub mod contents;
+pub mod import;
+pub mod maintainer;
+pub mod raw;
+pub mod readme;
+pub mod repair;
+pub mod request;
+pub mod sync;
+pub mod template;
+pub mod tracking;
+pub mod working_copy;
+
 /// Object encapsulating project metadata.
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -67,29 +85,35 @@ impl TryFrom<LinkProject> for Metadata {
 /// See [`Projects`] for a detailed breakdown of both kinds of projects.
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-pub struct Project<S> {
+pub struct Project<S, C> {
     /// Unique identifier of the project in the network.
     pub urn: Urn,
     /// Attached metadata, mostly for human pleasure.
     pub metadata: Metadata,
     /// High-level statistics about the project
     pub stats: S,
+    /// License and languages of the project.
+    #[serde(flatten)]
+    pub contents: C,
 }
 
 /// A `Partial` project is one where we _weren't_ able to fetch the [`Stats`] for it.
-pub type Partial = Project<()>;
+pub type Partial = Project<(), ()>;
 
-/// A `Full` project is one where we _were_ able to fetch the [`Stats`] for it.
-pub type Full = Project<Stats>;
+/// A `Full` project is one where we _were_ able to fetch the [`Stats`] and [`contents::Contents`]
+/// for it.
+pub type Full = Project<Stats, contents::Contents>;
 
 impl Partial {
-    /// Convert a `Partial` project into a `Full` one by providing the `stats` for the project.
+    /// Convert a `Partial` project into a `Full` one by providing the `stats` and `contents` for
+    /// the project.
     #[allow(clippy::missing_const_for_fn)]
-    pub fn fulfill(self, stats: Stats) -> Full {
+    pub fn fulfill(self, stats: Stats, contents: contents::Contents) -> Full {
         Project {
             urn: self.urn,
             metadata: self.metadata,
             stats,
+            contents,
         }
     }
 }
@@ -108,17 +132,20 @@ impl TryFrom<LinkProject> for Partial {
             urn,
             metadata,
             stats: (),
+            contents: (),
         })
     }
 }
 
-/// Construct a Project from its metadata and stats
-impl TryFrom<(LinkProject, Stats)> for Full {
+/// Construct a Project from its metadata, stats and contents
+impl TryFrom<(LinkProject, Stats, contents::Contents)> for Full {
     type Error = error::Error;
 
-    /// Create a `Project` given a [`LinkProject`] and the [`Stats`]
+    /// Create a `Project` given a [`LinkProject`], the [`Stats`] and the [`contents::Contents`]
     /// for the repository.
-    fn try_from((project, stats): (LinkProject, Stats)) -> Result<Self, Self::Error> {
+    fn try_from(
+        (project, stats, contents): (LinkProject, Stats, contents::Contents),
+    ) -> Result<Self, Self::Error> {
         let urn = project.urn();
         let metadata = Metadata::try_from(project)?;
 
@@ -126,15 +153,71 @@ impl TryFrom<(LinkProject, Stats)> for Full {
             urn,
             metadata,
             stats,
+            contents,
         })
     }
 }
 
 /// Codified relation in form of roles and availability of project views.
 #[derive(Debug, Clone, Serialize)]
-pub struct Peer(
-    radicle_daemon::project::peer::Peer<radicle_daemon::project::peer::Status<identity::Identity>>,
-);
+#[serde(rename_all = "camelCase")]
+pub struct Peer {
+    /// Role and replication status of the peer.
+    #[serde(flatten)]
+    peer: radicle_daemon::project::peer::Peer<
+        radicle_daemon::project::peer::Status<identity::Identity>,
+    >,
+    /// How up to date we are with a remote peer, if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sync: Option<sync::Status>,
+    /// Name the local user gave the peer or its person, see [`crate::petname`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    petname: Option<String>,
+}
+
+impl Peer {
+    /// Attach the [`sync::Status`] of the local view of the project with respect to the peer.
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn with_sync(self, sync: sync::Status) -> Self {
+        Self {
+            sync: Some(sync),
+            ..self
+        }
+    }
+
+    /// Attach the petnames of the peer and its person from the address book in `store`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if we cannot read from the store.
+    pub fn with_petnames(self, store: &kv::Store) -> Result<Self, error::Error> {
+        let peer_petname = crate::petname::lookup(store, None, &[self.peer.peer_id()])?;
+        let mut user_petname = None;
+        let mut failed = None;
+        let peer = self.peer.map(|status| {
+            status.map(|user| match user.clone().with_petname(store) {
+                Ok(user) => {
+                    user_petname = user.petname.clone();
+                    user
+                },
+                Err(err) => {
+                    failed = Some(err);
+                    user
+                },
+            })
+        });
+        if let Some(err) = failed {
+            return Err(err);
+        }
+
+        Ok(Self {
+            peer,
+            sync: self.sync,
+            petname: user_petname.or(peer_petname),
+        })
+    }
+}
 
 impl Deref for Peer {
     type Target = radicle_daemon::project::peer::Peer<
@@ -142,7 +225,7 @@ impl Deref for Peer {
     >;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.peer
     }
 }
 
@@ -153,7 +236,11 @@ impl From<radicle_daemon::project::peer::Peer<radicle_daemon::project::peer::Sta
         peer: radicle_daemon::project::peer::Peer<radicle_daemon::project::peer::Status<Person>>,
     ) -> Self {
         let peer_id = peer.peer_id();
-        Self(peer.map(|status| status.map(|user| (peer_id, user).into())))
+        Self {
+            peer: peer.map(|status| status.map(|user| (peer_id, user).into())),
+            sync: None,
+            petname: None,
+        }
     }
 }
 
@@ -204,6 +291,10 @@ pub struct Projects {
 
     /// A project that failed partially when trying to retrieve metadata for it.
     pub failures: Vec<Failure>,
+
+    /// `false` if the stats of any project were served from an outdated cache entry. Up to date
+    /// stats are computed in the background and will be served by a subsequent listing.
+    pub fresh: bool,
 }
 
 impl Projects {
@@ -217,11 +308,12 @@ impl Projects {
     ///   * We couldn't get the list of projects
     ///   * We couldn't inspect the `signed_refs` of the project
     ///   * We couldn't get stats for a project
-    pub async fn list(peer: &crate::peer::Peer) -> Result<Self, error::Error> {
+    pub async fn list(peer: &crate::peer::Peer, store: &kv::Store) -> Result<Self, error::Error> {
         let mut projects = Self {
             tracked: vec![],
             contributed: vec![],
             failures: vec![],
+            fresh: true,
         };
 
         for project in radicle_daemon::state::list_projects(peer.librad_peer()).await? {
@@ -240,18 +332,17 @@ impl Projects {
                 Ok(branch) => branch,
             };
 
-            let stats = match browser::using(peer, default_branch, |browser| {
-                Ok(browser.get_stats()?)
-            }) {
+            let cached = match cache::stats(peer, store, &project.urn, default_branch) {
                 Err(err) => {
                     tracing::warn!(project_urn = %project.urn, ?err, "cannot get project stats");
                     projects.failures.push(Failure::Stats(project));
                     continue;
                 },
-                Ok(stats) => stats,
+                Ok(cached) => cached,
             };
+            projects.fresh &= cached.fresh;
 
-            let project = project.fulfill(stats);
+            let project = project.fulfill(cached.stats, cached.contents);
 
             let refs =
                 match radicle_daemon::state::load_refs(peer.librad_peer(), project.urn.clone())
@@ -279,6 +370,29 @@ impl Projects {
 
         Ok(projects)
     }
+
+    /// Keep only the tracked and contributed projects whose [`annotation::Annotation`] matches
+    /// `filter`.
+    ///
+    /// # Errors
+    ///
+    ///   * We couldn't read the annotations from the store.
+    pub fn retain_annotated(
+        &mut self,
+        store: &kv::Store,
+        filter: &annotation::Filter,
+    ) -> Result<(), error::Error> {
+        let annotations = annotation::list(store)?;
+        let matches = |urn: &Urn| {
+            annotations.get(&urn.to_string()).map_or_else(
+                || filter.matches(&annotation::Annotation::default()),
+                |annotation| filter.matches(annotation),
+            )
+        };
+        self.tracked.retain(|project| matches(&project.urn));
+        self.contributed.retain(|project| matches(&project.urn));
+        Ok(())
+    }
 }
 
 /// An iterator over [`Projects`] that first yields contributed projects and then tracked projects.
@@ -350,9 +464,250 @@ pub async fn get(peer: &crate::peer::Peer, project_urn: Urn) -> Result<Full, err
 
     let branch =
         radicle_daemon::state::find_default_branch(peer.librad_peer(), project_urn.clone()).await?;
+    let project_contents = contents::detect(peer, &branch)?;
     let project_stats = browser::using(peer, branch, |browser| Ok(browser.get_stats()?))?;
 
-    Full::try_from((project, project_stats))
+    Full::try_from((project, project_stats, project_contents))
+}
+
+/// User provided changes to the [`Metadata`] of a project. Fields that are `None` are left as they
+/// are.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataUpdate {
+    /// New project name.
+    pub name: Option<String>,
+    /// New high-level description of the project.
+    pub description: Option<String>,
+    /// New default branch. The branch must exist in the local peer's view of the project.
+    pub default_branch: Option<String>,
+}
+
+/// Update the metadata of the project `urn` by signing a new revision of its identity document.
+///
+/// # Errors
+///
+///   * The project could not be found.
+///   * The local identity is not a delegate of the project.
+///   * The new default branch does not exist.
+///   * Signing or storing the new revision failed.
+pub async fn update(
+    peer: &crate::peer::Peer,
+    owner: &radicle_daemon::LocalIdentity,
+    urn: Urn,
+    update: MetadataUpdate,
+) -> Result<Full, error::Error> {
+    let project = radicle_daemon::state::get_project(peer.librad_peer(), urn.clone())
+        .await?
+        .ok_or(error::Error::ProjectNotFound)?;
+    ensure_delegate(peer, owner, &project)?;
+
+    let mut payload = project.payload().clone();
+    if let Some(name) = update.name {
+        payload.subject.name = name.into();
+    }
+    if let Some(description) = update.description {
+        payload.subject.description = Some(description.into());
+    }
+    if let Some(default_branch) = update.default_branch {
+        payload.subject.default_branch = Some(default_branch.clone().into());
+        match radicle_daemon::state::get_branch(
+            peer.librad_peer(),
+            urn.clone(),
+            None,
+            payload.subject.default_branch.clone(),
+        )
+        .await
+        {
+            Ok(_) => {},
+            Err(radicle_daemon::state::Error::MissingRef { .. }) => {
+                return Err(error::Error::BranchNotFound(default_branch));
+            },
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    let delegations = project.delegations().clone();
+    peer.librad_peer()
+        .using_storage({
+            let urn = urn.clone();
+            let owner = owner.clone();
+            move |storage| {
+                librad::git::identities::project::update(
+                    storage,
+                    &urn,
+                    Some(owner),
+                    Some(payload),
+                    Some(delegations),
+                )
+            }
+        })
+        .await??;
+
+    get(peer, urn).await
+}
+
+/// List the peers of project `urn` with their petnames and the [`sync::Status`] of every remote
+/// peer, if the project has a default branch. Blocked peers are left out. Peers whose sync status
+/// can't be computed are listed without one.
+///
+/// # Errors
+///
+///   * The project could not be found.
+///   * The peers could not be listed.
+pub async fn peers(
+    peer: &crate::peer::Peer,
+    store: &kv::Store,
+    urn: Urn,
+) -> Result<Vec<Peer>, error::Error> {
+    let project = radicle_daemon::state::get_project(peer.librad_peer(), urn.clone())
+        .await?
+        .ok_or(error::Error::ProjectNotFound)?;
+    let default_branch = project
+        .subject()
+        .default_branch
+        .as_ref()
+        .map(ToString::to_string);
+
+    let blocklist = crate::session::blocklist(store)?;
+    let mut peers = vec![];
+    for project_peer in
+        radicle_daemon::state::list_project_peers(peer.librad_peer(), urn.clone()).await?
+    {
+        let remote = match &project_peer {
+            radicle_daemon::project::Peer::Local { .. } => None,
+            radicle_daemon::project::Peer::Remote { peer_id, .. } => Some(*peer_id),
+        };
+        if remote.map_or(false, |peer_id| blocklist.contains(&peer_id)) {
+            continue;
+        }
+        let project_peer = Peer::from(project_peer).with_petnames(store)?;
+        peers.push(match (remote, &default_branch) {
+            (Some(peer_id), Some(default_branch)) => {
+                match sync::status(peer, store, &urn, default_branch, peer_id).await {
+                    Ok(status) => project_peer.with_sync(status),
+                    // A single peer we can't compare with must not hide the others, it is listed
+                    // with an unknown sync status.
+                    Err(err) => {
+                        tracing::warn!(project_urn = %urn, %peer_id, ?err, "cannot get sync status");
+                        project_peer
+                    },
+                }
+            },
+            // Without a default branch there is nothing to compare, the peer is listed without
+            // its sync status.
+            (Some(_), None) | (None, _) => project_peer,
+        });
+    }
+
+    Ok(peers)
+}
+
+/// Remove the project `urn` from the monorepo.
+///
+/// All peers of the project are untracked, the refs under the project namespace are deleted and
+/// any data cached for the project is dropped. The registered working copies are forgotten. If
+/// `remove_working_copies` is set, the ones the proxy checked out are deleted from disk as long as
+/// they are still git repositories whose `rad` remote points at the project.
+///
+/// # Errors
+///
+///   * The project could not be found.
+///   * Untracking a peer failed.
+///   * The refs could not be deleted.
+///   * A working copy could not be deleted.
+pub async fn forget(
+    peer: &crate::peer::Peer,
+    store: &kv::Store,
+    urn: Urn,
+    remove_working_copies: bool,
+) -> Result<(), error::Error> {
+    radicle_daemon::state::get_project(peer.librad_peer(), urn.clone())
+        .await?
+        .ok_or(error::Error::ProjectNotFound)?;
+
+    for project_peer in radicle_daemon::state::tracked(peer.librad_peer(), urn.clone()).await? {
+        radicle_daemon::state::untrack(peer.librad_peer(), urn.clone(), project_peer.peer_id())
+            .await?;
+    }
+
+    let monorepo = radicle_daemon::state::monorepo(peer.librad_peer());
+    let namespace_glob = format!("refs/namespaces/{}/*", urn.encode_id());
+    tokio::task::spawn_blocking(move || -> Result<(), error::Error> {
+        let repo = radicle_source::surf::vcs::git::git2::Repository::open(monorepo)?;
+        for reference in repo.references_glob(&namespace_glob)? {
+            reference?.delete()?;
+        }
+        Ok(())
+    })
+    .await
+    .expect("task to delete project refs was aborted")?;
+
+    cache::remove(store, &urn)?;
+    sync::remove(store, &urn)?;
+    annotation::remove(store, &urn)?;
+    activity::remove(store, &urn)?;
+    request::remove(store, &urn)?;
+    crate::session::update_tracking_policy(
+        store,
+        &urn,
+        crate::session::settings::TrackingPolicy::Manual,
+    )?;
+
+    for registration in working_copy::unregister_all(store, &urn)? {
+        if remove_working_copies
+            && registration.origin == working_copy::Origin::Checkout
+            && working_copy::is_checkout_of(&registration.path, &urn)
+        {
+            tokio::fs::remove_dir_all(&registration.path).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Run `git` with `args` in the repository at `dir` and return its standard output.
+async fn git(dir: &Path, args: &[String]) -> Result<String, error::Error> {
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        // There is no terminal to ask for credentials, fail instead of waiting for input.
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .await?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(error::Error::GitCommand {
+            args: args.join(" "),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+}
+
+/// Reject with [`error::Error::NotADelegate`] unless `owner` or the key of the local peer is a
+/// delegate of `project`.
+fn ensure_delegate(
+    peer: &crate::peer::Peer,
+    owner: &radicle_daemon::LocalIdentity,
+    project: &LinkProject,
+) -> Result<(), error::Error> {
+    let peer_id = peer.librad_peer().peer_id();
+    let is_delegate = project
+        .delegations()
+        .iter()
+        .any(|delegation| match delegation {
+            Either::Left(key) => *key == *peer_id.as_public_key(),
+            Either::Right(person) => person.urn() == owner.urn(),
+        });
+
+    if is_delegate {
+        Ok(())
+    } else {
+        Err(error::Error::NotADelegate)
+    }
 }
 
 /// This lists all the projects for a given `user`. This `user` should not be your particular
@@ -362,19 +717,25 @@ pub async fn get(peer: &crate::peer::Peer, project_urn: Urn) -> Result<Full, err
 /// to. This is because we can only know our projects (local-first) and the users that we track
 /// for those projects.
 ///
-/// TODO(finto): We would like to also differentiate whether these are tracked or contributed to
-/// for this given user. See <https://github.com/radicle-dev/radicle-upstream/issues/915>
+/// A project is considered contributed to by `user` if the signed refs of their peer contain at
+/// least one branch. Otherwise it is considered tracked. Like in [`Projects::list`], the stats are
+/// those of the default branch of the project and may be served from the cache.
 ///
 /// # Errors
 ///
 /// * We couldn't get a project list.
-/// * We couldn't get project stats.
 /// * We couldn't determine the tracking peers of a project.
 pub async fn list_for_user(
     peer: &crate::peer::Peer,
+    store: &kv::Store,
     user: &Urn,
-) -> Result<Vec<Full>, error::Error> {
-    let mut projects = vec![];
+) -> Result<Projects, error::Error> {
+    let mut projects = Projects {
+        tracked: vec![],
+        contributed: vec![],
+        failures: vec![],
+        fresh: true,
+    };
 
     for project in radicle_daemon::state::list_projects(peer.librad_peer()).await? {
         let tracked = radicle_daemon::state::tracked(peer.librad_peer(), project.urn())
@@ -382,19 +743,85 @@ pub async fn list_for_user(
             .into_iter()
             .filter_map(radicle_daemon::project::Peer::replicated_remote)
             .find(|(_, project_user)| project_user.urn() == *user);
-        if let Some((peer_id, _)) = tracked {
-            let subject = project.subject();
-            let branch = radicle_daemon::state::get_branch(
-                peer.librad_peer(),
-                project.urn(),
-                peer_id,
-                subject.default_branch.clone(),
-            )
-            .await?;
-            let stats = browser::using(peer, branch, |browser| Ok(browser.get_stats()?))?;
-            let full = Full::try_from((project, stats))?;
+        let peer_id = match tracked {
+            Some((peer_id, _)) => peer_id,
+            None => continue,
+        };
+
+        let project = Project::try_from(project)?;
+        let default_branch = match radicle_daemon::state::find_default_branch(
+            peer.librad_peer(),
+            project.urn.clone(),
+        )
+        .await
+        {
+            Err(err) => {
+                tracing::warn!(project_urn = %project.urn, ?err, "cannot find default branch");
+                projects.failures.push(Failure::DefaultBranch(project));
+                continue;
+            },
+            Ok(branch) => branch,
+        };
+
+        let cached = match cache::stats(peer, store, &project.urn, default_branch) {
+            Err(err) => {
+                tracing::warn!(project_urn = %project.urn, ?err, "cannot get project stats");
+                projects.failures.push(Failure::Stats(project));
+                continue;
+            },
+            Ok(cached) => cached,
+        };
+        projects.fresh &= cached.fresh;
+
+        let project = project.fulfill(cached.stats, cached.contents);
+
+        let refs = match peer
+            .librad_peer()
+            .using_storage({
+                let urn = project.urn.clone();
+                move |storage| librad::git::refs::Refs::load(storage, &urn, Some(peer_id))
+            })
+            .await
+        {
+            Ok(Ok(refs)) => refs,
+            Ok(Err(err)) => {
+                tracing::warn!(project_urn = %project.urn, %peer_id, ?err, "cannot load refs");
+                projects.failures.push(Failure::SignedRefs(project));
+                continue;
+            },
+            Err(err) => return Err(err.into()),
+        };
 
-            projects.push(full);
+        match refs {
+            Some(refs) if refs.heads().next().is_some() => projects.contributed.push(project),
+            _ => projects.tracked.push(Tracked(project)),
+        }
+    }
+
+    Ok(projects)
+}
+
+/// Fetch the projects that have the person `maintainer` as a delegate, see
+/// [`Metadata::maintainers`]. Projects without a default branch are skipped.
+///
+/// # Errors
+///
+/// * We couldn't get a project list.
+pub async fn list_maintained_by(
+    peer: &crate::peer::Peer,
+    maintainer: &Urn,
+) -> Result<Vec<Partial>, error::Error> {
+    let mut projects = vec![];
+    for project in radicle_daemon::state::list_projects(peer.librad_peer()).await? {
+        let urn = project.urn();
+        match Partial::try_from(project) {
+            Ok(project) if project.metadata.maintainers.contains(maintainer) => {
+                projects.push(project);
+            },
+            Ok(_) => {},
+            Err(err) => {
+                tracing::warn!(project_urn = %urn, ?err, "cannot read project metadata");
+            },
         }
     }
     Ok(projects)
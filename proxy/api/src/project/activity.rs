@@ -0,0 +1,367 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Persistent feed of what happened to a project while the proxy was running.
+//!
+//! [`crate::notification::Notification`]s are only delivered to connected clients. To not lose
+//! what happened while the UI was closed, [`record`] turns the relevant peer events into
+//! [`Event`]s stored in the [`kv::Store`], one feed per project URN. New peers and patches are
+//! found by comparing the remote refs of a project after every fetch with those seen before.
+//! Refs of blocked peers are ignored. Only the latest [`MAX_EVENTS`] events of a project are kept.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use chrono::{DateTime, Utc};
+use futures::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use link_crypto::PeerId;
+use link_identities::git::Urn;
+use radicle_source::surf::vcs::git::git2;
+
+use crate::{error, notification::Notification};
+
+/// Name for the storage bucket used for project activity.
+const BUCKET_NAME: &str = "project_activity";
+
+/// Number of events kept per project.
+pub const MAX_EVENTS: usize = 1000;
+
+/// Number of events in a [`Page`] if the client doesn't ask for a specific number.
+pub const DEFAULT_LIMIT: usize = 50;
+
+/// Something that happened to a project.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    /// Position in the feed of the project, increasing with every event.
+    pub id: u64,
+    /// When the event was recorded.
+    pub timestamp: DateTime<Utc>,
+    /// What happened.
+    #[serde(flatten)]
+    pub kind: Kind,
+}
+
+/// Kinds of [`Event`]s.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Kind {
+    /// Gossip told us about updates which we fetched from `provider`.
+    #[serde(rename_all = "camelCase")]
+    Updated {
+        /// Peer the updates were fetched from.
+        provider: PeerId,
+    },
+    /// A request for the project was fulfilled by cloning it from `peer`.
+    #[serde(rename_all = "camelCase")]
+    Cloned {
+        /// Peer the project was cloned from.
+        peer: PeerId,
+    },
+    /// We saw refs of `peer` for the first time.
+    #[serde(rename_all = "camelCase")]
+    PeerAdded {
+        /// The new peer.
+        peer: PeerId,
+    },
+    /// We saw patch `id` of `peer` for the first time.
+    #[serde(rename_all = "camelCase")]
+    PatchCreated {
+        /// Peer that published the patch.
+        peer: PeerId,
+        /// ID of the patch, see [`crate::patch::Patch::id`].
+        id: String,
+    },
+}
+
+/// A slice of the feed of a project, newest event first.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Page {
+    /// The events of the page.
+    pub events: Vec<Event>,
+    /// Cursor to pass as `before` to get the next, older page. `None` if this is the last page.
+    pub next: Option<u64>,
+}
+
+/// Feed of a project as kept in the store.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Feed {
+    /// Latest events, oldest first.
+    events: VecDeque<Event>,
+    /// ID of the next event.
+    next_id: u64,
+    /// Remote refs seen so far. `None` until the first fetch was recorded.
+    seen: Option<Seen>,
+}
+
+impl Feed {
+    fn push(&mut self, kind: Kind, timestamp: DateTime<Utc>) {
+        self.events.push_back(Event {
+            id: self.next_id,
+            timestamp,
+            kind,
+        });
+        self.next_id += 1;
+        while self.events.len() > MAX_EVENTS {
+            self.events.pop_front();
+        }
+    }
+
+    /// Add events for the peers and patches in `current` missing from the refs seen so far. The
+    /// first time nothing is added, since we don't know when those refs appeared.
+    fn observe(&mut self, current: Seen, timestamp: DateTime<Utc>) {
+        if let Some(seen) = self.seen.take() {
+            for peer in current.peers.difference(&seen.peers) {
+                if let Ok(peer) = peer.parse() {
+                    self.push(Kind::PeerAdded { peer }, timestamp);
+                }
+            }
+            for (peer, id) in current.patches.difference(&seen.patches) {
+                if let Ok(peer) = peer.parse() {
+                    self.push(
+                        Kind::PatchCreated {
+                            peer,
+                            id: id.clone(),
+                        },
+                        timestamp,
+                    );
+                }
+            }
+        }
+        self.seen = Some(current);
+    }
+
+    fn page(&self, before: Option<u64>, limit: usize) -> Page {
+        let mut events = self
+            .events
+            .iter()
+            .rev()
+            .filter(|event| before.map_or(true, |before| event.id < before));
+        let page = events.by_ref().take(limit).cloned().collect::<Vec<_>>();
+        let next = match (events.next(), page.last()) {
+            (Some(_), Some(last)) => Some(last.id),
+            _ => None,
+        };
+
+        Page { events: page, next }
+    }
+}
+
+/// Remote peers and patches of a project.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Seen {
+    /// Remote peers with refs in the monorepo.
+    peers: BTreeSet<String>,
+    /// Patches of remote peers as pairs of peer and patch ID.
+    patches: BTreeSet<(String, String)>,
+}
+
+/// Get up to `limit` events of project `urn` older than the event with ID `before`, newest first.
+///
+/// # Errors
+///
+/// Errors if we cannot read from the store.
+pub fn page(
+    store: &kv::Store,
+    urn: &Urn,
+    before: Option<u64>,
+    limit: usize,
+) -> Result<Page, error::Error> {
+    Ok(get(store, urn)?.page(before, limit))
+}
+
+/// Drop the feed of project `urn`.
+///
+/// # Errors
+///
+/// Errors if we cannot write to the store.
+pub fn remove(store: &kv::Store, urn: &Urn) -> Result<(), error::Error> {
+    store
+        .bucket::<&str, kv::Json<Feed>>(Some(BUCKET_NAME))?
+        .remove(urn.to_string().as_str())?;
+    Ok(())
+}
+
+/// Record the project activity `events` report.
+///
+/// The future completes when `events` ends.
+pub async fn record(
+    peer: crate::peer::Peer,
+    store: kv::Store,
+    events: impl Stream<Item = radicle_daemon::PeerEvent>,
+) {
+    events
+        .for_each(|event| {
+            let peer = peer.clone();
+            let store = store.clone();
+            async move {
+                let recorded = match crate::notification::from_peer_event(event) {
+                    Some(Notification::ProjectUpdated { provider, urn }) => {
+                        Some((urn, Kind::Updated { provider }))
+                    },
+                    Some(Notification::RequestCloned { peer, urn }) => {
+                        Some((urn, Kind::Cloned { peer }))
+                    },
+                    _ => None,
+                };
+                if let Some((urn, kind)) = recorded {
+                    // Scanning the refs of the project is blocking git I/O.
+                    let result = tokio::task::spawn_blocking({
+                        let urn = urn.clone();
+                        move || record_fetch(&peer, &store, &urn, kind)
+                    })
+                    .await
+                    .expect("task to record project activity was aborted");
+                    if let Err(err) = result {
+                        tracing::warn!(project_urn = %urn, ?err, "failed to record project activity");
+                    }
+                }
+            }
+        })
+        .await;
+}
+
+/// Add the event `kind` for a fetch of `urn` and the events for the peers and patches it brought.
+fn record_fetch(
+    peer: &crate::peer::Peer,
+    store: &kv::Store,
+    urn: &Urn,
+    kind: Kind,
+) -> Result<(), error::Error> {
+    let now = Utc::now();
+    let mut feed = get(store, urn)?;
+    feed.push(kind, now);
+    let blocklist = crate::session::blocklist(store)?;
+    feed.observe(seen(peer, urn, &blocklist)?, now);
+    store
+        .bucket::<&str, kv::Json<Feed>>(Some(BUCKET_NAME))?
+        .set(urn.to_string().as_str(), kv::Json(feed))?;
+    Ok(())
+}
+
+/// Collect the remote peers and patches of `urn` from the monorepo, leaving out the peers in
+/// `blocklist`.
+fn seen(peer: &crate::peer::Peer, urn: &Urn, blocklist: &[PeerId]) -> Result<Seen, error::Error> {
+    let blocked = blocklist
+        .iter()
+        .map(ToString::to_string)
+        .collect::<BTreeSet<_>>();
+    let repo = git2::Repository::open(radicle_daemon::state::monorepo(peer.librad_peer()))?;
+    let remotes = format!("refs/namespaces/{}/refs/remotes/", urn.encode_id());
+    let mut seen = Seen::default();
+    for reference in repo.references_glob(&format!("{}*", remotes))? {
+        let reference = reference?;
+        let name = match reference.name() {
+            Some(name) => name,
+            None => continue,
+        };
+        let mut parts = name.trim_start_matches(&remotes).splitn(3, '/');
+        let (remote, category, rest) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(remote), Some(category), Some(rest)) => (remote, category, rest),
+            _ => continue,
+        };
+        if blocked.contains(remote) {
+            continue;
+        }
+        seen.peers.insert(remote.to_string());
+        if category == "tags" {
+            if let Some(id) = rest.strip_prefix(crate::patch::TAG_PREFIX) {
+                seen.patches.insert((remote.to_string(), id.to_string()));
+            }
+        }
+    }
+    Ok(seen)
+}
+
+fn get(store: &kv::Store, urn: &Urn) -> Result<Feed, error::Error> {
+    Ok(store
+        .bucket::<&str, kv::Json<Feed>>(Some(BUCKET_NAME))?
+        .get(urn.to_string().as_str())?
+        .map(|json| json.0)
+        .unwrap_or_default())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use pretty_assertions::assert_eq;
+
+    use super::{Feed, Kind, Seen};
+
+    #[test]
+    fn observe() {
+        let now = Utc::now();
+        let alice = crate::control::generate_peer_id();
+        let bob = crate::control::generate_peer_id();
+        let mut feed = Feed::default();
+
+        feed.observe(
+            Seen {
+                peers: vec![alice.to_string()].into_iter().collect(),
+                patches: vec![(alice.to_string(), "fix".to_string())]
+                    .into_iter()
+                    .collect(),
+            },
+            now,
+        );
+        assert!(feed.events.is_empty());
+
+        feed.observe(
+            Seen {
+                peers: vec![alice.to_string(), bob.to_string()]
+                    .into_iter()
+                    .collect(),
+                patches: vec![
+                    (alice.to_string(), "fix".to_string()),
+                    (bob.to_string(), "feature".to_string()),
+                ]
+                .into_iter()
+                .collect(),
+            },
+            now,
+        );
+        assert_eq!(
+            feed.events
+                .iter()
+                .map(|event| event.kind.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                Kind::PeerAdded { peer: bob },
+                Kind::PatchCreated {
+                    peer: bob,
+                    id: "feature".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn page() {
+        let now = Utc::now();
+        let provider = crate::control::generate_peer_id();
+        let mut feed = Feed::default();
+        for _ in 0..(super::MAX_EVENTS + 5) {
+            feed.push(Kind::Updated { provider }, now);
+        }
+        assert_eq!(feed.events.len(), super::MAX_EVENTS);
+
+        let ids = |page: &super::Page| page.events.iter().map(|event| event.id).collect::<Vec<_>>();
+        let first = feed.page(None, 2);
+        assert_eq!(ids(&first), vec![1004, 1003]);
+        assert_eq!(first.next, Some(1003));
+
+        let second = feed.page(first.next, 2);
+        assert_eq!(ids(&second), vec![1002, 1001]);
+
+        let last = feed.page(Some(7), 10);
+        assert_eq!(ids(&last), vec![6, 5]);
+        assert_eq!(last.next, None);
+    }
+}
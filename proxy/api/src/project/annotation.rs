@@ -0,0 +1,207 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Private annotations the user attaches to projects to organise them.
+//!
+//! Annotations are kept in the [`kv::Store`], one entry per project URN. They are never written to
+//! the monorepo and so are never replicated.
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use link_identities::git::Urn;
+
+use crate::error;
+
+/// Name for the storage bucket used for project annotations.
+const BUCKET_NAME: &str = "project_annotations";
+
+/// Favourite flag, labels and note of a project.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Annotation {
+    /// The project is one of the user's favourites.
+    #[serde(default)]
+    pub favourite: bool,
+    /// Free-form labels, sorted and without duplicates.
+    #[serde(default)]
+    pub labels: BTreeSet<String>,
+    /// Private note.
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl Annotation {
+    /// Drop blank labels and notes and trim the remaining ones.
+    fn normalise(self) -> Self {
+        Self {
+            favourite: self.favourite,
+            labels: self
+                .labels
+                .iter()
+                .map(|label| label.trim())
+                .filter(|label| !label.is_empty())
+                .map(ToString::to_string)
+                .collect(),
+            note: self
+                .note
+                .map(|note| note.trim().to_string())
+                .filter(|note| !note.is_empty()),
+        }
+    }
+}
+
+/// Criteria to narrow down project listings with. Criteria that are `None` match every project.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Filter {
+    /// Only projects that are, or are not, favourites.
+    pub favourite: Option<bool>,
+    /// Only projects with this label.
+    pub label: Option<String>,
+}
+
+impl Filter {
+    /// Whether a project annotated with `annotation` satisfies all criteria.
+    #[must_use]
+    pub fn matches(&self, annotation: &Annotation) -> bool {
+        self.favourite
+            .map_or(true, |favourite| annotation.favourite == favourite)
+            && self
+                .label
+                .as_ref()
+                .map_or(true, |label| annotation.labels.contains(label.trim()))
+    }
+}
+
+/// Annotation of project `urn`, the empty one if the project was never annotated.
+///
+/// # Errors
+///
+/// Errors if we cannot read from the store.
+pub fn get(store: &kv::Store, urn: &Urn) -> Result<Annotation, error::Error> {
+    Ok(store
+        .bucket::<&str, kv::Json<Annotation>>(Some(BUCKET_NAME))?
+        .get(urn.to_string().as_str())?
+        .map(|json| json.0)
+        .unwrap_or_default())
+}
+
+/// Annotations of all annotated projects.
+///
+/// # Errors
+///
+/// Errors if we cannot read from the store or an entry is malformed.
+pub fn list(store: &kv::Store) -> Result<HashMap<String, Annotation>, error::Error> {
+    let bucket = store.bucket::<&str, kv::Json<Annotation>>(Some(BUCKET_NAME))?;
+    let mut annotations = HashMap::new();
+    for item in bucket.iter() {
+        let item = item?;
+        let urn = item.key::<&str>()?.to_string();
+        annotations.insert(urn, item.value::<kv::Json<Annotation>>()?.0);
+    }
+    Ok(annotations)
+}
+
+/// Replace the annotation of project `urn` and return the stored one. An empty annotation removes
+/// the entry.
+///
+/// # Errors
+///
+/// Errors if we cannot write to the store.
+pub fn set(
+    store: &kv::Store,
+    urn: &Urn,
+    annotation: Annotation,
+) -> Result<Annotation, error::Error> {
+    let annotation = annotation.normalise();
+    if annotation == Annotation::default() {
+        remove(store, urn)?;
+    } else {
+        store
+            .bucket::<&str, kv::Json<Annotation>>(Some(BUCKET_NAME))?
+            .set(urn.to_string().as_str(), kv::Json(annotation.clone()))?;
+    }
+    Ok(annotation)
+}
+
+/// Drop the annotation of project `urn`.
+///
+/// # Errors
+///
+/// Errors if we cannot write to the store.
+pub fn remove(store: &kv::Store, urn: &Urn) -> Result<(), error::Error> {
+    store
+        .bucket::<&str, kv::Json<Annotation>>(Some(BUCKET_NAME))?
+        .remove(urn.to_string().as_str())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Annotation, Filter};
+
+    #[test]
+    fn normalise() {
+        let annotation = Annotation {
+            favourite: true,
+            labels: vec![" work ".to_string(), "work".to_string(), "  ".to_string()]
+                .into_iter()
+                .collect(),
+            note: Some("   ".to_string()),
+        }
+        .normalise();
+
+        assert_eq!(
+            annotation,
+            Annotation {
+                favourite: true,
+                labels: vec!["work".to_string()].into_iter().collect(),
+                note: None,
+            }
+        );
+    }
+
+    #[test]
+    fn filter() {
+        let annotation = Annotation {
+            favourite: true,
+            labels: vec!["work".to_string()].into_iter().collect(),
+            note: None,
+        };
+
+        assert!(Filter::default().matches(&annotation));
+        assert!(Filter::default().matches(&Annotation::default()));
+        assert!(Filter {
+            favourite: Some(true),
+            label: Some("work".to_string()),
+        }
+        .matches(&annotation));
+        assert!(!Filter {
+            favourite: Some(false),
+            label: None,
+        }
+        .matches(&annotation));
+        assert!(!Filter {
+            favourite: None,
+            label: Some("home".to_string()),
+        }
+        .matches(&annotation));
+        assert!(!Filter {
+            favourite: None,
+            label: Some("work".to_string()),
+        }
+        .matches(&Annotation {
+            labels: BTreeSet::new(),
+            ..annotation
+        }));
+    }
+}
@@ -0,0 +1,350 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Transfer projects between monorepos through [git bundles][bundle] instead of the network.
+//!
+//! A bundle contains the refs of a project namespace in the layout a replicating peer would
+//! store them: the identity document under `rad/` and the refs of every exported peer, including
+//! the exporting peer itself, under `remotes/<peer_id>/`.
+//!
+//! libgit2 cannot read or write bundles, so we shell out to `git`.
+//!
+//! [bundle]: https://git-scm.com/docs/git-bundle
+
+use std::{collections::BTreeMap, convert::TryFrom as _, path::Path};
+
+use librad::git::refs::Refs;
+use link_crypto::PeerId;
+use link_identities::git::Urn;
+use radicle_source::surf::vcs::git::git2;
+
+use crate::error;
+
+/// Maximum size in bytes of a bundle accepted by [`import`].
+pub const MAX_SIZE: u64 = 512 * 1024 * 1024;
+
+/// Create a bundle of project `urn` that contains the refs of the local peer and of `peers`.
+///
+/// Returns the content of the bundle file.
+///
+/// # Errors
+///
+///   * The project could not be found.
+///   * One of the `git` invocations failed.
+pub async fn export(
+    peer: &crate::peer::Peer,
+    urn: Urn,
+    peers: Vec<PeerId>,
+) -> Result<Vec<u8>, error::Error> {
+    radicle_daemon::state::get_project(peer.librad_peer(), urn.clone())
+        .await?
+        .ok_or(error::Error::ProjectNotFound)?;
+
+    let monorepo = radicle_daemon::state::monorepo(peer.librad_peer());
+    let local_peer_id = peer.librad_peer().peer_id();
+    let namespace = format!("refs/namespaces/{}/refs", urn.encode_id());
+
+    let mut refspecs = vec![
+        format!("+{ns}/rad/*:{ns}/rad/*", ns = namespace),
+        format!(
+            "+{ns}/heads/*:{ns}/remotes/{peer}/heads/*",
+            ns = namespace,
+            peer = local_peer_id
+        ),
+        format!(
+            "+{ns}/tags/*:{ns}/remotes/{peer}/tags/*",
+            ns = namespace,
+            peer = local_peer_id
+        ),
+        format!(
+            "+{ns}/rad/*:{ns}/remotes/{peer}/rad/*",
+            ns = namespace,
+            peer = local_peer_id
+        ),
+    ];
+    for peer_id in peers
+        .into_iter()
+        .filter(|peer_id| *peer_id != local_peer_id)
+    {
+        refspecs.push(format!(
+            "+{ns}/remotes/{peer}/*:{ns}/remotes/{peer}/*",
+            ns = namespace,
+            peer = peer_id
+        ));
+    }
+
+    let staging = tempfile::tempdir()?;
+    git2::Repository::init_bare(staging.path())?;
+    let mut fetch = vec!["fetch".to_string(), monorepo.display().to_string()];
+    fetch.extend(refspecs);
+    super::git(staging.path(), &fetch).await?;
+
+    let bundle_path = staging.path().join("project.bundle");
+    super::git(
+        staging.path(),
+        &[
+            "bundle".to_string(),
+            "create".to_string(),
+            bundle_path.display().to_string(),
+            "--all".to_string(),
+        ],
+    )
+    .await?;
+
+    Ok(tokio::fs::read(bundle_path).await?)
+}
+
+/// Import the project contained in `bundle` into the monorepo and track the peers it contains.
+///
+/// The identity document and the signed refs of every peer in the bundle are verified before the
+/// refs are kept. Refs of a peer whose tip is not listed in its signed refs are dropped. This
+/// includes the `rad/` refs of the peers, which are not covered by the signed refs. They are
+/// fetched once we replicate from the peer. Refs of the local peer and of blocked peers in the
+/// bundle are ignored.
+///
+/// Returns the URN of the imported project.
+///
+/// # Errors
+///
+///   * The bundle is malformed or does not contain a single project.
+///   * The identity document or the signed refs of a peer cannot be verified.
+///   * One of the `git` invocations failed.
+pub async fn import(
+    peer: &crate::peer::Peer,
+    store: &kv::Store,
+    bundle: &[u8],
+) -> Result<Urn, error::Error> {
+    let monorepo = radicle_daemon::state::monorepo(peer.librad_peer());
+    let local_peer_id = peer.librad_peer().peer_id();
+
+    let staging = tempfile::tempdir()?;
+    let bundle_path = staging.path().join("project.bundle");
+    tokio::fs::write(&bundle_path, bundle).await?;
+    let bundle_arg = bundle_path.display().to_string();
+
+    super::git(
+        &monorepo,
+        &[
+            "bundle".to_string(),
+            "verify".to_string(),
+            bundle_arg.clone(),
+        ],
+    )
+    .await
+    .map_err(|err| error::Error::InvalidBundle(err.to_string()))?;
+    let heads = super::git(
+        &monorepo,
+        &[
+            "bundle".to_string(),
+            "list-heads".to_string(),
+            bundle_arg.clone(),
+        ],
+    )
+    .await?;
+
+    let (urn, peers) = parse_heads(&heads)?;
+    let namespace = format!("refs/namespaces/{}/refs", urn.encode_id());
+    let blocklist = crate::session::blocklist(store)?;
+    let peers = peers
+        .into_iter()
+        .filter(|peer_id| *peer_id != local_peer_id && !blocklist.contains(peer_id))
+        .collect::<Vec<_>>();
+
+    let has_identity = radicle_daemon::state::get_project(peer.librad_peer(), urn.clone())
+        .await?
+        .is_some();
+
+    let mut fetch = vec!["fetch".to_string(), bundle_arg];
+    if !has_identity {
+        fetch.push(format!("{ns}/rad/id:{ns}/rad/id", ns = namespace));
+        fetch.push(format!("+{ns}/rad/ids/*:{ns}/rad/ids/*", ns = namespace));
+    }
+    for peer_id in &peers {
+        fetch.push(format!(
+            "+{ns}/remotes/{peer}/*:{ns}/remotes/{peer}/*",
+            ns = namespace,
+            peer = peer_id
+        ));
+    }
+    super::git(&monorepo, &fetch).await?;
+
+    let verification = peer
+        .librad_peer()
+        .using_storage({
+            let urn = urn.clone();
+            let peers = peers.clone();
+            let namespace = namespace.clone();
+            move |storage| -> Result<BTreeMap<String, String>, String> {
+                librad::git::identities::project::verify(storage, &urn)
+                    .map_err(|err| err.to_string())?
+                    .ok_or_else(|| "the bundle does not contain a project identity".to_string())?;
+                let mut signed = BTreeMap::new();
+                for peer_id in peers {
+                    let refs = Refs::load(storage, &urn, Some(peer_id))
+                        .map_err(|err| format!("signed refs of {}: {}", peer_id, err))?
+                        .ok_or_else(|| format!("signed refs of {} are missing", peer_id))?;
+                    for ((name, oid), category) in refs.iter_categorised() {
+                        signed.insert(
+                            format!("{}/remotes/{}/{}/{}", namespace, peer_id, category, name),
+                            oid.to_string(),
+                        );
+                    }
+                }
+                Ok(signed)
+            }
+        })
+        .await?;
+
+    let signed = match verification {
+        Ok(signed) => signed,
+        Err(reason) => {
+            let mut globs = peers
+                .iter()
+                .map(|peer_id| format!("{}/remotes/{}/*", namespace, peer_id))
+                .collect::<Vec<_>>();
+            if !has_identity {
+                globs.push(format!("{}/*", namespace));
+            }
+            tokio::task::spawn_blocking(move || delete_refs(&monorepo, &globs))
+                .await
+                .expect("task to delete imported refs was aborted")?;
+            return Err(error::Error::InvalidBundle(reason));
+        },
+    };
+    tokio::task::spawn_blocking({
+        let peers = peers.clone();
+        move || delete_unsigned_refs(&monorepo, &namespace, &peers, &signed)
+    })
+    .await
+    .expect("task to delete unsigned refs was aborted")?;
+
+    for peer_id in peers {
+        radicle_daemon::state::track(peer.librad_peer(), urn.clone(), peer_id).await?;
+    }
+
+    Ok(urn)
+}
+
+/// Extract the project URN and the peers from the output of `git bundle list-heads`.
+fn parse_heads(heads: &str) -> Result<(Urn, Vec<PeerId>), error::Error> {
+    let mut namespace = None;
+    let mut peers = Vec::new();
+
+    for line in heads.lines() {
+        let refname = line
+            .split_whitespace()
+            .nth(1)
+            .ok_or_else(|| error::Error::InvalidBundle(format!("malformed head `{}`", line)))?;
+        let mut components = refname
+            .strip_prefix("refs/namespaces/")
+            .ok_or_else(|| error::Error::InvalidBundle(format!("unexpected ref `{}`", refname)))?
+            .split('/');
+        let id = components.next().unwrap_or_default();
+
+        match namespace {
+            None => namespace = Some(id.to_string()),
+            Some(ref namespace) if namespace != id => {
+                return Err(error::Error::InvalidBundle(
+                    "the bundle contains more than one project".to_string(),
+                ));
+            },
+            Some(_) => {},
+        }
+
+        if let (Some("refs"), Some("remotes"), Some(peer_id)) =
+            (components.next(), components.next(), components.next())
+        {
+            let peer_id = peer_id.parse::<PeerId>().map_err(|_| {
+                error::Error::InvalidBundle(format!("invalid peer id in `{}`", refname))
+            })?;
+            if !peers.contains(&peer_id) {
+                peers.push(peer_id);
+            }
+        }
+    }
+
+    let namespace =
+        namespace.ok_or_else(|| error::Error::InvalidBundle("the bundle is empty".to_string()))?;
+    let urn = Urn::try_from_id(&namespace)
+        .map_err(|_| error::Error::InvalidBundle(format!("invalid project id `{}`", namespace)))?;
+
+    Ok((urn, peers))
+}
+
+/// Delete all refs in the monorepo that match any of `globs`.
+fn delete_refs(monorepo: &Path, globs: &[String]) -> Result<(), error::Error> {
+    let repo = git2::Repository::open(monorepo)?;
+    for glob in globs {
+        for reference in repo.references_glob(glob)? {
+            reference?.delete()?;
+        }
+    }
+    Ok(())
+}
+
+/// Delete the refs of `peers` whose tip is not listed in the `signed` refs.
+fn delete_unsigned_refs(
+    monorepo: &Path,
+    namespace: &str,
+    peers: &[PeerId],
+    signed: &BTreeMap<String, String>,
+) -> Result<(), error::Error> {
+    let repo = git2::Repository::open(monorepo)?;
+    for peer_id in peers {
+        for reference in repo.references_glob(&format!("{}/remotes/{}/*", namespace, peer_id))? {
+            let mut reference = reference?;
+            let name = reference.name().map(ToString::to_string);
+            let tip = reference.target().map(|oid| oid.to_string());
+            if tip.is_none() || tip.as_ref() != name.and_then(|name| signed.get(&name)) {
+                reference.delete()?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use link_identities::payload::Person;
+
+    use crate::{context, project};
+
+    #[tokio::test]
+    async fn export_import() -> Result<(), Box<dyn std::error::Error>> {
+        let alice_dir = tempfile::tempdir()?;
+        let (alice, _) = context::Unsealed::tmp(&alice_dir)?;
+        let bob_dir = tempfile::tempdir()?;
+        let (bob, _) = context::Unsealed::tmp(&bob_dir)?;
+
+        let owner = radicle_daemon::state::init_owner(
+            alice.peer.librad_peer(),
+            Person {
+                name: "alice".into(),
+            },
+        )
+        .await?;
+        let urn = crate::control::replicate_platinum(
+            &alice.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+
+        let bundle = super::export(&alice.peer, urn.clone(), vec![]).await?;
+        let imported = super::import(&bob.peer, &bob.rest.store, &bundle).await?;
+        assert_eq!(imported, urn);
+
+        let project = project::get(&bob.peer, urn).await?;
+        assert_eq!(project.metadata.name, "git-platinum");
+
+        Ok(())
+    }
+}
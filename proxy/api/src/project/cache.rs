@@ -0,0 +1,274 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Persistent cache for project [`Stats`] and [`Contents`].
+//!
+//! Computing [`Stats`] requires walking the whole history of a project and detecting the
+//! [`Contents`] the whole tree of its default branch, which is too slow to do for every project on
+//! every listing. Entries are stored in the [`kv::Store`] per project URN
+//! together with the head of the default branch they were computed for. An entry is fresh if the
+//! head still matches and the entry has not been invalidated by a
+//! [`crate::notification::Notification::ProjectUpdated`] event. Local pushes move the head of the
+//! default branch and are therefore picked up by the head comparison.
+
+use std::{collections::HashSet, sync::Mutex};
+
+use futures::prelude::*;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use librad::git::types::{Reference, Single};
+use link_identities::git::Urn;
+use radicle_git_ext::Oid;
+use radicle_source::surf::vcs::git::{git2, Stats};
+
+use super::contents::{self, Contents};
+use crate::{browser, error};
+
+/// Name for the storage bucket used for cached project stats.
+const BUCKET_NAME: &str = "project_stats";
+
+lazy_static! {
+    /// Projects whose stats are currently being recomputed in the background.
+    static ref REFRESHING: Mutex<HashSet<Urn>> = Mutex::new(HashSet::new());
+}
+
+/// Cached [`Stats`] and [`Contents`] for a project.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    /// Head of the default branch the stats were computed for.
+    head: Oid,
+    /// Number of branches.
+    branches: usize,
+    /// Number of commits on the default branch.
+    commits: usize,
+    /// Number of unique contributors on the default branch.
+    contributors: usize,
+    /// License and languages of the default branch. Missing in entries written before they were
+    /// cached.
+    #[serde(default)]
+    contents: Option<Contents>,
+    /// Set when a peer event tells us the project changed.
+    invalidated: bool,
+}
+
+impl Entry {
+    fn new(head: Oid, stats: &Stats, contents: Contents) -> Self {
+        Self {
+            head,
+            branches: stats.branches,
+            commits: stats.commits,
+            contributors: stats.contributors,
+            contents: Some(contents),
+            invalidated: false,
+        }
+    }
+
+    fn cached(self, fresh: bool) -> Cached {
+        Cached {
+            stats: Stats {
+                branches: self.branches,
+                commits: self.commits,
+                contributors: self.contributors,
+            },
+            contents: self.contents.unwrap_or_default(),
+            fresh,
+        }
+    }
+}
+
+/// [`Stats`] and [`Contents`] served from the cache.
+pub struct Cached {
+    /// The stats of the project.
+    pub stats: Stats,
+    /// License and languages of the project.
+    pub contents: Contents,
+    /// `false` if the stats and contents are outdated and are being recomputed in the background.
+    pub fresh: bool,
+}
+
+/// Get the [`Stats`] and [`Contents`] for the project `urn` whose default branch is
+/// `default_branch`.
+///
+/// If there is no cache entry, the stats and contents are computed and stored before returning. If
+/// there is an outdated entry, it is returned and a recomputation is kicked off in the
+/// background, unless one is already running for `urn`.
+///
+/// # Errors
+///
+///   * We couldn't resolve the head of the default branch.
+///   * We couldn't compute the stats or detect the contents.
+///   * We couldn't read from or write to the store.
+pub fn stats(
+    peer: &crate::peer::Peer,
+    store: &kv::Store,
+    urn: &Urn,
+    default_branch: Reference<Single>,
+) -> Result<Cached, error::Error> {
+    let head = head(peer, &default_branch)?;
+
+    match get(store, urn)? {
+        Some(entry) if entry.head == head && !entry.invalidated && entry.contents.is_some() => {
+            Ok(entry.cached(true))
+        },
+        Some(entry) if entry.contents.is_some() => {
+            if REFRESHING
+                .lock()
+                .expect("refresh set lock poisoned")
+                .insert(urn.clone())
+            {
+                tokio::task::spawn_blocking({
+                    let peer = peer.clone();
+                    let store = store.clone();
+                    let urn = urn.clone();
+                    move || {
+                        if let Err(err) = refresh(&peer, &store, &urn, default_branch) {
+                            tracing::warn!(project_urn = %urn, ?err, "failed to refresh project stats");
+                        }
+                        REFRESHING
+                            .lock()
+                            .expect("refresh set lock poisoned")
+                            .remove(&urn);
+                    }
+                });
+            }
+
+            Ok(entry.cached(false))
+        },
+        _ => Ok(refresh(peer, store, urn, default_branch)?.cached(true)),
+    }
+}
+
+/// Mark the cached stats for `urn` as outdated.
+///
+/// # Errors
+///
+/// Errors if we cannot read from or write to the store.
+pub fn invalidate(store: &kv::Store, urn: &Urn) -> Result<(), error::Error> {
+    if let Some(mut entry) = get(store, urn)? {
+        entry.invalidated = true;
+        set(store, urn, entry)?;
+    }
+    Ok(())
+}
+
+/// Drop the cached stats for `urn`.
+///
+/// # Errors
+///
+/// Errors if we cannot write to the store.
+pub fn remove(store: &kv::Store, urn: &Urn) -> Result<(), error::Error> {
+    store
+        .bucket::<&str, kv::Json<Entry>>(Some(BUCKET_NAME))?
+        .remove(urn.to_string().as_str())?;
+    Ok(())
+}
+
+/// Invalidate cached stats whenever `events` reports that a project was updated from the network.
+///
+/// The future completes when `events` ends.
+pub async fn invalidate_on_updates(
+    store: kv::Store,
+    events: impl Stream<Item = radicle_daemon::PeerEvent>,
+) {
+    events
+        .for_each(|event| {
+            if let Some(crate::notification::Notification::ProjectUpdated { urn, .. }) =
+                crate::notification::from_peer_event(event)
+            {
+                if let Err(err) = invalidate(&store, &urn) {
+                    tracing::warn!(project_urn = %urn, ?err, "failed to invalidate project stats");
+                }
+            }
+            future::ready(())
+        })
+        .await;
+}
+
+/// Compute the stats and detect the contents for `urn` and store them in the cache.
+fn refresh(
+    peer: &crate::peer::Peer,
+    store: &kv::Store,
+    urn: &Urn,
+    default_branch: Reference<Single>,
+) -> Result<Entry, error::Error> {
+    let head = head(peer, &default_branch)?;
+    let contents = contents::detect(peer, &default_branch)?;
+    let stats = browser::using(peer, default_branch, |browser| Ok(browser.get_stats()?))?;
+    let entry = Entry::new(head, &stats, contents);
+    set(store, urn, entry.clone())?;
+    Ok(entry)
+}
+
+/// Resolve the commit `reference` points to in the monorepo.
+fn head(peer: &crate::peer::Peer, reference: &Reference<Single>) -> Result<Oid, error::Error> {
+    let repo = git2::Repository::open(radicle_daemon::state::monorepo(peer.librad_peer()))?;
+    let oid = repo.refname_to_id(&reference.to_string())?;
+    Ok(Oid::from(oid))
+}
+
+fn get(store: &kv::Store, urn: &Urn) -> Result<Option<Entry>, error::Error> {
+    Ok(store
+        .bucket::<&str, kv::Json<Entry>>(Some(BUCKET_NAME))?
+        .get(urn.to_string().as_str())?
+        .map(|json| json.0))
+}
+
+fn set(store: &kv::Store, urn: &Urn, entry: Entry) -> Result<(), error::Error> {
+    Ok(store
+        .bucket::<&str, kv::Json<Entry>>(Some(BUCKET_NAME))?
+        .set(urn.to_string().as_str(), kv::Json(entry))?)
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use link_identities::payload::Person;
+
+    use crate::context;
+
+    #[tokio::test]
+    async fn invalidate() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+
+        let owner = radicle_daemon::state::init_owner(
+            ctx.peer.librad_peer(),
+            Person {
+                name: "cloudhead".into(),
+            },
+        )
+        .await?;
+        let urn = crate::control::replicate_platinum(
+            &ctx.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+        let default_branch =
+            radicle_daemon::state::find_default_branch(ctx.peer.librad_peer(), urn.clone()).await?;
+
+        let computed = super::stats(&ctx.peer, &ctx.rest.store, &urn, default_branch.clone())?;
+        assert!(computed.fresh);
+
+        let cached = super::stats(&ctx.peer, &ctx.rest.store, &urn, default_branch.clone())?;
+        assert!(cached.fresh);
+        assert_eq!(cached.stats.commits, computed.stats.commits);
+        assert_eq!(cached.contents.languages, computed.contents.languages);
+
+        super::invalidate(&ctx.rest.store, &urn)?;
+        let stale = super::stats(&ctx.peer, &ctx.rest.store, &urn, default_branch)?;
+        assert!(!stale.fresh);
+        assert_eq!(stale.stats.commits, computed.stats.commits);
+
+        Ok(())
+    }
+}
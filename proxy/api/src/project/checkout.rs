@@ -0,0 +1,367 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Create working copies of projects with a choice of branch, remotes and history depth.
+//!
+//! A full checkout is delegated to [`radicle_daemon::state::checkout`]. libgit2 cannot fetch
+//! shallow histories, so shallow checkouts and fetching additional remotes shell out to `git`,
+//! which reads the project namespace of the monorepo directly. The resulting working copy is set
+//! up like a full one, so subsequent fetches and pushes go through the `rad` remote.
+
+use std::{
+    convert::TryFrom as _,
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use link_crypto::PeerId;
+use link_identities::git::Urn;
+use radicle_git_ext::RefLike;
+use radicle_source::surf::vcs::git::git2;
+
+use crate::{error, notification};
+
+/// Choices for a checkout beyond the location and the peer to check out from.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Options {
+    /// Branch to check out. Defaults to the default branch of the project.
+    pub branch: Option<String>,
+    /// Tracked peers whose branches are fetched into the working copy.
+    #[serde(default)]
+    pub remotes: Vec<PeerId>,
+    /// Only fetch this many commits of history.
+    pub depth: Option<NonZeroU32>,
+}
+
+/// A stage of a checkout, reported through [`notification::Notification::CheckoutProgress`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Progress {
+    /// The history of the project is being cloned.
+    Cloning,
+    /// The branches of a remote peer are being fetched.
+    #[serde(rename_all = "camelCase")]
+    FetchingRemote {
+        /// The peer whose branches are fetched.
+        peer_id: PeerId,
+    },
+    /// The branch is being checked out into the working copy.
+    #[serde(rename_all = "camelCase")]
+    CheckingOut {
+        /// The branch that is checked out.
+        branch: String,
+    },
+    /// The working copy is ready.
+    #[serde(rename_all = "camelCase")]
+    Done {
+        /// Location of the working copy.
+        path: PathBuf,
+    },
+    /// The checkout failed.
+    #[serde(rename_all = "camelCase")]
+    Failed {
+        /// What went wrong.
+        message: String,
+    },
+}
+
+/// Check out project `urn` from `peer_id`, or the local peer if `None`, into a new directory named
+/// after the project inside `path`.
+///
+/// Every stage is sent to `notifications`. Returns the location of the working copy.
+///
+/// # Errors
+///
+///   * The project could not be found or has no default branch.
+///   * A peer in [`Options::remotes`] is not tracked.
+///   * The branch to check out is not a valid branch name or does not exist.
+///   * The handle of a remote peer doesn't make a valid remote name.
+///   * Cloning, fetching or checking out failed.
+pub async fn checkout(
+    peer: &crate::peer::Peer,
+    paths: &librad::paths::Paths,
+    notifications: &notification::Local,
+    urn: Urn,
+    peer_id: Option<PeerId>,
+    path: PathBuf,
+    options: Options,
+) -> Result<PathBuf, error::Error> {
+    let notify = |progress: Progress| {
+        notifications.send(notification::Notification::CheckoutProgress {
+            urn: urn.clone(),
+            path: path.clone(),
+            progress,
+        });
+    };
+
+    let result = run(peer, paths, &notify, &urn, peer_id, &path, options).await;
+    match &result {
+        Ok(repo_path) => notify(Progress::Done {
+            path: repo_path.clone(),
+        }),
+        Err(err) => notify(Progress::Failed {
+            message: err.to_string(),
+        }),
+    }
+
+    result
+}
+
+/// Run all stages of a checkout, reporting them to `notify`.
+async fn run(
+    peer: &crate::peer::Peer,
+    paths: &librad::paths::Paths,
+    notify: &impl Fn(Progress),
+    urn: &Urn,
+    peer_id: Option<PeerId>,
+    path: &Path,
+    options: Options,
+) -> Result<PathBuf, error::Error> {
+    let project = radicle_daemon::state::get_project(peer.librad_peer(), urn.clone())
+        .await?
+        .ok_or(error::Error::ProjectNotFound)?;
+    let branch = match options.branch {
+        Some(branch) => branch,
+        None => project
+            .subject()
+            .default_branch
+            .clone()
+            .ok_or(error::Error::MissingDefaultBranch)?
+            .to_string(),
+    };
+    validate_branch(&branch)?;
+
+    let tracked = radicle_daemon::state::tracked(peer.librad_peer(), urn.clone())
+        .await?
+        .into_iter()
+        .filter_map(radicle_daemon::project::Peer::replicated_remote)
+        .collect::<Vec<_>>();
+    let remotes = options
+        .remotes
+        .iter()
+        .map(|remote| {
+            let (peer_id, person) = tracked
+                .iter()
+                .find(|(peer_id, _)| peer_id == remote)
+                .ok_or(error::Error::PeerNotTracked(*remote))?;
+            Ok((*peer_id, remote_name(*peer_id, person)?))
+        })
+        .collect::<Result<Vec<_>, error::Error>>()?;
+
+    notify(Progress::Cloning);
+    let repo_path = match options.depth {
+        None => {
+            radicle_daemon::state::checkout(
+                peer.librad_peer(),
+                urn.clone(),
+                peer_id,
+                path.to_path_buf(),
+            )
+            .await?
+        },
+        Some(depth) => {
+            let repo_path = path.join(project.subject().name.to_string());
+            let upstream = match peer_id {
+                None => radicle_daemon::config::RAD_REMOTE.to_string(),
+                Some(peer_id) => {
+                    let (peer_id, person) = tracked
+                        .iter()
+                        .find(|(tracked_peer_id, _)| *tracked_peer_id == peer_id)
+                        .ok_or(error::Error::PeerNotTracked(peer_id))?;
+                    remote_name(*peer_id, person)?
+                },
+            };
+            init(paths, urn, &repo_path)?;
+            if let Some(peer_id) = peer_id {
+                ensure_remote(urn, &repo_path, peer_id, &upstream)?;
+            }
+            super::git(
+                &repo_path,
+                &fetch_args(
+                    peer,
+                    urn,
+                    Some(depth),
+                    format!(
+                        "+{}:refs/remotes/{}/{}",
+                        peer_ref(peer_id, &branch),
+                        upstream,
+                        branch
+                    ),
+                ),
+            )
+            .await?;
+            repo_path
+        },
+    };
+
+    for (remote_peer_id, name) in remotes {
+        notify(Progress::FetchingRemote {
+            peer_id: remote_peer_id,
+        });
+        ensure_remote(urn, &repo_path, remote_peer_id, &name)?;
+        super::git(
+            &repo_path,
+            &fetch_args(
+                peer,
+                urn,
+                options.depth,
+                format!(
+                    "+{}:refs/remotes/{}/*",
+                    peer_ref(Some(remote_peer_id), "*"),
+                    name
+                ),
+            ),
+        )
+        .await?;
+    }
+
+    notify(Progress::CheckingOut {
+        branch: branch.clone(),
+    });
+    let repo = git2::Repository::open(&repo_path)?;
+    let mut args = vec!["checkout".to_string()];
+    if repo.find_branch(&branch, git2::BranchType::Local).is_ok() {
+        args.push(branch);
+    } else {
+        let upstream = upstream_branch(&repo, &branch)?
+            .ok_or_else(|| error::Error::BranchNotFound(branch.clone()))?;
+        args.extend(["-b".to_string(), branch, "--track".to_string(), upstream]);
+    }
+    // Everything before `--` is a branch, never a path in the working copy.
+    args.push("--".to_string());
+    super::git(&repo_path, &args).await?;
+
+    Ok(repo_path)
+}
+
+/// Find the remote tracking branch for `branch`, preferring the `rad` remote over those of other
+/// peers.
+fn upstream_branch(repo: &git2::Repository, branch: &str) -> Result<Option<String>, error::Error> {
+    let mut candidates = vec![];
+    for remote_branch in repo.branches(Some(git2::BranchType::Remote))? {
+        let (remote_branch, _) = remote_branch?;
+        if let Some(name) = remote_branch.name()? {
+            if name.split_once('/').map(|(_, name)| name) == Some(branch) {
+                candidates.push(name.to_string());
+            }
+        }
+    }
+
+    let rad = format!("{}/{}", radicle_daemon::config::RAD_REMOTE, branch);
+    Ok(if candidates.contains(&rad) {
+        Some(rad)
+    } else {
+        candidates.into_iter().next()
+    })
+}
+
+/// Create an empty repository at `repo_path` with the `rad` remote and the include file of the
+/// project configured.
+fn init(paths: &librad::paths::Paths, urn: &Urn, repo_path: &Path) -> Result<(), error::Error> {
+    let repo = git2::Repository::init(repo_path)?;
+    repo.remote(
+        radicle_daemon::config::RAD_REMOTE,
+        &radicle_daemon::LocalUrl::from(urn.clone()).to_string(),
+    )?;
+    let include = paths
+        .git_includes_dir()
+        .join(format!("{}.inc", urn.encode_id()));
+    repo.config()?.set_str(
+        radicle_daemon::include::GIT_CONFIG_PATH_KEY,
+        &include.display().to_string(),
+    )?;
+    Ok(())
+}
+
+/// Configure a remote for the branches of `peer_id` unless the include file already did.
+fn ensure_remote(
+    urn: &Urn,
+    repo_path: &Path,
+    peer_id: PeerId,
+    name: &str,
+) -> Result<(), error::Error> {
+    let repo = git2::Repository::open(repo_path)?;
+    if repo.find_remote(name).is_err() {
+        repo.remote_with_fetch(
+            name,
+            &radicle_daemon::LocalUrl::from(urn.clone()).to_string(),
+            &format!("+{}:refs/remotes/{}/*", peer_ref(Some(peer_id), "*"), name),
+        )?;
+    }
+    Ok(())
+}
+
+/// Arguments for `git fetch` of `refspec` from the namespace of `urn` in the monorepo.
+fn fetch_args(
+    peer: &crate::peer::Peer,
+    urn: &Urn,
+    depth: Option<NonZeroU32>,
+    refspec: String,
+) -> Vec<String> {
+    // The `ext` transport lets `git upload-pack` serve the project namespace only. Spaces in the
+    // command have to be escaped as `% `.
+    let monorepo = radicle_daemon::state::monorepo(peer.librad_peer())
+        .display()
+        .to_string()
+        .replace('%', "%%")
+        .replace(' ', "% ");
+    let mut args = vec![
+        "-c".to_string(),
+        "protocol.ext.allow=always".to_string(),
+        "fetch".to_string(),
+        "--no-tags".to_string(),
+    ];
+    if let Some(depth) = depth {
+        args.push(format!("--depth={}", depth));
+    }
+    args.push(format!(
+        "ext::git --namespace={} %s {}",
+        urn.encode_id(),
+        monorepo
+    ));
+    args.push(refspec);
+    args
+}
+
+/// Ref of `branch` of `peer_id`, or of the local peer if `None`, inside the project namespace.
+fn peer_ref(peer_id: Option<PeerId>, branch: &str) -> String {
+    match peer_id {
+        None => format!("refs/heads/{}", branch),
+        Some(peer_id) => format!("refs/remotes/{}/heads/{}", peer_id, branch),
+    }
+}
+
+/// Check that the user supplied `branch` is a valid branch name that can't be mistaken for an
+/// option of `git`.
+fn validate_branch(branch: &str) -> Result<(), error::Error> {
+    if branch.starts_with('-')
+        || RefLike::try_from(format!("refs/heads/{}", branch).as_str()).is_err()
+    {
+        return Err(error::Error::InvalidBranchName(branch.to_string()));
+    }
+    Ok(())
+}
+
+/// Name of the git remote for `peer_id`, following the naming of the project include file.
+///
+/// # Errors
+///
+/// The name contains the handle the peer chose, which might not be valid in a ref.
+pub(super) fn remote_name(
+    peer_id: PeerId,
+    person: &link_identities::Person,
+) -> Result<String, error::Error> {
+    let name = format!("{}@{}", person.subject().name, peer_id);
+    if name.starts_with('-')
+        || RefLike::try_from(format!("refs/remotes/{}", name).as_str()).is_err()
+    {
+        return Err(error::Error::InvalidRemoteName(name));
+    }
+    Ok(name)
+}
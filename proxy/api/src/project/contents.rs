@@ -0,0 +1,260 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Detect what a project is made of from the tree of its default branch.
+//!
+//! The license is recognised from a `LICENSE`, `LICENCE` or `COPYING` file at the root of the
+//! tree, either by its `SPDX-License-Identifier` or by phrases characteristic of common license
+//! texts. The language breakdown sums up the sizes of all files by their extension.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use librad::git::types::{Reference, Single};
+use radicle_source::surf::vcs::git::git2;
+
+use crate::error;
+
+/// Characteristic phrases of license texts and the SPDX identifier they indicate. The first
+/// match wins, so more specific licenses come before those they contain the phrases of.
+const LICENSE_PHRASES: &[(&[&str], &str)] = &[
+    (
+        &["GNU AFFERO GENERAL PUBLIC LICENSE", "Version 3"],
+        "AGPL-3.0-only",
+    ),
+    (
+        &["GNU LESSER GENERAL PUBLIC LICENSE", "Version 3"],
+        "LGPL-3.0-only",
+    ),
+    (
+        &["GNU LESSER GENERAL PUBLIC LICENSE", "Version 2.1"],
+        "LGPL-2.1-only",
+    ),
+    (&["GNU GENERAL PUBLIC LICENSE", "Version 3"], "GPL-3.0-only"),
+    (&["GNU GENERAL PUBLIC LICENSE", "Version 2"], "GPL-2.0-only"),
+    (&["Apache License", "Version 2.0"], "Apache-2.0"),
+    (&["Mozilla Public License Version 2.0"], "MPL-2.0"),
+    (&["Permission is hereby granted, free of charge"], "MIT"),
+    (
+        &["Permission to use, copy, modify, and/or distribute this software"],
+        "ISC",
+    ),
+    (
+        &[
+            "Redistribution and use in source and binary forms",
+            "Neither the name",
+        ],
+        "BSD-3-Clause",
+    ),
+    (
+        &["Redistribution and use in source and binary forms"],
+        "BSD-2-Clause",
+    ),
+    (
+        &["This is free and unencumbered software released into the public domain"],
+        "Unlicense",
+    ),
+    (&["CC0 1.0 Universal"], "CC0-1.0"),
+];
+
+/// Languages by file extension.
+const LANGUAGES: &[(&str, &str)] = &[
+    ("bash", "Shell"),
+    ("c", "C"),
+    ("cc", "C++"),
+    ("cjs", "JavaScript"),
+    ("clj", "Clojure"),
+    ("cpp", "C++"),
+    ("cs", "C#"),
+    ("css", "CSS"),
+    ("cxx", "C++"),
+    ("dart", "Dart"),
+    ("ex", "Elixir"),
+    ("exs", "Elixir"),
+    ("go", "Go"),
+    ("h", "C"),
+    ("hpp", "C++"),
+    ("hs", "Haskell"),
+    ("html", "HTML"),
+    ("java", "Java"),
+    ("js", "JavaScript"),
+    ("json", "JSON"),
+    ("jsx", "JavaScript"),
+    ("kt", "Kotlin"),
+    ("lua", "Lua"),
+    ("md", "Markdown"),
+    ("mjs", "JavaScript"),
+    ("ml", "OCaml"),
+    ("nix", "Nix"),
+    ("php", "PHP"),
+    ("py", "Python"),
+    ("rb", "Ruby"),
+    ("rs", "Rust"),
+    ("scala", "Scala"),
+    ("scss", "SCSS"),
+    ("sh", "Shell"),
+    ("sol", "Solidity"),
+    ("svelte", "Svelte"),
+    ("swift", "Swift"),
+    ("toml", "TOML"),
+    ("ts", "TypeScript"),
+    ("tsx", "TypeScript"),
+    ("yaml", "YAML"),
+    ("yml", "YAML"),
+    ("zig", "Zig"),
+];
+
+/// License and languages of a project.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Contents {
+    /// SPDX identifier of the license, if one was recognised.
+    pub license: Option<String>,
+    /// Languages of the files, largest first.
+    pub languages: Vec<Language>,
+}
+
+/// Share of a language in a project.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Language {
+    /// Name of the language.
+    pub name: String,
+    /// Total size of the files in this language.
+    pub bytes: usize,
+}
+
+/// Detect the [`Contents`] of the tree `reference` points to in the monorepo.
+///
+/// # Errors
+///
+///   * The reference could not be resolved.
+///   * The tree or its blobs could not be read.
+pub fn detect(
+    peer: &crate::peer::Peer,
+    reference: &Reference<Single>,
+) -> Result<Contents, error::Error> {
+    let repo = git2::Repository::open(radicle_daemon::state::monorepo(peer.librad_peer()))?;
+    let tree = repo
+        .find_reference(&reference.to_string())?
+        .peel_to_tree()?;
+    let odb = repo.odb()?;
+
+    let mut license = None;
+    for entry in tree.iter() {
+        if entry.kind() != Some(git2::ObjectType::Blob) {
+            continue;
+        }
+        let is_license_file = entry.name().map_or(false, |name| {
+            let name = name.to_uppercase();
+            ["LICENSE", "LICENCE", "COPYING"]
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+        });
+        if is_license_file {
+            let blob = repo.find_blob(entry.id())?;
+            license = recognise_license(&String::from_utf8_lossy(blob.content()));
+            if license.is_some() {
+                break;
+            }
+        }
+    }
+
+    let mut bytes = HashMap::<&str, usize>::new();
+    let mut failed = None;
+    tree.walk(git2::TreeWalkMode::PreOrder, |_, entry| {
+        if entry.kind() != Some(git2::ObjectType::Blob) {
+            return git2::TreeWalkResult::Ok;
+        }
+        if let Some(language) = entry.name().and_then(language) {
+            match odb.read_header(entry.id()) {
+                Ok((size, _)) => *bytes.entry(language).or_default() += size,
+                Err(err) => {
+                    failed = Some(err);
+                    return git2::TreeWalkResult::Abort;
+                },
+            }
+        }
+        git2::TreeWalkResult::Ok
+    })?;
+    if let Some(err) = failed {
+        return Err(err.into());
+    }
+
+    let mut languages = bytes
+        .into_iter()
+        .map(|(name, bytes)| Language {
+            name: name.to_string(),
+            bytes,
+        })
+        .collect::<Vec<_>>();
+    languages.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(Contents { license, languages })
+}
+
+/// Find the SPDX identifier of the license `text`.
+fn recognise_license(text: &str) -> Option<String> {
+    for line in text.lines().take(10) {
+        if let Some((_, id)) = line.split_once("SPDX-License-Identifier:") {
+            return Some(id.trim().to_string());
+        }
+    }
+
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    LICENSE_PHRASES
+        .iter()
+        .find(|(phrases, _)| phrases.iter().all(|phrase| text.contains(phrase)))
+        .map(|(_, id)| (*id).to_string())
+}
+
+/// Language of the file `name` by its extension.
+fn language(name: &str) -> Option<&'static str> {
+    let (_, extension) = name.rsplit_once('.')?;
+    let extension = extension.to_lowercase();
+    LANGUAGES
+        .binary_search_by(|(known, _)| (*known).cmp(extension.as_str()))
+        .ok()
+        .map(|index| LANGUAGES[index].1)
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn recognise_license() {
+        assert_eq!(
+            super::recognise_license(include_str!("../../templates/licenses/MIT.txt")),
+            Some("MIT".to_string())
+        );
+        assert_eq!(
+            super::recognise_license(include_str!("../../templates/licenses/BSD-3-Clause.txt")),
+            Some("BSD-3-Clause".to_string())
+        );
+        assert_eq!(
+            super::recognise_license(include_str!("../../templates/licenses/BSD-2-Clause.txt")),
+            Some("BSD-2-Clause".to_string())
+        );
+        assert_eq!(
+            super::recognise_license("// SPDX-License-Identifier: GPL-3.0-or-later\n"),
+            Some("GPL-3.0-or-later".to_string())
+        );
+        assert_eq!(super::recognise_license("All rights reserved."), None);
+    }
+
+    #[test]
+    fn language() {
+        assert!(super::LANGUAGES
+            .windows(2)
+            .all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(super::language("main.rs"), Some("Rust"));
+        assert_eq!(super::language("App.SVELTE"), Some("Svelte"));
+        assert_eq!(super::language("Makefile"), None);
+        assert_eq!(super::language("archive.tar.gz"), None);
+    }
+}
@@ -0,0 +1,212 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Import an existing repository from a git URL as a new project.
+//!
+//! The repository is cloned with `git` so that the credentials and SSH setup of the user apply.
+//! Every remote branch gets a local branch, the clone is initialised as a project like an existing
+//! working copy and its tags are pushed to the monorepo.
+
+use std::{
+    convert::TryFrom as _,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use librad::git::{
+    local::url::LocalUrl,
+    types::{remote::Remote, Force, Refspec},
+};
+use link_identities::git::Urn;
+use radicle_git_ext::{OneLevel, RefLike};
+use radicle_source::surf::vcs::git::git2;
+
+use crate::error;
+
+/// A repository to import.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Import {
+    /// URL to clone from. Anything `git clone` understands, e.g. `file://`, `https://` or `ssh://`.
+    pub url: String,
+    /// Directory the working copy is created in.
+    pub path: PathBuf,
+    /// Name of the project. Defaults to the last component of `url`.
+    pub name: Option<String>,
+    /// Long form outline.
+    #[serde(default)]
+    pub description: String,
+    /// Default branch of the project. Defaults to the branch `HEAD` of the cloned repository
+    /// points to.
+    pub default_branch: Option<String>,
+}
+
+/// Clone the repository described by `import` and initialise it as a project of `owner`.
+///
+/// Returns the URN of the created project and the location of its working copy.
+///
+/// # Errors
+///
+///   * The working copy location already exists.
+///   * Cloning failed.
+///   * The default branch doesn't exist or is not a valid branch name.
+///   * Initialising the project or pushing its tags failed.
+pub async fn import(
+    peer: &crate::peer::Peer,
+    owner: &radicle_daemon::LocalIdentity,
+    import: Import,
+) -> Result<(Urn, PathBuf), error::Error> {
+    let name = match import.name {
+        Some(name) => name,
+        None => name_from_url(&import.url)?,
+    };
+    let repo_path = import.path.join(&name);
+    if repo_path.exists() {
+        return Err(error::Error::WorkingCopyExists(repo_path));
+    }
+
+    tokio::fs::create_dir_all(&import.path).await?;
+    super::git(
+        &import.path,
+        &[
+            "clone".to_string(),
+            "--".to_string(),
+            import.url.clone(),
+            name.clone(),
+        ],
+    )
+    .await?;
+
+    let project = match init(
+        peer,
+        owner,
+        &repo_path,
+        import.description,
+        import.default_branch,
+    )
+    .await
+    {
+        Ok(project) => project,
+        Err(err) => {
+            // Don't leave the clone behind, a retry would fail with `WorkingCopyExists`.
+            tokio::fs::remove_dir_all(&repo_path).await?;
+            return Err(err);
+        },
+    };
+
+    {
+        let repo = git2::Repository::open(&repo_path)?;
+        let mut rad = Remote::rad_remote(
+            LocalUrl::from(project.urn()),
+            Refspec {
+                src: librad::refspec_pattern!("refs/tags/*"),
+                dst: librad::refspec_pattern!("refs/tags/*"),
+                force: Force::False,
+            },
+        );
+        let settings = radicle_daemon::state::settings(peer.librad_peer());
+        crate::control::push_tags(&mut rad, settings, &repo)?;
+    }
+
+    Ok((project.urn(), repo_path))
+}
+
+/// Initialise the clone at `repo_path` as a project of `owner`.
+async fn init(
+    peer: &crate::peer::Peer,
+    owner: &radicle_daemon::LocalIdentity,
+    repo_path: &Path,
+    description: String,
+    default_branch: Option<String>,
+) -> Result<librad::identities::Project, error::Error> {
+    let default_branch = {
+        let repo = git2::Repository::open(&repo_path)?;
+        track_remote_branches(&repo)?;
+        let branch = match default_branch {
+            Some(branch) => branch,
+            None => repo
+                .head()?
+                .shorthand()
+                .map(ToString::to_string)
+                .ok_or(error::Error::MissingDefaultBranch)?,
+        };
+        if repo.find_branch(&branch, git2::BranchType::Local).is_err() {
+            return Err(error::Error::BranchNotFound(branch));
+        }
+        OneLevel::from(
+            RefLike::try_from(branch.as_str()).map_err(|_| error::Error::BranchNotFound(branch))?,
+        )
+    };
+
+    Ok(radicle_daemon::state::init_project(
+        peer.librad_peer(),
+        owner,
+        radicle_daemon::project::Create {
+            description,
+            default_branch,
+            repo: radicle_daemon::project::Repo::Existing {
+                path: repo_path.to_path_buf(),
+            },
+        },
+    )
+    .await?)
+}
+
+/// Create a local branch for every branch of the `origin` remote that doesn't have one yet.
+fn track_remote_branches(repo: &git2::Repository) -> Result<(), error::Error> {
+    for branch in repo.branches(Some(git2::BranchType::Remote))? {
+        let (branch, _) = branch?;
+        let name = match branch.name()?.and_then(|name| name.strip_prefix("origin/")) {
+            Some(name) if name != "HEAD" => name.to_string(),
+            _ => continue,
+        };
+        if repo.find_branch(&name, git2::BranchType::Local).is_ok() {
+            continue;
+        }
+        let commit = branch.get().peel_to_commit()?;
+        let mut local = repo.branch(&name, &commit, false)?;
+        local.set_upstream(Some(&format!("origin/{}", name)))?;
+    }
+    Ok(())
+}
+
+/// Derive a project name from the last path component of `url`, like `git clone` does.
+fn name_from_url(url: &str) -> Result<String, error::Error> {
+    url.trim_end_matches('/')
+        .rsplit(|c| c == '/' || c == ':')
+        .next()
+        .map(|name| name.trim_end_matches(".git"))
+        .filter(|name| !name.is_empty())
+        .map(ToString::to_string)
+        .ok_or_else(|| error::Error::InvalidImportUrl(url.to_string()))
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn name_from_url() {
+        for (url, name) in [
+            (
+                "https://github.com/radicle-dev/radicle-upstream.git",
+                "radicle-upstream",
+            ),
+            (
+                "git@github.com:radicle-dev/radicle-upstream",
+                "radicle-upstream",
+            ),
+            ("file:///tmp/repos/git-platinum/", "git-platinum"),
+            ("upstream", "upstream"),
+        ] {
+            assert_eq!(super::name_from_url(url).unwrap(), name);
+        }
+
+        assert!(super::name_from_url("https://").is_err());
+    }
+}
@@ -0,0 +1,268 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Propose and sign changes to the maintainers of a project.
+//!
+//! Maintainers are the indirect delegations of the project identity document. A change to them is
+//! a new revision of the document that only takes effect once a quorum of the current delegates
+//! has signed it. Every delegate publishes the revision they signed last under their own `rad/id`
+//! ref, so a revision is pending as long as it differs from the verified one.
+
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom as _,
+};
+
+use either::Either;
+use serde::{Deserialize, Serialize};
+
+use librad::git::identities;
+use link_crypto::{PeerId, PublicKey};
+use link_identities::git::{IndirectDelegation, Urn};
+use radicle_git_ext::{Oid, RefLike};
+
+use crate::error;
+
+/// A change to the set of maintainers of a project.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Change {
+    /// Add the person with the given URN as a maintainer.
+    Add {
+        /// URN of the person identity.
+        urn: Urn,
+    },
+    /// Remove the person with the given URN from the maintainers.
+    Remove {
+        /// URN of the person identity.
+        urn: Urn,
+    },
+}
+
+/// A revision of the project identity document that has not reached quorum yet.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Proposal {
+    /// Revision of the identity document.
+    pub revision: Oid,
+    /// Delegates that published the revision under their `rad/id` ref.
+    pub proposed_by: Vec<PeerId>,
+    /// Maintainers the project would have once the revision is accepted.
+    pub maintainers: HashSet<Urn>,
+    /// Keys that signed the revision.
+    pub signed_by: Vec<PeerId>,
+}
+
+/// Propose `change` to the maintainers of the project `urn` by signing a new revision of its
+/// identity document.
+///
+/// Returns the resulting revision, which is pending until enough delegates signed it.
+///
+/// # Errors
+///
+///   * The project or the person to add could not be found.
+///   * The local identity is not a delegate of the project.
+///   * The change would leave the project without maintainers or removes a non-maintainer.
+///   * Signing or storing the new revision failed.
+pub async fn propose(
+    peer: &crate::peer::Peer,
+    owner: &radicle_daemon::LocalIdentity,
+    urn: Urn,
+    change: Change,
+) -> Result<Proposal, error::Error> {
+    let project = radicle_daemon::state::get_project(peer.librad_peer(), urn.clone())
+        .await?
+        .ok_or(error::Error::ProjectNotFound)?;
+    super::ensure_delegate(peer, owner, &project)?;
+
+    let owner = owner.clone();
+    let local_peer_id = peer.librad_peer().peer_id();
+    peer.librad_peer()
+        .using_storage(move |storage| {
+            let mut delegations = project
+                .delegations()
+                .iter()
+                .map(|delegation| match delegation {
+                    Either::Left(key) => Either::Left(*key),
+                    Either::Right(person) => Either::Right(person.clone()),
+                })
+                .collect::<Vec<_>>();
+
+            match change {
+                Change::Add { urn: person_urn } => {
+                    let person = identities::person::get(storage, &person_urn)?
+                        .ok_or(error::Error::PersonNotFound)?;
+                    if !delegations.iter().any(|delegation| {
+                        delegation.as_ref().right().map(|person| person.urn())
+                            == Some(person_urn.clone())
+                    }) {
+                        delegations.push(Either::Right(person));
+                    }
+                },
+                Change::Remove { urn: person_urn } => {
+                    let before = delegations.len();
+                    delegations.retain(|delegation| match delegation {
+                        Either::Left(_) => true,
+                        Either::Right(person) => person.urn() != person_urn,
+                    });
+                    if delegations.len() == before {
+                        return Err(error::Error::InvalidDelegations(format!(
+                            "{} is not a maintainer",
+                            person_urn
+                        )));
+                    }
+                },
+            }
+
+            if delegations.is_empty() {
+                return Err(error::Error::InvalidDelegations(
+                    "a project needs at least one maintainer".to_string(),
+                ));
+            }
+
+            let payload = project.payload().clone();
+            let delegations = IndirectDelegation::try_from_iter(delegations)
+                .map_err(|err| error::Error::InvalidDelegations(err.to_string()))?;
+            let updated = identities::project::update(
+                storage,
+                &urn,
+                Some(owner),
+                Some(payload),
+                Some(delegations),
+            )?;
+
+            Ok(Proposal {
+                revision: Oid::from(updated.revision),
+                proposed_by: vec![local_peer_id],
+                maintainers: maintainers(&updated),
+                signed_by: signers(&updated),
+            })
+        })
+        .await?
+}
+
+/// List the revisions of the identity document of project `urn` that the delegates published but
+/// that are not verified yet.
+///
+/// # Errors
+///
+///   * The project could not be found.
+///   * The identity documents could not be read.
+pub async fn proposals(peer: &crate::peer::Peer, urn: Urn) -> Result<Vec<Proposal>, error::Error> {
+    let local_peer_id = peer.librad_peer().peer_id();
+    peer.librad_peer()
+        .using_storage(move |storage| {
+            let verified =
+                identities::project::verify(storage, &urn)?.ok_or(error::Error::ProjectNotFound)?;
+
+            let mut proposals = HashMap::<Oid, Proposal>::new();
+            for delegate in delegate_peers(&verified) {
+                let delegate_urn = if delegate == local_peer_id {
+                    urn.clone()
+                } else {
+                    urn.with_path(
+                        RefLike::try_from(format!("refs/remotes/{}/rad/id", delegate).as_str())
+                            .expect("peer id is a valid ref component"),
+                    )
+                };
+                let view = match identities::project::get(storage, &delegate_urn)? {
+                    Some(view) => view,
+                    // We haven't replicated this delegate's view yet.
+                    None => continue,
+                };
+                if view.revision == verified.revision {
+                    continue;
+                }
+
+                proposals
+                    .entry(Oid::from(view.revision))
+                    .or_insert_with(|| Proposal {
+                        revision: Oid::from(view.revision),
+                        proposed_by: vec![],
+                        maintainers: maintainers(&view),
+                        signed_by: vec![],
+                    })
+                    .merge(delegate, signers(&view));
+            }
+
+            Ok(proposals.into_values().collect())
+        })
+        .await?
+}
+
+/// Sign the revision of the identity document of project `urn` that `delegate` published.
+///
+/// Returns the remaining pending proposals. The signed revision is no longer among them if our
+/// signature completed the quorum.
+///
+/// # Errors
+///
+///   * The project could not be found.
+///   * The local identity is not a delegate of the project.
+///   * Merging and signing the revision failed.
+pub async fn sign(
+    peer: &crate::peer::Peer,
+    owner: &radicle_daemon::LocalIdentity,
+    urn: Urn,
+    delegate: PeerId,
+) -> Result<Vec<Proposal>, error::Error> {
+    let project = radicle_daemon::state::get_project(peer.librad_peer(), urn.clone())
+        .await?
+        .ok_or(error::Error::ProjectNotFound)?;
+    super::ensure_delegate(peer, owner, &project)?;
+
+    peer.librad_peer()
+        .using_storage({
+            let urn = urn.clone();
+            move |storage| identities::project::merge(storage, &urn, delegate)
+        })
+        .await??;
+
+    proposals(peer, urn).await
+}
+
+impl Proposal {
+    /// Record that `delegate` published this revision with the given signatures.
+    fn merge(&mut self, delegate: PeerId, signed_by: Vec<PeerId>) {
+        self.proposed_by.push(delegate);
+        for signer in signed_by {
+            if !self.signed_by.contains(&signer) {
+                self.signed_by.push(signer);
+            }
+        }
+    }
+}
+
+/// URNs of the persons the project delegates to.
+fn maintainers(project: &link_identities::git::Project) -> HashSet<Urn> {
+    project
+        .delegations()
+        .iter()
+        .filter_map(|delegation| delegation.as_ref().right().map(|person| person.urn()))
+        .collect()
+}
+
+/// Keys that signed the revision of `project`.
+fn signers(project: &link_identities::git::Project) -> Vec<PeerId> {
+    project
+        .signatures
+        .keys()
+        .map(|key| PeerId::from(*key))
+        .collect()
+}
+
+/// All peers whose keys are delegates of `project`, either directly or through a person.
+pub(super) fn delegate_peers(project: &link_identities::git::Project) -> Vec<PeerId> {
+    project
+        .delegations()
+        .iter()
+        .flat_map(|delegation| match delegation {
+            Either::Left(key) => Either::Left(std::iter::once(key)),
+            Either::Right(person) => Either::Right(person.delegations().iter()),
+        })
+        .map(|key: &PublicKey| PeerId::from(*key))
+        .collect()
+}
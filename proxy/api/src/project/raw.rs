@@ -0,0 +1,98 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Read files of a project at a revision straight from the monorepo.
+//!
+//! Unlike [`radicle_source::blob`] this gives access to the bytes of binary files, which is what
+//! images embedded in a README need.
+
+use std::path::Path;
+
+use link_crypto::PeerId;
+use link_identities::git::Urn;
+use radicle_source::{surf::vcs::git::git2, Revision};
+
+use crate::error;
+
+/// Kind of the object found at a path of a project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// A file.
+    Blob,
+    /// A directory.
+    Tree,
+}
+
+/// Read the file at `path` of project `urn` at `revision`, or the default branch if `None`.
+///
+/// # Errors
+///
+///   * The project or its default branch could not be found.
+///   * The revision could not be resolved.
+///   * There is no file at `path`.
+pub async fn blob(
+    peer: &crate::peer::Peer,
+    urn: Urn,
+    revision: Option<Revision<PeerId>>,
+    path: String,
+) -> Result<Vec<u8>, error::Error> {
+    let default_branch =
+        radicle_daemon::state::find_default_branch(peer.librad_peer(), urn.clone()).await?;
+    let repo = git2::Repository::open(radicle_daemon::state::monorepo(peer.librad_peer()))?;
+    let tree = tree(&repo, &urn, &default_branch.to_string(), revision.as_ref())?;
+    let entry = tree
+        .get_path(Path::new(&path))
+        .map_err(|_| error::Error::PathNotFound(path.clone()))?;
+    let blob = entry
+        .to_object(&repo)?
+        .into_blob()
+        .map_err(|_| error::Error::PathNotFound(path))?;
+
+    Ok(blob.content().to_vec())
+}
+
+/// Kind of the object at `path` in `tree`, `None` if there is nothing.
+#[must_use]
+pub fn kind(tree: &git2::Tree, path: &str) -> Option<Kind> {
+    match tree.get_path(Path::new(path)).ok()?.kind()? {
+        git2::ObjectType::Blob => Some(Kind::Blob),
+        git2::ObjectType::Tree => Some(Kind::Tree),
+        _ => None,
+    }
+}
+
+/// Resolve the tree of project `urn` at `revision`, or at `default_branch` if `None`.
+///
+/// # Errors
+///
+///   * The revision could not be resolved to a commit.
+pub fn tree<'repo>(
+    repo: &'repo git2::Repository,
+    urn: &Urn,
+    default_branch: &str,
+    revision: Option<&Revision<PeerId>>,
+) -> Result<git2::Tree<'repo>, error::Error> {
+    let namespace = format!("refs/namespaces/{}/refs", urn.encode_id());
+    let object = match revision {
+        None => repo.revparse_single(default_branch)?,
+        Some(Revision::Branch {
+            name,
+            peer_id: None,
+        }) => repo.revparse_single(&format!("{}/heads/{}", namespace, name))?,
+        Some(Revision::Branch {
+            name,
+            peer_id: Some(peer_id),
+        }) => repo.revparse_single(&format!("{}/remotes/{}/heads/{}", namespace, peer_id, name))?,
+        Some(Revision::Tag { name }) => {
+            repo.revparse_single(&format!("{}/tags/{}", namespace, name))?
+        },
+        Some(Revision::Sha { sha }) => {
+            repo.find_object(git2::Oid::from_str(&sha.to_string())?, None)?
+        },
+    };
+
+    Ok(object.peel_to_commit()?.tree()?)
+}
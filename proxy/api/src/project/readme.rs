@@ -0,0 +1,246 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Find the README of a project and render it to HTML.
+//!
+//! Markdown is rendered with [`pulldown_cmark`], any other README is shown as preformatted text.
+//! Relative images are rewritten to absolute URLs of the `raw` source endpoint of the proxy.
+//! Relative links are rewritten to `#` and carry the path they point at in the tree in
+//! `data-path` and whether it is a `blob` or a `tree` in `data-kind`, so the client can open them
+//! in its source browser. The resulting HTML is sanitised with [`ammonia`], so it is safe to
+//! embed.
+
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+use serde::Serialize;
+
+use link_crypto::PeerId;
+use link_identities::git::Urn;
+use radicle_source::{surf::vcs::git::git2, Revision};
+
+use super::raw;
+use crate::error;
+
+/// README file names in order of preference. Matched case-insensitively.
+const FILE_NAMES: &[&str] = &[
+    "README.md",
+    "README.markdown",
+    "README.mdown",
+    "README.mkd",
+    "README",
+    "README.txt",
+    "README.rst",
+    "README.org",
+];
+
+/// A rendered README.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Readme {
+    /// Location of the README in the tree.
+    pub path: String,
+    /// Sanitised HTML.
+    pub html: String,
+}
+
+/// Query for the raw source endpoint images are rewritten to.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RawQuery<'a> {
+    /// Location of the image in the tree.
+    path: &'a str,
+    /// Revision the README was rendered at.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revision: Option<&'a Revision<PeerId>>,
+}
+
+/// Find and render the README of project `urn` at `revision`, or the default branch if `None`.
+///
+/// Images point at the proxy reachable at `origin`, e.g. `http://127.0.0.1:17246`.
+///
+/// # Errors
+///
+///   * The project or its default branch could not be found.
+///   * The revision could not be resolved.
+///   * The project has no README.
+pub async fn get(
+    peer: &crate::peer::Peer,
+    urn: Urn,
+    revision: Option<Revision<PeerId>>,
+    origin: &str,
+) -> Result<Readme, error::Error> {
+    let default_branch =
+        radicle_daemon::state::find_default_branch(peer.librad_peer(), urn.clone()).await?;
+    let repo = git2::Repository::open(radicle_daemon::state::monorepo(peer.librad_peer()))?;
+    let tree = raw::tree(&repo, &urn, &default_branch.to_string(), revision.as_ref())?;
+
+    let entry = FILE_NAMES
+        .iter()
+        .find_map(|file_name| {
+            tree.iter().find(|entry| {
+                entry.kind() == Some(git2::ObjectType::Blob)
+                    && entry
+                        .name()
+                        .map_or(false, |name| name.eq_ignore_ascii_case(file_name))
+            })
+        })
+        .ok_or(error::Error::ReadmeNotFound)?;
+    let path = entry.name().unwrap_or_default().to_string();
+    let blob = repo.find_blob(entry.id())?;
+    let text = String::from_utf8_lossy(blob.content());
+
+    let is_markdown = path.rsplit_once('.').map_or(false, |(_, extension)| {
+        ["md", "markdown", "mdown", "mkd"].contains(&extension.to_lowercase().as_str())
+    });
+    let html = if is_markdown {
+        render_markdown(
+            &text,
+            |dest| resolve(dest).map(|path| (raw::kind(&tree, &path), path)),
+            |dest| image_url(origin, &urn, revision.as_ref(), dest),
+        )
+    } else {
+        format!("<pre>{}</pre>", ammonia::clean_text(&text))
+    };
+
+    Ok(Readme {
+        path,
+        html: ammonia::Builder::default()
+            .add_generic_attributes(&["align"])
+            .add_tag_attributes("a", &["data-path", "data-kind"])
+            .clean(&html)
+            .to_string(),
+    })
+}
+
+/// Render `markdown` to HTML. Links for which `link` returns a path in the tree are rewritten
+/// to point at that path, images for which `image` returns a URL are rewritten to that URL.
+fn render_markdown(
+    markdown: &str,
+    link: impl Fn(&str) -> Option<(Option<raw::Kind>, String)>,
+    image: impl Fn(&str) -> Option<String>,
+) -> String {
+    let parser = Parser::new_ext(
+        markdown,
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS,
+    )
+    .map(|event| match event {
+        Event::Start(Tag::Link(link_type, dest, title)) => match link(&dest) {
+            Some((kind, path)) => Event::Html(CowStr::from(link_html(kind, &path, &title))),
+            None => Event::Start(Tag::Link(link_type, dest, title)),
+        },
+        Event::Start(Tag::Image(link_type, dest, title)) => {
+            let dest = image(&dest).map_or(dest, CowStr::from);
+            Event::Start(Tag::Image(link_type, dest, title))
+        },
+        event => event,
+    });
+
+    let mut html = String::new();
+    html::push_html(&mut html, parser);
+    html
+}
+
+/// Opening tag of a link to `path` in the tree. A missing `kind` is treated as a file.
+fn link_html(kind: Option<raw::Kind>, path: &str, title: &str) -> String {
+    let kind = match kind {
+        Some(raw::Kind::Tree) => "tree",
+        Some(raw::Kind::Blob) | None => "blob",
+    };
+    let mut html = format!(
+        "<a href=\"#\" data-path=\"{}\" data-kind=\"{}\"",
+        ammonia::clean_text(path),
+        kind
+    );
+    if !title.is_empty() {
+        html.push_str(&format!(" title=\"{}\"", ammonia::clean_text(title)));
+    }
+    html.push('>');
+    html
+}
+
+/// Absolute URL of the raw source endpoint of the proxy at `origin` for a relative image `dest`.
+/// Returns `None` for absolute URLs and images outside of the repository.
+fn image_url(
+    origin: &str,
+    urn: &Urn,
+    revision: Option<&Revision<PeerId>>,
+    dest: &str,
+) -> Option<String> {
+    let path = resolve(dest)?;
+    let query = RawQuery {
+        path: &path,
+        revision,
+    };
+
+    Some(format!(
+        "{}/v1/source/raw/{}?{}",
+        origin,
+        urn,
+        serde_qs::to_string(&query).ok()?
+    ))
+}
+
+/// Resolve a relative link `dest` of a file at the root of the tree to a path in the tree.
+fn resolve(dest: &str) -> Option<String> {
+    let is_url = dest
+        .split_once(':')
+        .map_or(false, |(scheme, _)| !scheme.contains('/'));
+    if dest.is_empty() || dest.starts_with('#') || dest.starts_with("//") || is_url {
+        return None;
+    }
+
+    let dest = dest.split(|c| c == '#' || c == '?').next()?;
+    let mut components = vec![];
+    for component in dest.split('/') {
+        match component {
+            "" | "." => {},
+            ".." => {
+                components.pop()?;
+            },
+            component => components.push(component),
+        }
+    }
+
+    Some(components.join("/"))
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn resolve() {
+        assert_eq!(
+            super::resolve("docs/intro.md"),
+            Some("docs/intro.md".into())
+        );
+        assert_eq!(
+            super::resolve("./docs/../logo.png"),
+            Some("logo.png".into())
+        );
+        assert_eq!(super::resolve("/src/lib.rs#L10"), Some("src/lib.rs".into()));
+        assert_eq!(super::resolve("../outside"), None);
+        assert_eq!(super::resolve("#usage"), None);
+        assert_eq!(super::resolve("https://radicle.xyz"), None);
+        assert_eq!(super::resolve("mailto:hello@radicle.xyz"), None);
+        assert_eq!(super::resolve("//radicle.xyz/logo.png"), None);
+    }
+
+    #[test]
+    fn render_markdown() {
+        let html = super::render_markdown(
+            "[docs](docs \"Docs\") ![logo](logo.png) [site](https://radicle.xyz)",
+            |dest| super::resolve(dest).map(|path| (Some(super::raw::Kind::Tree), path)),
+            |dest| Some(format!("http://127.0.0.1:17246/{}", dest)),
+        );
+
+        assert_eq!(
+            html,
+            "<p><a href=\"#\" data-path=\"docs\" data-kind=\"tree\" title=\"Docs\">docs</a> \
+             <img src=\"http://127.0.0.1:17246/logo.png\" alt=\"logo\" /> \
+             <a href=\"https://radicle.xyz\">site</a></p>\n"
+        );
+    }
+}
@@ -0,0 +1,345 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Diagnose and repair projects that end up in the [`super::Failure`] list.
+//!
+//! A project fails to list if the default branch can't be resolved, its history can't be read or
+//! its signed refs can't be verified. [`diagnose`] tells these cases apart, [`fetch`] and
+//! [`reset_default_branch`] try to fix them. Requesting the project from the network again goes
+//! through the waiting room and is done by the caller.
+
+use std::net::SocketAddr;
+
+use serde::{Deserialize, Serialize};
+
+use librad::git::refs::Refs;
+use link_crypto::PeerId;
+use link_identities::git::Urn;
+use radicle_git_ext::Oid;
+use radicle_source::surf::vcs::git::git2;
+
+use crate::error;
+
+/// A reason for a project to fail.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Problem {
+    /// The identity document does not name a default branch.
+    NoDefaultBranch,
+    /// Neither the local peer nor any delegate has the default branch.
+    #[serde(rename_all = "camelCase")]
+    MissingRef {
+        /// Name of the missing branch reference.
+        reference: String,
+    },
+    /// An object reachable from `reference` cannot be read from the monorepo.
+    #[serde(rename_all = "camelCase")]
+    CorruptObject {
+        /// Name of the reference the object is reachable from.
+        reference: String,
+        /// The error reported by git.
+        message: String,
+    },
+    /// The signed refs of a peer are missing or their signature doesn't verify.
+    #[serde(rename_all = "camelCase")]
+    UnverifiableSignedRefs {
+        /// The peer the signed refs belong to.
+        peer_id: PeerId,
+        /// Why the signed refs could not be verified.
+        message: String,
+    },
+}
+
+/// The default branch head of a delegate.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Head {
+    /// The delegate.
+    pub peer_id: PeerId,
+    /// Commit the default branch of the delegate points to.
+    pub oid: Oid,
+}
+
+/// The result of diagnosing a project.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnosis {
+    /// The project that was diagnosed.
+    pub urn: Urn,
+    /// Everything that is wrong with the project. Empty if the project is healthy.
+    pub problems: Vec<Problem>,
+    /// Readable default branch heads of delegates the local default branch can be reset to.
+    pub delegate_heads: Vec<Head>,
+}
+
+/// A repair action for a failed project.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Action {
+    /// Request the project from the network through the waiting room.
+    Request,
+    /// Fetch the project from a specific peer.
+    #[serde(rename_all = "camelCase")]
+    Fetch {
+        /// The peer to fetch from.
+        peer_id: PeerId,
+        /// Addresses of the peer. If empty, the peer must be connected already.
+        #[serde(default)]
+        addrs: Vec<SocketAddr>,
+    },
+    /// Point the local default branch at the default branch of a delegate.
+    #[serde(rename_all = "camelCase")]
+    ResetDefaultBranch {
+        /// The delegate whose head to use.
+        peer_id: PeerId,
+    },
+}
+
+/// Find out what is wrong with project `urn`.
+///
+/// # Errors
+///
+///   * The project could not be found.
+///   * The tracked peers of the project could not be determined.
+///   * The monorepo could not be opened.
+pub async fn diagnose(peer: &crate::peer::Peer, urn: Urn) -> Result<Diagnosis, error::Error> {
+    let project = radicle_daemon::state::get_project(peer.librad_peer(), urn.clone())
+        .await?
+        .ok_or(error::Error::ProjectNotFound)?;
+    let local_peer_id = peer.librad_peer().peer_id();
+    let remotes = radicle_daemon::state::tracked(peer.librad_peer(), urn.clone())
+        .await?
+        .into_iter()
+        .filter_map(radicle_daemon::project::Peer::replicated_remote)
+        .map(|(peer_id, _)| peer_id)
+        .collect::<Vec<_>>();
+
+    let mut diagnosis = Diagnosis {
+        urn: urn.clone(),
+        problems: vec![],
+        delegate_heads: vec![],
+    };
+
+    match project.subject().default_branch.clone() {
+        None => diagnosis.problems.push(Problem::NoDefaultBranch),
+        Some(default_branch) => {
+            let delegates = super::maintainer::delegate_peers(&project)
+                .into_iter()
+                .filter(|peer_id| *peer_id != local_peer_id)
+                .collect::<Vec<_>>();
+            let monorepo = radicle_daemon::state::monorepo(peer.librad_peer());
+            let urn = urn.clone();
+            let (problems, delegate_heads) = tokio::task::spawn_blocking(move || {
+                check_default_branch(&monorepo, &urn, &default_branch.to_string(), &delegates)
+            })
+            .await
+            .expect("failed to join blocking task")?;
+            diagnosis.problems.extend(problems);
+            diagnosis.delegate_heads = delegate_heads;
+        },
+    }
+
+    let signed_refs = peer
+        .librad_peer()
+        .using_storage(move |storage| {
+            let mut problems = vec![];
+            if let Err(err) = Refs::load(storage, &urn, None) {
+                problems.push(Problem::UnverifiableSignedRefs {
+                    peer_id: local_peer_id,
+                    message: err.to_string(),
+                });
+            }
+            for peer_id in remotes {
+                let message = match Refs::load(storage, &urn, Some(peer_id)) {
+                    Ok(Some(_)) => continue,
+                    Ok(None) => "signed refs are missing".to_string(),
+                    Err(err) => err.to_string(),
+                };
+                problems.push(Problem::UnverifiableSignedRefs { peer_id, message });
+            }
+            problems
+        })
+        .await?;
+    diagnosis.problems.extend(signed_refs);
+
+    Ok(diagnosis)
+}
+
+/// Fetch project `urn` from `peer_id` and track the peer.
+///
+/// If `addrs` is empty, the peer must already be connected so that we can discover its address.
+///
+/// # Errors
+///
+///   * The peer is blocked.
+///   * The peer is not connected and no addresses were given.
+///   * Tracking the peer or replicating from it failed.
+pub async fn fetch(
+    peer: &crate::peer::Peer,
+    store: &kv::Store,
+    urn: Urn,
+    peer_id: PeerId,
+    addrs: Vec<SocketAddr>,
+) -> Result<(), error::Error> {
+    crate::blocklist::guard(store, peer_id)?;
+    let addrs = if addrs.is_empty() {
+        peer.librad_peer()
+            .stats()
+            .await
+            .connected_peers
+            .get(&peer_id)
+            .cloned()
+            .ok_or(error::Error::PeerNotConnected(peer_id))?
+    } else {
+        addrs
+    };
+
+    radicle_daemon::state::track(peer.librad_peer(), urn.clone(), peer_id).await?;
+    let output = peer
+        .librad_peer()
+        .replicate((peer_id, addrs), urn.clone(), None)
+        .await?;
+    tracing::info!(project_urn = %urn, %peer_id, mode = ?output.mode, "re-fetched project");
+
+    Ok(())
+}
+
+/// Point the local default branch of project `urn` at the default branch of `delegate` and sign
+/// the updated refs.
+///
+/// # Errors
+///
+///   * The project could not be found or has no default branch.
+///   * `delegate` is not a delegate of the project or doesn't have the default branch.
+///   * Updating or signing the refs failed.
+pub async fn reset_default_branch(
+    peer: &crate::peer::Peer,
+    urn: Urn,
+    delegate: PeerId,
+) -> Result<(), error::Error> {
+    let project = radicle_daemon::state::get_project(peer.librad_peer(), urn.clone())
+        .await?
+        .ok_or(error::Error::ProjectNotFound)?;
+    if !super::maintainer::delegate_peers(&project).contains(&delegate) {
+        return Err(error::Error::NotADelegatePeer(delegate));
+    }
+    if delegate == peer.librad_peer().peer_id() {
+        return Ok(());
+    }
+    let default_branch = project
+        .subject()
+        .default_branch
+        .clone()
+        .ok_or(error::Error::MissingDefaultBranch)?
+        .to_string();
+
+    let monorepo = radicle_daemon::state::monorepo(peer.librad_peer());
+    tokio::task::spawn_blocking({
+        let urn = urn.clone();
+        move || -> Result<(), error::Error> {
+            let repo = git2::Repository::open(monorepo)?;
+            let remote = branch_ref(&urn, Some(delegate), &default_branch);
+            let commit = match repo.find_reference(&remote) {
+                Ok(reference) => reference.peel_to_commit()?,
+                Err(err) if err.code() == git2::ErrorCode::NotFound => {
+                    return Err(error::Error::BranchNotFound(default_branch));
+                },
+                Err(err) => return Err(err.into()),
+            };
+            repo.reference(
+                &branch_ref(&urn, None, &default_branch),
+                commit.id(),
+                true,
+                &format!("reset default branch to {}", delegate),
+            )?;
+            Ok(())
+        }
+    })
+    .await
+    .expect("failed to join blocking task")?;
+
+    peer.librad_peer()
+        .using_storage(move |storage| Refs::update(storage, &urn))
+        .await??;
+
+    Ok(())
+}
+
+/// Check that the default branch of the local peer and of `delegates` can be read.
+///
+/// Returns the problems found and the heads of the delegates whose default branch is readable.
+fn check_default_branch(
+    monorepo: &std::path::Path,
+    urn: &Urn,
+    default_branch: &str,
+    delegates: &[PeerId],
+) -> Result<(Vec<Problem>, Vec<Head>), error::Error> {
+    let repo = git2::Repository::open(monorepo)?;
+    let mut problems = vec![];
+    let mut heads = vec![];
+    let mut found = false;
+
+    let candidates = std::iter::once(None).chain(delegates.iter().copied().map(Some));
+    for remote in candidates {
+        let reference = branch_ref(urn, remote, default_branch);
+        match check_history(&repo, &reference) {
+            Ok(None) => {},
+            Ok(Some(oid)) => {
+                found = true;
+                if let Some(peer_id) = remote {
+                    heads.push(Head { peer_id, oid });
+                }
+            },
+            Err(err) => {
+                found = true;
+                problems.push(Problem::CorruptObject {
+                    reference,
+                    message: err.message().to_string(),
+                });
+            },
+        }
+    }
+
+    if !found {
+        problems.push(Problem::MissingRef {
+            reference: format!("refs/heads/{}", default_branch),
+        });
+    }
+
+    Ok((problems, heads))
+}
+
+/// Read every commit and tree reachable from `reference`.
+///
+/// Returns the commit `reference` points to or `None` if it doesn't exist.
+fn check_history(repo: &git2::Repository, reference: &str) -> Result<Option<Oid>, git2::Error> {
+    let head = match repo.find_reference(reference) {
+        Ok(reference) => reference.peel_to_commit()?.id(),
+        Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(head)?;
+    for oid in revwalk {
+        repo.find_commit(oid?)?.tree()?;
+    }
+
+    Ok(Some(Oid::from(head)))
+}
+
+/// Name of the ref of `branch` in the namespace of `urn`, either of the local peer or of `remote`.
+fn branch_ref(urn: &Urn, remote: Option<PeerId>, branch: &str) -> String {
+    match remote {
+        None => format!("refs/namespaces/{}/refs/heads/{}", urn.encode_id(), branch),
+        Some(peer_id) => format!(
+            "refs/namespaces/{}/refs/remotes/{}/heads/{}",
+            urn.encode_id(),
+            peer_id,
+            branch
+        ),
+    }
+}
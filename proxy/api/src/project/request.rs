@@ -0,0 +1,551 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Project requests with custom limits and a history of what happened to them.
+//!
+//! The waiting room of the daemon queries the network for a requested project and clones it from
+//! the first peer that has it, with the same limits for every request. [`create`] stores
+//! [`Options`] next to the request and asks the preferred peers directly while the waiting room
+//! queries the network. [`supervise`] cancels requests that ran out of time or queries and turns
+//! the waiting room transitions into a [`History`] per project URN, so it is possible to tell why
+//! a request didn't succeed.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Utc};
+use futures::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use link_crypto::PeerId;
+use link_identities::git::Urn;
+use radicle_daemon::{
+    peer::WaitingRoomEvent,
+    request::{RequestState, SomeRequest},
+};
+use radicle_git_ext::Oid;
+
+use crate::{blocklist, error};
+
+/// Name for the storage bucket used for request histories.
+const BUCKET_NAME: &str = "project_requests";
+
+/// Number of entries kept per history.
+pub const MAX_ENTRIES: usize = 200;
+
+/// Limits and hints for a project request.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Options {
+    /// Seconds after which the request is cancelled if the project wasn't cloned by then.
+    #[serde(default)]
+    pub timeout: Option<u32>,
+    /// Number of times the network is queried before the request is cancelled.
+    #[serde(default)]
+    pub max_queries: Option<usize>,
+    /// Peers that are asked for the project directly, in order, while the waiting room queries
+    /// the network. No more peers are asked once the request is finished.
+    #[serde(default)]
+    pub preferred_peers: Vec<PeerId>,
+}
+
+/// Everything that happened to the latest request for a project.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct History {
+    /// Options the request was created with.
+    pub options: Options,
+    /// When the request was created.
+    pub created_at: DateTime<Utc>,
+    /// Number of times the network was queried. Counted separately as old entries are dropped.
+    #[serde(default)]
+    pub queries: usize,
+    /// What happened, oldest first.
+    pub entries: VecDeque<Entry>,
+}
+
+/// Something that happened to a request.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    /// When it happened.
+    pub timestamp: DateTime<Utc>,
+    /// What happened.
+    #[serde(flatten)]
+    pub kind: Kind,
+}
+
+/// Kinds of [`Entry`]s.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Kind {
+    /// The request was submitted to the waiting room.
+    Created,
+    /// The network was queried for the project.
+    #[serde(rename_all = "camelCase")]
+    Queried {
+        /// Number of the query, starting at 1.
+        query: usize,
+    },
+    /// `peer` responded that it has the project.
+    #[serde(rename_all = "camelCase")]
+    Found {
+        /// Peer that has the project.
+        peer: PeerId,
+    },
+    /// One of the preferred peers was asked for the project directly.
+    #[serde(rename_all = "camelCase")]
+    AskedPreferredPeer {
+        /// The preferred peer.
+        peer: PeerId,
+    },
+    /// Cloning the project from `peer` started.
+    #[serde(rename_all = "camelCase")]
+    Cloning {
+        /// Peer the project is cloned from.
+        peer: PeerId,
+    },
+    /// Cloning the project from `peer` failed.
+    #[serde(rename_all = "camelCase")]
+    CloningFailed {
+        /// Peer the project was cloned from.
+        peer: PeerId,
+        /// Why cloning failed.
+        reason: String,
+    },
+    /// The project was cloned from `peer`.
+    #[serde(rename_all = "camelCase")]
+    Cloned {
+        /// Peer the project was cloned from.
+        peer: PeerId,
+    },
+    /// The request was cancelled.
+    Canceled,
+    /// The waiting room gave up on the request.
+    TimedOut,
+    /// The request was cancelled because it exceeded one of its [`Options`].
+    #[serde(rename_all = "camelCase")]
+    LimitReached {
+        /// The limit that was exceeded.
+        limit: Limit,
+    },
+}
+
+/// Limits of a request, see [`Options`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Limit {
+    /// [`Options::timeout`]
+    Timeout,
+    /// [`Options::max_queries`]
+    MaxQueries,
+}
+
+impl History {
+    /// Empty history of a request created at `now`.
+    #[must_use]
+    pub const fn new(options: Options, now: DateTime<Utc>) -> Self {
+        Self {
+            options,
+            created_at: now,
+            queries: 0,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// Add an entry for `kind`, dropping the oldest entry if there are more than
+    /// [`MAX_ENTRIES`].
+    fn push(&mut self, kind: Kind, now: DateTime<Utc>) {
+        if let Kind::Queried { .. } = kind {
+            self.queries += 1;
+        }
+        self.entries.push_back(Entry {
+            timestamp: now,
+            kind,
+        });
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Whether the request was cloned or gave up.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.entries.iter().any(|entry| {
+            matches!(
+                entry.kind,
+                Kind::Cloned { .. } | Kind::Canceled | Kind::TimedOut | Kind::LimitReached { .. }
+            )
+        })
+    }
+
+    /// The limit an unfinished request exceeded at `now`, if any. A request exceeds
+    /// [`Options::max_queries`] when the network is queried once more.
+    fn limit_reached(&self, now: DateTime<Utc>) -> Option<Limit> {
+        if self.is_finished() {
+            return None;
+        }
+        let timed_out = self.options.timeout.map_or(false, |timeout| {
+            now.signed_duration_since(self.created_at) >= chrono::Duration::seconds(timeout.into())
+        });
+        let queried_out = self
+            .options
+            .max_queries
+            .map_or(false, |max_queries| self.queries > max_queries);
+
+        if timed_out {
+            Some(Limit::Timeout)
+        } else if queried_out {
+            Some(Limit::MaxQueries)
+        } else {
+            None
+        }
+    }
+}
+
+/// History of the latest request for project `urn`, if any.
+///
+/// # Errors
+///
+/// Errors if we cannot read from the store.
+pub fn history(store: &kv::Store, urn: &Urn) -> Result<Option<History>, error::Error> {
+    Ok(store
+        .bucket::<&str, kv::Json<History>>(Some(BUCKET_NAME))?
+        .get(urn.to_string().as_str())?
+        .map(|json| json.0))
+}
+
+/// Remove the request history of project `urn`.
+///
+/// # Errors
+///
+/// Errors if we cannot write to the store.
+pub fn remove(store: &kv::Store, urn: &Urn) -> Result<(), error::Error> {
+    store
+        .bucket::<&str, kv::Json<History>>(Some(BUCKET_NAME))?
+        .remove(urn.to_string().as_str())?;
+    Ok(())
+}
+
+/// Request project `urn` from the network with `options` and ask the preferred peers for it in
+/// the background. If a request for `urn` is already in progress, only its options are replaced.
+///
+/// # Errors
+///
+///   * One of the preferred peers is blocked.
+///   * We cannot read from or write to the store.
+pub async fn create(
+    peer: &mut crate::peer::Peer,
+    store: &kv::Store,
+    urn: Urn,
+    options: Options,
+) -> Result<SomeRequest<SystemTime>, error::Error> {
+    for peer_id in &options.preferred_peers {
+        blocklist::guard(store, *peer_id)?;
+    }
+    let local = peer.librad_peer().peer_id();
+    let preferred_peers = options
+        .preferred_peers
+        .iter()
+        .copied()
+        .filter(|peer_id| *peer_id != local)
+        .collect::<Vec<_>>();
+
+    let history = match history(store, &urn)? {
+        Some(history) if !history.is_finished() => History { options, ..history },
+        _ => History::new(options, Utc::now()),
+    };
+    set(store, &urn, &history)?;
+
+    let request = peer
+        .daemon_control()
+        .request_project(&urn, SystemTime::now())
+        .await;
+
+    if !preferred_peers.is_empty() {
+        tokio::task::spawn(ask_preferred_peers(
+            peer.clone(),
+            store.clone(),
+            urn,
+            preferred_peers,
+        ));
+    }
+
+    Ok(request)
+}
+
+/// Cancel requests that exceed their [`Options`] and record the waiting room transitions of
+/// `events` in the request histories. Limits of the requests in the waiting room are checked on
+/// every transition, which the waiting room makes at least on every tick.
+///
+/// The future completes when `events` ends.
+pub async fn supervise(
+    peer: crate::peer::Peer,
+    store: kv::Store,
+    events: impl Stream<Item = radicle_daemon::PeerEvent>,
+) {
+    events
+        .for_each(|event| {
+            let mut peer = peer.clone();
+            let store = store.clone();
+            async move {
+                if let radicle_daemon::PeerEvent::WaitingRoomTransition(transition) = event {
+                    let result = observe(
+                        &mut peer,
+                        &store,
+                        transition.event,
+                        &transition.state_before,
+                        &transition.state_after,
+                    )
+                    .await;
+                    if let Err(err) = result {
+                        tracing::warn!(?err, "failed to supervise project requests");
+                    }
+                }
+            }
+        })
+        .await;
+}
+
+/// Record the transition from `state_before` to `state_after` caused by `event` and cancel the
+/// requests that exceeded their limits since.
+async fn observe(
+    peer: &mut crate::peer::Peer,
+    store: &kv::Store,
+    event: WaitingRoomEvent,
+    state_before: &HashMap<Oid, SomeRequest<SystemTime>>,
+    state_after: &HashMap<Oid, SomeRequest<SystemTime>>,
+) -> Result<(), error::Error> {
+    let now = Utc::now();
+    let recorded = match event {
+        WaitingRoomEvent::Tick => None,
+        WaitingRoomEvent::Created { urn } => Some((urn, Some(Kind::Created))),
+        // Queries are numbered once we know how many there were before.
+        WaitingRoomEvent::Queried { urn } => Some((urn, None)),
+        WaitingRoomEvent::Found { urn, peer } => Some((urn, Some(Kind::Found { peer }))),
+        WaitingRoomEvent::Cloning { urn, peer } => Some((urn, Some(Kind::Cloning { peer }))),
+        WaitingRoomEvent::CloningFailed { urn, peer, reason } => Some((
+            urn,
+            Some(Kind::CloningFailed {
+                peer,
+                reason: reason.to_string(),
+            }),
+        )),
+        WaitingRoomEvent::Cloned { urn, peer } => Some((urn, Some(Kind::Cloned { peer }))),
+        WaitingRoomEvent::Canceled { urn } => Some((urn, Some(Kind::Canceled))),
+    };
+    if let Some((urn, kind)) = recorded {
+        update(store, &urn, now, |history| {
+            // Requests that were created again without going through `create` start over.
+            if kind == Some(Kind::Created) && history.is_finished() {
+                *history = History::new(Options::default(), now);
+            }
+            // Requests are cancelled once a preferred peer gave us the project, which is not
+            // worth an entry.
+            if kind == Some(Kind::Canceled) && history.is_finished() {
+                return;
+            }
+            let kind = kind.unwrap_or(Kind::Queried {
+                query: history.queries + 1,
+            });
+            history.push(kind, now);
+        })?;
+    }
+
+    for (id, request) in state_after {
+        let was_timed_out = state_before.get(id).map_or(false, is_timed_out);
+        if is_timed_out(request) && !was_timed_out {
+            update(store, &Urn::new(*id), now, |history| {
+                history.push(Kind::TimedOut, now);
+            })?;
+        }
+    }
+
+    // Only requests the waiting room still knows about can exceed their limits, the histories of
+    // all other requests are finished.
+    let mut exceeded = vec![];
+    for id in state_after.keys() {
+        let urn = Urn::new(*id);
+        if let Some(limit) = history(store, &urn)?.and_then(|history| history.limit_reached(now)) {
+            exceeded.push((urn, limit));
+        }
+    }
+    for (urn, limit) in exceeded {
+        tracing::info!(project_urn = %urn, ?limit, "cancelling project request");
+        update(store, &urn, now, |history| {
+            history.push(Kind::LimitReached { limit }, now);
+        })?;
+        peer.daemon_control()
+            .cancel_project_request(&urn, SystemTime::now())
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Ask `peers` for project `urn` one after the other until one of them gave it to us, then
+/// cancel the request in the waiting room. Stops early if the request finished in the meantime.
+/// Peers that failed to give us the project are untracked again.
+async fn ask_preferred_peers(
+    mut peer: crate::peer::Peer,
+    store: kv::Store,
+    urn: Urn,
+    peers: Vec<PeerId>,
+) {
+    for peer_id in peers {
+        let result = async {
+            if history(&store, &urn)?.map_or(false, |history| history.is_finished()) {
+                return Ok(true);
+            }
+            update(&store, &urn, Utc::now(), |history| {
+                history.push(Kind::AskedPreferredPeer { peer: peer_id }, Utc::now());
+            })?;
+            let kind = match super::repair::fetch(&peer, &store, urn.clone(), peer_id, vec![]).await
+            {
+                Ok(()) => Kind::Cloned { peer: peer_id },
+                Err(err) => {
+                    if let Err(err) =
+                        radicle_daemon::state::untrack(peer.librad_peer(), urn.clone(), peer_id)
+                            .await
+                    {
+                        tracing::warn!(project_urn = %urn, %peer_id, ?err, "failed to untrack preferred peer");
+                    }
+                    Kind::CloningFailed {
+                        peer: peer_id,
+                        reason: err.to_string(),
+                    }
+                },
+            };
+            let cloned = matches!(kind, Kind::Cloned { .. });
+            update(&store, &urn, Utc::now(), |history| {
+                history.push(kind, Utc::now());
+            })?;
+            if cloned {
+                // The waiting room would keep looking for the project otherwise.
+                peer.daemon_control()
+                    .cancel_project_request(&urn, SystemTime::now())
+                    .await?;
+            }
+            Ok::<_, error::Error>(cloned)
+        }
+        .await;
+
+        match result {
+            // Either this peer gave us the project or the request is done otherwise.
+            Ok(true) => break,
+            Ok(false) => {},
+            Err(err) => {
+                tracing::warn!(project_urn = %urn, %peer_id, ?err, "failed to ask preferred peer");
+            },
+        }
+    }
+}
+
+/// Whether the waiting room gave up on `request`.
+fn is_timed_out(request: &SomeRequest<SystemTime>) -> bool {
+    matches!(RequestState::from(request), RequestState::TimedOut)
+}
+
+/// Apply `f` to the history of `urn`. Requests we didn't create get a history with the default
+/// [`Options`], starting at `now`.
+fn update<F>(store: &kv::Store, urn: &Urn, now: DateTime<Utc>, f: F) -> Result<(), error::Error>
+where
+    F: FnOnce(&mut History),
+{
+    let mut history = history(store, urn)?.unwrap_or_else(|| History::new(Options::default(), now));
+    f(&mut history);
+    set(store, urn, &history)
+}
+
+/// Store `history` as the history of `urn`.
+fn set(store: &kv::Store, urn: &Urn, history: &History) -> Result<(), error::Error> {
+    store
+        .bucket::<&str, kv::Json<History>>(Some(BUCKET_NAME))?
+        .set(urn.to_string().as_str(), kv::Json(history.clone()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::{History, Kind, Limit, Options, MAX_ENTRIES};
+
+    #[test]
+    fn limits() {
+        let created_at = chrono::Utc::now();
+        let mut history = History::new(
+            Options {
+                timeout: Some(60),
+                max_queries: Some(2),
+                preferred_peers: vec![],
+            },
+            created_at,
+        );
+        history.push(Kind::Created, created_at);
+        history.push(Kind::Queried { query: 1 }, created_at);
+        history.push(Kind::Queried { query: 2 }, created_at);
+        assert_eq!(history.limit_reached(created_at), None);
+        assert_eq!(
+            history.limit_reached(created_at + chrono::Duration::seconds(60)),
+            Some(Limit::Timeout)
+        );
+
+        history.push(Kind::Queried { query: 3 }, created_at);
+        assert_eq!(history.limit_reached(created_at), Some(Limit::MaxQueries));
+
+        history.push(
+            Kind::LimitReached {
+                limit: Limit::MaxQueries,
+            },
+            created_at,
+        );
+        assert!(history.is_finished());
+        assert_eq!(history.limit_reached(created_at), None);
+    }
+
+    #[test]
+    fn unlimited() {
+        let created_at = chrono::Utc::now();
+        let mut history = History::new(Options::default(), created_at);
+        for query in 1..=10 {
+            history.push(Kind::Queried { query }, created_at);
+        }
+        assert_eq!(
+            history.limit_reached(created_at + chrono::Duration::days(1)),
+            None
+        );
+        assert!(!history.is_finished());
+    }
+
+    #[test]
+    fn queries_outlive_entries() {
+        let created_at = chrono::Utc::now();
+        let mut history = History::new(
+            Options {
+                max_queries: Some(MAX_ENTRIES),
+                ..Options::default()
+            },
+            created_at,
+        );
+        for query in 1..=MAX_ENTRIES {
+            history.push(Kind::Queried { query }, created_at);
+        }
+        assert_eq!(history.limit_reached(created_at), None);
+
+        history.push(
+            Kind::Queried {
+                query: MAX_ENTRIES + 1,
+            },
+            created_at,
+        );
+        assert_eq!(history.entries.len(), MAX_ENTRIES);
+        assert_eq!(history.queries, MAX_ENTRIES + 1);
+        assert_eq!(history.limit_reached(created_at), Some(Limit::MaxQueries));
+    }
+}
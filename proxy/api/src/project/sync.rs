@@ -0,0 +1,300 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! How up to date the local view of a project is with respect to the remote peers.
+//!
+//! The time of the last fetch from a peer is recorded from peer events in the [`kv::Store`], one
+//! entry per project URN. Everything else is read from the monorepo when a [`Status`] is requested.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use futures::prelude::*;
+use serde::Serialize;
+
+use librad::git::refs::Refs;
+use link_crypto::PeerId;
+use link_identities::git::Urn;
+use radicle_git_ext::Oid;
+use radicle_source::surf::vcs::git::git2;
+
+use crate::error;
+
+/// Name for the storage bucket used for the last fetch times.
+const BUCKET_NAME: &str = "peer_fetches";
+
+/// Sync status of the local view of a project with respect to a remote peer.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Status {
+    /// Last time the project was fetched from the peer, if ever since we started recording.
+    pub last_fetch: Option<DateTime<Utc>>,
+    /// Tip of the default branch of the peer.
+    pub head: Option<Oid>,
+    /// Our default branch compared to the one of the peer. `None` if either is missing or the
+    /// histories can't be compared.
+    pub default_branch: Option<Comparison>,
+    /// Whether the signed refs of the peer verify.
+    pub signed_refs: SignedRefs,
+}
+
+/// Our default branch compared to the default branch of a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Comparison {
+    /// Both point to the same commit.
+    Even,
+    /// We have commits the peer doesn't have.
+    Ahead {
+        /// Number of commits only we have.
+        commits: usize,
+    },
+    /// The peer has commits we don't have.
+    Behind {
+        /// Number of commits only the peer has.
+        commits: usize,
+    },
+    /// Both have commits the other doesn't have.
+    Diverged {
+        /// Number of commits only we have.
+        ahead: usize,
+        /// Number of commits only the peer has.
+        behind: usize,
+    },
+}
+
+impl Comparison {
+    /// Compare from the number of commits only we and only the peer have.
+    const fn new(ahead: usize, behind: usize) -> Self {
+        match (ahead, behind) {
+            (0, 0) => Self::Even,
+            (commits, 0) => Self::Ahead { commits },
+            (0, commits) => Self::Behind { commits },
+            (ahead, behind) => Self::Diverged { ahead, behind },
+        }
+    }
+}
+
+/// Verification result of the signed refs of a peer.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum SignedRefs {
+    /// The signature of the signed refs verifies.
+    Verified,
+    /// We don't have signed refs of the peer.
+    Missing,
+    /// The signed refs could not be loaded or their signature doesn't verify.
+    Invalid {
+        /// Why the signed refs could not be verified.
+        message: String,
+    },
+}
+
+/// Compute the sync [`Status`] of project `urn` with respect to `remote`, whose default branch is
+/// `default_branch`.
+///
+/// Our side of the comparison is the default branch as resolved by
+/// [`radicle_daemon::state::find_default_branch`], which falls back to the branch of a delegate
+/// for projects we only track.
+///
+/// # Errors
+///
+///   * The monorepo could not be read.
+///   * The last fetch time could not be read from the store.
+pub async fn status(
+    peer: &crate::peer::Peer,
+    store: &kv::Store,
+    urn: &Urn,
+    default_branch: &str,
+    remote: PeerId,
+) -> Result<Status, error::Error> {
+    let last_fetch = last_fetches(store, urn)?.get(&remote.to_string()).copied();
+    let ours = radicle_daemon::state::find_default_branch(peer.librad_peer(), urn.clone())
+        .await
+        .ok()
+        .map(|reference| reference.to_string());
+
+    let (head, comparison) = {
+        let repo = git2::Repository::open(radicle_daemon::state::monorepo(peer.librad_peer()))?;
+        let namespace = format!("refs/namespaces/{}/refs", urn.encode_id());
+        let theirs = repo
+            .refname_to_id(&format!(
+                "{}/remotes/{}/heads/{}",
+                namespace, remote, default_branch
+            ))
+            .ok();
+        let ours = ours.and_then(|ours| repo.refname_to_id(&ours).ok());
+        let comparison = match (ours, theirs) {
+            (Some(ours), Some(theirs)) => match repo.graph_ahead_behind(ours, theirs) {
+                Ok((ahead, behind)) => Some(Comparison::new(ahead, behind)),
+                Err(err) => {
+                    tracing::warn!(project_urn = %urn, peer_id = %remote, ?err, "cannot compare default branches");
+                    None
+                },
+            },
+            _ => None,
+        };
+        (theirs.map(Oid::from), comparison)
+    };
+
+    let signed_refs = peer
+        .librad_peer()
+        .using_storage({
+            let urn = urn.clone();
+            move |storage| match Refs::load(storage, &urn, Some(remote)) {
+                Ok(Some(_)) => SignedRefs::Verified,
+                Ok(None) => SignedRefs::Missing,
+                Err(err) => SignedRefs::Invalid {
+                    message: err.to_string(),
+                },
+            }
+        })
+        .await?;
+
+    Ok(Status {
+        last_fetch,
+        head,
+        default_branch: comparison,
+        signed_refs,
+    })
+}
+
+/// Record that project `urn` was fetched from `remote` at `at`.
+///
+/// # Errors
+///
+/// Errors if we cannot read from or write to the store.
+pub fn record_fetch(
+    store: &kv::Store,
+    urn: &Urn,
+    remote: PeerId,
+    at: DateTime<Utc>,
+) -> Result<(), error::Error> {
+    let mut fetches = last_fetches(store, urn)?;
+    fetches.insert(remote.to_string(), at);
+    store
+        .bucket::<&str, kv::Json<HashMap<String, DateTime<Utc>>>>(Some(BUCKET_NAME))?
+        .set(urn.to_string().as_str(), kv::Json(fetches))?;
+    Ok(())
+}
+
+/// Drop the recorded fetch times for `urn`.
+///
+/// # Errors
+///
+/// Errors if we cannot write to the store.
+pub fn remove(store: &kv::Store, urn: &Urn) -> Result<(), error::Error> {
+    store
+        .bucket::<&str, kv::Json<HashMap<String, DateTime<Utc>>>>(Some(BUCKET_NAME))?
+        .remove(urn.to_string().as_str())?;
+    Ok(())
+}
+
+/// Record the fetches `events` report.
+///
+/// The future completes when `events` ends.
+pub async fn record_fetches(
+    store: kv::Store,
+    events: impl Stream<Item = radicle_daemon::PeerEvent>,
+) {
+    events
+        .for_each(|event| {
+            let fetched = match crate::notification::from_peer_event(event) {
+                Some(crate::notification::Notification::ProjectUpdated { provider, urn }) => {
+                    Some((urn, provider))
+                },
+                Some(crate::notification::Notification::RequestCloned { peer, urn }) => {
+                    Some((urn, peer))
+                },
+                _ => None,
+            };
+            if let Some((urn, remote)) = fetched {
+                if let Err(err) = record_fetch(&store, &urn, remote, Utc::now()) {
+                    tracing::warn!(project_urn = %urn, %remote, ?err, "failed to record fetch");
+                }
+            }
+            future::ready(())
+        })
+        .await;
+}
+
+/// Last fetch times of project `urn` by peer.
+fn last_fetches(
+    store: &kv::Store,
+    urn: &Urn,
+) -> Result<HashMap<String, DateTime<Utc>>, error::Error> {
+    Ok(store
+        .bucket::<&str, kv::Json<HashMap<String, DateTime<Utc>>>>(Some(BUCKET_NAME))?
+        .get(urn.to_string().as_str())?
+        .map(|json| json.0)
+        .unwrap_or_default())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use pretty_assertions::assert_eq;
+
+    use link_identities::payload::Person;
+
+    use super::Comparison;
+    use crate::context;
+
+    #[tokio::test]
+    async fn record_fetch() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let (ctx, _) = context::Unsealed::tmp(&tmp_dir)?;
+
+        let owner = radicle_daemon::state::init_owner(
+            ctx.peer.librad_peer(),
+            Person {
+                name: "cloudhead".into(),
+            },
+        )
+        .await?;
+        let urn = crate::control::replicate_platinum(
+            &ctx.peer,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            crate::control::default_branch(),
+        )
+        .await?
+        .urn();
+        let remote = crate::control::generate_peer_id();
+
+        let status = super::status(&ctx.peer, &ctx.rest.store, &urn, "master", remote).await?;
+        assert_eq!(status.last_fetch, None);
+        assert_eq!(status.head, None);
+        assert_eq!(status.default_branch, None);
+        assert!(matches!(status.signed_refs, super::SignedRefs::Missing));
+
+        let now = Utc::now();
+        super::record_fetch(&ctx.rest.store, &urn, remote, now)?;
+        let status = super::status(&ctx.peer, &ctx.rest.store, &urn, "master", remote).await?;
+        assert_eq!(status.last_fetch, Some(now));
+
+        super::remove(&ctx.rest.store, &urn)?;
+        let status = super::status(&ctx.peer, &ctx.rest.store, &urn, "master", remote).await?;
+        assert_eq!(status.last_fetch, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn comparison() {
+        assert_eq!(Comparison::new(0, 0), Comparison::Even);
+        assert_eq!(Comparison::new(2, 0), Comparison::Ahead { commits: 2 });
+        assert_eq!(Comparison::new(0, 3), Comparison::Behind { commits: 3 });
+        assert_eq!(
+            Comparison::new(2, 3),
+            Comparison::Diverged {
+                ahead: 2,
+                behind: 3
+            }
+        );
+    }
+}
@@ -0,0 +1,258 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Scaffolding for new projects.
+//!
+//! A [`Template`] describes the files the initial commit of a new project starts with: a license,
+//! a README, a `.gitignore` preset and the contents of a user-defined template directory. The
+//! user-defined templates live in [`templates_dir`], one directory per template.
+
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use radicle_git_ext::OneLevel;
+use radicle_source::surf::vcs::git::git2;
+
+use crate::{config, error};
+
+/// License texts by SPDX identifier. `{year}` and `{holder}` are replaced when the file is
+/// written.
+const LICENSES: &[(&str, &str)] = &[
+    (
+        "BSD-2-Clause",
+        include_str!("../../templates/licenses/BSD-2-Clause.txt"),
+    ),
+    (
+        "BSD-3-Clause",
+        include_str!("../../templates/licenses/BSD-3-Clause.txt"),
+    ),
+    ("ISC", include_str!("../../templates/licenses/ISC.txt")),
+    ("MIT", include_str!("../../templates/licenses/MIT.txt")),
+    (
+        "Unlicense",
+        include_str!("../../templates/licenses/Unlicense.txt"),
+    ),
+];
+
+/// Files to add to the initial commit of a new project.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Template {
+    /// SPDX identifier of the license to add as `LICENSE`.
+    pub license: Option<String>,
+    /// Add a `README.md` with the name and description of the project.
+    #[serde(default)]
+    pub readme: bool,
+    /// Preset to add as `.gitignore`.
+    pub gitignore: Option<Gitignore>,
+    /// Name of a user-defined template directory whose contents are copied into the project.
+    pub directory: Option<String>,
+}
+
+/// `.gitignore` presets for common toolchains.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Gitignore {
+    /// Go modules.
+    Go,
+    /// Node.js packages.
+    Node,
+    /// Python packages.
+    Python,
+    /// Cargo crates.
+    Rust,
+}
+
+impl Gitignore {
+    /// All presets.
+    const ALL: [Self; 4] = [Self::Go, Self::Node, Self::Python, Self::Rust];
+
+    /// Contents of the `.gitignore` file.
+    const fn contents(self) -> &'static str {
+        match self {
+            Self::Go => include_str!("../../templates/gitignore/go.gitignore"),
+            Self::Node => include_str!("../../templates/gitignore/node.gitignore"),
+            Self::Python => include_str!("../../templates/gitignore/python.gitignore"),
+            Self::Rust => include_str!("../../templates/gitignore/rust.gitignore"),
+        }
+    }
+}
+
+/// Everything a [`Template`] can be made of.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Available {
+    /// SPDX identifiers of the known licenses.
+    pub licenses: Vec<&'static str>,
+    /// `.gitignore` presets.
+    pub gitignores: Vec<Gitignore>,
+    /// Names of the user-defined template directories.
+    pub directories: Vec<String>,
+}
+
+/// Location of the user-defined template directories.
+#[must_use]
+pub fn templates_dir() -> PathBuf {
+    config::dirs().config_dir().join("templates")
+}
+
+/// List the licenses, presets and user-defined template directories.
+///
+/// # Errors
+///
+///   * The template directory could not be read.
+pub fn available() -> Result<Available, error::Error> {
+    let mut directories = vec![];
+    let dir = templates_dir();
+    if dir.is_dir() {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                if let Some(name) = entry.file_name().to_str() {
+                    directories.push(name.to_string());
+                }
+            }
+        }
+    }
+    directories.sort();
+
+    Ok(Available {
+        licenses: LICENSES.iter().map(|(id, _)| *id).collect(),
+        gitignores: Gitignore::ALL.to_vec(),
+        directories,
+    })
+}
+
+/// Create a repository at `repo_path` whose initial commit on `default_branch` contains the files
+/// of `template`.
+///
+/// The files of the template directory are copied first, so the license, README and `.gitignore`
+/// take precedence over files of the same name.
+///
+/// # Errors
+///
+///   * `repo_path` already exists.
+///   * The license or template directory is unknown.
+///   * Writing the files or committing them failed.
+pub fn scaffold(
+    owner: &radicle_daemon::LocalIdentity,
+    template: &Template,
+    description: &str,
+    default_branch: &OneLevel,
+    repo_path: &Path,
+) -> Result<(), error::Error> {
+    if repo_path.exists() {
+        return Err(error::Error::WorkingCopyExists(repo_path.to_path_buf()));
+    }
+    let name = repo_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let holder = owner.subject().name.to_string();
+
+    let license = template
+        .license
+        .as_ref()
+        .map(|id| {
+            LICENSES
+                .iter()
+                .find(|(known, _)| known.eq_ignore_ascii_case(id))
+                .map(|(_, text)| *text)
+                .ok_or_else(|| error::Error::UnknownLicense(id.clone()))
+        })
+        .transpose()?;
+    let directory = template
+        .directory
+        .as_ref()
+        .map(|directory| {
+            let path = templates_dir().join(directory);
+            // Only plain names, the template has to be inside the template directory.
+            let mut components = Path::new(directory).components();
+            let plain = matches!(
+                (components.next(), components.next()),
+                (Some(Component::Normal(_)), None)
+            );
+            if plain && path.is_dir() {
+                Ok(path)
+            } else {
+                Err(error::Error::TemplateNotFound(directory.clone()))
+            }
+        })
+        .transpose()?;
+
+    let repo = git2::Repository::init_opts(
+        repo_path,
+        git2::RepositoryInitOptions::new()
+            .no_reinit(true)
+            .initial_head(&default_branch.to_string()),
+    )?;
+
+    if let Some(directory) = directory {
+        copy_dir(&directory, repo_path)?;
+    }
+    if let Some(license) = license {
+        let year = chrono::Utc::now().format("%Y").to_string();
+        std::fs::write(
+            repo_path.join("LICENSE"),
+            license
+                .replace("{year}", &year)
+                .replace("{holder}", &holder),
+        )?;
+    }
+    if template.readme {
+        let mut readme = format!("# {}\n", name);
+        if !description.is_empty() {
+            readme.push_str(&format!("\n{}\n", description));
+        }
+        std::fs::write(repo_path.join("README.md"), readme)?;
+    }
+    if let Some(gitignore) = template.gitignore {
+        std::fs::write(repo_path.join(".gitignore"), gitignore.contents())?;
+    }
+
+    let mut index = repo.index()?;
+    index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let signature = repo
+        .signature()
+        .or_else(|_| git2::Signature::now(&holder, &format!("{}@{}", holder, owner.urn())))?;
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        "Initial commit",
+        &tree,
+        &[],
+    )?;
+
+    Ok(())
+}
+
+/// Copy the contents of `from` into `to`, leaving out git metadata.
+fn copy_dir(from: &Path, to: &Path) -> Result<(), error::Error> {
+    for entry in WalkDir::new(from)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git")
+    {
+        let entry = entry.map_err(std::io::Error::from)?;
+        let target = to.join(
+            entry
+                .path()
+                .strip_prefix(from)
+                .expect("walked entries are inside the walked directory"),
+        );
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,196 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Track and untrack several peers of a project at once and enforce the
+//! [`TrackingPolicy`] of projects.
+//!
+//! Policies are stored in the session settings, see [`session::tracking_policy`]. [`enforce`]
+//! watches peer events and tracks the peers a policy asks for whenever a project is announced.
+//! [`TrackingPolicy::Everyone`] only ever adds peers. [`TrackingPolicy::Delegates`] also untracks
+//! every peer that is not a delegate and, while it is active, [`guard`] refuses to track them.
+
+use std::collections::HashSet;
+
+use either::Either;
+use futures::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use link_crypto::PeerId;
+use link_identities::git::Urn;
+
+use crate::{
+    blocklist, error, notification::Notification, session, session::settings::TrackingPolicy,
+};
+
+/// Peers to track and untrack.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bulk {
+    /// Peers to start tracking.
+    #[serde(default)]
+    pub track: Vec<PeerId>,
+    /// Peers to stop tracking.
+    #[serde(default)]
+    pub untrack: Vec<PeerId>,
+}
+
+/// Apply the tracking changes of `bulk` to project `urn`. Our own peer is never tracked or
+/// untracked.
+///
+/// # Errors
+///
+///   * The project could not be found.
+///   * One of the peers to track is blocked or not allowed by the tracking policy.
+///   * Tracking or untracking a peer failed.
+pub async fn bulk(
+    peer: &crate::peer::Peer,
+    store: &kv::Store,
+    urn: Urn,
+    bulk: Bulk,
+) -> Result<(), error::Error> {
+    radicle_daemon::state::get_project(peer.librad_peer(), urn.clone())
+        .await?
+        .ok_or(error::Error::ProjectNotFound)?;
+    let local = peer.librad_peer().peer_id();
+
+    for peer_id in &bulk.track {
+        blocklist::guard(store, *peer_id)?;
+        guard(peer, store, &urn, *peer_id).await?;
+    }
+
+    for peer_id in bulk.track.into_iter().filter(|peer_id| *peer_id != local) {
+        radicle_daemon::state::track(peer.librad_peer(), urn.clone(), peer_id).await?;
+    }
+    for peer_id in bulk.untrack.into_iter().filter(|peer_id| *peer_id != local) {
+        radicle_daemon::state::untrack(peer.librad_peer(), urn.clone(), peer_id).await?;
+    }
+
+    Ok(())
+}
+
+/// Check that the tracking policy of project `urn` allows tracking `peer_id`.
+///
+/// # Errors
+///
+///   * The policy is [`TrackingPolicy::Delegates`] and `peer_id` is not a delegate.
+///   * The policy could not be read or the project could not be found.
+pub async fn guard(
+    peer: &crate::peer::Peer,
+    store: &kv::Store,
+    urn: &Urn,
+    peer_id: PeerId,
+) -> Result<(), error::Error> {
+    match session::tracking_policy(store, urn)? {
+        TrackingPolicy::Delegates if !delegates(peer, urn).await?.contains(&peer_id) => {
+            Err(error::Error::NotADelegatePeer(peer_id))
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Set the tracking policy of project `urn` and apply it right away.
+///
+/// # Errors
+///
+///   * The project could not be found.
+///   * The policy could not be stored.
+///   * Tracking or untracking a peer failed.
+pub async fn set_policy(
+    peer: &crate::peer::Peer,
+    store: &kv::Store,
+    urn: Urn,
+    policy: TrackingPolicy,
+) -> Result<(), error::Error> {
+    radicle_daemon::state::get_project(peer.librad_peer(), urn.clone())
+        .await?
+        .ok_or(error::Error::ProjectNotFound)?;
+    session::update_tracking_policy(store, &urn, policy)?;
+    apply(peer, store, &urn, policy, None).await
+}
+
+/// Enforce the tracking policies of all projects announced by `events`.
+///
+/// The future completes when `events` ends.
+pub async fn enforce(
+    peer: crate::peer::Peer,
+    store: kv::Store,
+    events: impl Stream<Item = radicle_daemon::PeerEvent>,
+) {
+    events
+        .for_each(|event| {
+            let peer = peer.clone();
+            let store = store.clone();
+            async move {
+                let (urn, announcer) = match crate::notification::from_peer_event(event) {
+                    Some(Notification::ProjectUpdated { provider, urn }) => (urn, provider),
+                    Some(Notification::RequestCloned { peer, urn }) => (urn, peer),
+                    _ => return,
+                };
+                let result = match session::tracking_policy(&store, &urn) {
+                    Ok(TrackingPolicy::Manual) => Ok(()),
+                    Ok(policy) => apply(&peer, &store, &urn, policy, Some(announcer)).await,
+                    Err(err) => Err(err),
+                };
+                if let Err(err) = result {
+                    tracing::warn!(project_urn = %urn, %announcer, ?err, "failed to enforce tracking policy");
+                }
+            }
+        })
+        .await;
+}
+
+/// Track the peers `policy` asks for, given that `announcer` announced the project. Blocked peers
+/// are never tracked. Under [`TrackingPolicy::Delegates`] all other peers are untracked.
+async fn apply(
+    peer: &crate::peer::Peer,
+    store: &kv::Store,
+    urn: &Urn,
+    policy: TrackingPolicy,
+    announcer: Option<PeerId>,
+) -> Result<(), error::Error> {
+    let peers = match policy {
+        TrackingPolicy::Manual => HashSet::new(),
+        TrackingPolicy::Delegates => delegates(peer, urn).await?,
+        TrackingPolicy::Everyone => announcer.into_iter().collect(),
+    };
+    let local = peer.librad_peer().peer_id();
+    let blocklist = session::blocklist(store)?;
+
+    if policy == TrackingPolicy::Delegates {
+        for project_peer in radicle_daemon::state::tracked(peer.librad_peer(), urn.clone()).await? {
+            let peer_id = project_peer.peer_id();
+            if !peers.contains(&peer_id) {
+                radicle_daemon::state::untrack(peer.librad_peer(), urn.clone(), peer_id).await?;
+            }
+        }
+    }
+
+    for peer_id in peers
+        .into_iter()
+        .filter(|peer_id| *peer_id != local && !blocklist.contains(peer_id))
+    {
+        radicle_daemon::state::track(peer.librad_peer(), urn.clone(), peer_id).await?;
+    }
+
+    Ok(())
+}
+
+/// Peers of the delegates of project `urn`, including all devices of indirect delegates.
+async fn delegates(peer: &crate::peer::Peer, urn: &Urn) -> Result<HashSet<PeerId>, error::Error> {
+    let project = radicle_daemon::state::get_project(peer.librad_peer(), urn.clone())
+        .await?
+        .ok_or(error::Error::ProjectNotFound)?;
+
+    Ok(project
+        .delegations()
+        .iter()
+        .flat_map(|either| match either {
+            Either::Left(key) => Either::Left(std::iter::once(key)),
+            Either::Right(indirect) => Either::Right(indirect.delegations().iter()),
+        })
+        .map(|key| PeerId::from(*key))
+        .collect())
+}
@@ -0,0 +1,491 @@
+// Copyright © 2021 The Radicle Upstream Contributors
+//
+// This file is part of radicle-upstream, distributed under the GPLv3
+// with Radicle Linking Exception. For full terms see the included
+// LICENSE file.
+
+//! Registry of the working copies the proxy created for a project and syncing them with the
+//! monorepo.
+//!
+//! Paths are stored in the [`kv::Store`] per project URN when a project is checked out or created,
+//! together with the [`Origin`] of the working copy.
+//! The state of a working copy is read from disk whenever it is requested, so a working copy that
+//! was moved or deleted outside of the proxy shows up as not existing until it is unregistered.
+//!
+//! [`publish`] and [`pull`] talk to the monorepo through the in-process `rad` transport and sign
+//! with the key of the running peer, so they work without `git-remote-rad` being installed.
+
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr as _,
+};
+
+use nonempty::NonEmpty;
+use serde::{Deserialize, Serialize};
+
+use librad::git::{
+    local::url::LocalUrl,
+    types::{
+        remote::{LocalFetchspec, LocalPushspec, Remote},
+        Fetchspec, Force, Pushspec, Refspec,
+    },
+};
+use link_crypto::PeerId;
+use link_identities::git::Urn;
+use radicle_git_ext::Oid;
+use radicle_source::surf::vcs::git::git2;
+
+use crate::error;
+
+/// Name for the storage bucket used for working copy paths.
+const BUCKET_NAME: &str = "working_copies";
+
+/// State of a registered working copy on disk.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkingCopy {
+    /// Location of the working copy.
+    pub path: PathBuf,
+    /// `false` if there is no git repository at `path` anymore.
+    pub exists: bool,
+    /// The checked out branch, `None` if `HEAD` is detached or the working copy doesn't exist.
+    pub branch: Option<String>,
+    /// `true` if there are uncommitted changes or untracked files.
+    pub dirty: bool,
+    /// Commits on `branch` that are not on the `rad` remote.
+    pub ahead: Option<usize>,
+    /// Commits on the `rad` remote that are not on `branch`.
+    pub behind: Option<usize>,
+}
+
+/// How a registered working copy came to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Origin {
+    /// The proxy checked out the project from the monorepo.
+    Checkout,
+    /// The proxy set up the repository for a new or imported project.
+    Created,
+    /// The repository existed before a project was created from it.
+    Existing,
+}
+
+/// A registered working copy as stored per project.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Registration {
+    /// Location of the working copy.
+    pub path: PathBuf,
+    /// How the working copy came to be.
+    pub origin: Origin,
+}
+
+/// Branches and tags of a working copy to publish.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Publish {
+    /// Location of the working copy.
+    pub path: PathBuf,
+    /// Branches to publish. They must fast-forward the published branches.
+    #[serde(default)]
+    pub branches: Vec<String>,
+    /// Tags to publish. Published tags with the same name are replaced.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A branch of a working copy to update from the monorepo.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pull {
+    /// Location of the working copy.
+    pub path: PathBuf,
+    /// The branch to fast-forward.
+    pub branch: String,
+    /// Peer whose version of `branch` to fast-forward to. Defaults to the local peer's.
+    pub peer_id: Option<PeerId>,
+}
+
+/// The outcome of a [`pull`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pulled {
+    /// Remote tracking refs of the working copy that were updated by the fetch.
+    pub updated: Vec<String>,
+    /// The branch that was fast-forwarded.
+    pub branch: String,
+    /// Commit `branch` points to after the pull.
+    pub head: Oid,
+}
+
+/// Remember `path` as a working copy of project `urn` that came to be as `origin`.
+///
+/// # Errors
+///
+/// Errors if we cannot read from or write to the store.
+pub fn register(
+    store: &kv::Store,
+    urn: &Urn,
+    path: PathBuf,
+    origin: Origin,
+) -> Result<(), error::Error> {
+    let mut registrations = registrations(store, urn)?;
+    if !registrations
+        .iter()
+        .any(|registration| registration.path == path)
+    {
+        registrations.push(Registration { path, origin });
+        set(store, urn, registrations)?;
+    }
+    Ok(())
+}
+
+/// Forget `path` as a working copy of project `urn`. The working copy itself is left untouched.
+///
+/// Returns `false` if `path` wasn't registered.
+///
+/// # Errors
+///
+/// Errors if we cannot read from or write to the store.
+pub fn unregister(store: &kv::Store, urn: &Urn, path: &Path) -> Result<bool, error::Error> {
+    let mut registrations = registrations(store, urn)?;
+    let before = registrations.len();
+    registrations.retain(|registration| registration.path != path);
+    if registrations.len() == before {
+        return Ok(false);
+    }
+    set(store, urn, registrations)?;
+    Ok(true)
+}
+
+/// Forget all working copies of project `urn` and return their registrations.
+///
+/// # Errors
+///
+/// Errors if we cannot read from or write to the store.
+pub fn unregister_all(store: &kv::Store, urn: &Urn) -> Result<Vec<Registration>, error::Error> {
+    let registrations = registrations(store, urn)?;
+    store
+        .bucket::<&str, kv::Json<Vec<Registration>>>(Some(BUCKET_NAME))?
+        .remove(urn.to_string().as_str())?;
+    Ok(registrations)
+}
+
+/// Paths of all registered working copies of project `urn`.
+///
+/// # Errors
+///
+/// Errors if we cannot read from the store.
+pub fn paths(store: &kv::Store, urn: &Urn) -> Result<Vec<PathBuf>, error::Error> {
+    Ok(registrations(store, urn)?
+        .into_iter()
+        .map(|registration| registration.path)
+        .collect())
+}
+
+/// All registered working copies of project `urn`.
+fn registrations(store: &kv::Store, urn: &Urn) -> Result<Vec<Registration>, error::Error> {
+    Ok(store
+        .bucket::<&str, kv::Json<Vec<Registration>>>(Some(BUCKET_NAME))?
+        .get(urn.to_string().as_str())?
+        .map(|json| json.0)
+        .unwrap_or_default())
+}
+
+/// `true` if `path` is still a git repository whose `rad` remote points at project `urn`.
+#[must_use]
+pub fn is_checkout_of(path: &Path, urn: &Urn) -> bool {
+    let url = LocalUrl::from(urn.clone()).to_string();
+    git2::Repository::open(path)
+        .and_then(|repo| {
+            let remote = repo.find_remote(radicle_daemon::config::RAD_REMOTE)?;
+            Ok(remote.url() == Some(url.as_str()))
+        })
+        .unwrap_or(false)
+}
+
+/// Read the state of all registered working copies of project `urn` from disk.
+///
+/// # Errors
+///
+///   * We couldn't read from the store.
+///   * The status of an existing working copy couldn't be determined.
+pub async fn list(store: &kv::Store, urn: &Urn) -> Result<Vec<WorkingCopy>, error::Error> {
+    let paths = paths(store, urn)?;
+    tokio::task::spawn_blocking(move || paths.into_iter().map(status).collect())
+        .await
+        .expect("task to read working copy status was aborted")
+}
+
+/// Push the branches and tags selected in `publish` from a registered working copy of project
+/// `urn` to the monorepo.
+///
+/// Returns the names of the refs that were pushed.
+///
+/// # Errors
+///
+///   * The working copy is not registered for the project.
+///   * Nothing was selected or a branch or tag doesn't exist in the working copy.
+///   * Pushing failed, e.g. because a branch doesn't fast-forward.
+pub async fn publish(
+    peer: &crate::peer::Peer,
+    store: &kv::Store,
+    urn: Urn,
+    publish: Publish,
+) -> Result<Vec<String>, error::Error> {
+    ensure_registered(store, &urn, &publish.path)?;
+
+    let branches = publish
+        .branches
+        .iter()
+        .map(|branch| format!("refs/heads/{}", branch));
+    let tags = publish.tags.iter().map(|tag| format!("refs/tags/{}", tag));
+    let refs = branches.chain(tags).collect::<Vec<_>>();
+
+    let settings = radicle_daemon::state::settings(peer.librad_peer());
+    tokio::task::spawn_blocking(move || {
+        let repo = git2::Repository::open(&publish.path)?;
+        let mut specs = vec![];
+        for name in refs {
+            if repo.find_reference(&name).is_err() {
+                return Err(error::Error::BranchNotFound(name));
+            }
+            let force = if name.starts_with("refs/tags/") {
+                "+"
+            } else {
+                ""
+            };
+            specs.push(
+                Pushspec::from_str(&format!("{}{}:{}", force, name, name))
+                    .map_err(|_| error::Error::BranchNotFound(name))?,
+            );
+        }
+        let specs = NonEmpty::from_vec(specs).ok_or(error::Error::NothingToPublish)?;
+
+        let mut rad = Remote::rad_remote(
+            LocalUrl::from(urn),
+            Refspec {
+                src: librad::refspec_pattern!("refs/heads/*"),
+                dst: librad::refspec_pattern!("refs/heads/*"),
+                force: Force::False,
+            },
+        );
+        let pushed = rad.push(settings, &repo, LocalPushspec::Specs(specs))?;
+        Ok(pushed.map(|name| name.to_string()).collect())
+    })
+    .await
+    .expect("task to publish working copy was aborted")
+}
+
+/// Fetch the branches of the local peer and all tracked peers of project `urn` into a registered
+/// working copy and fast-forward the branch selected in `pull`.
+///
+/// # Errors
+///
+///   * The working copy is not registered for the project.
+///   * `pull.peer_id` is not tracked or its handle doesn't make a valid remote name. Other tracked
+///     peers with such a handle are skipped.
+///   * Fetching failed or the branch to fast-forward to doesn't exist.
+///   * The branch has diverged and cannot be fast-forwarded.
+pub async fn pull(
+    peer: &crate::peer::Peer,
+    store: &kv::Store,
+    urn: Urn,
+    pull: Pull,
+) -> Result<Pulled, error::Error> {
+    ensure_registered(store, &urn, &pull.path)?;
+
+    let tracked = radicle_daemon::state::tracked(peer.librad_peer(), urn.clone())
+        .await?
+        .into_iter()
+        .filter_map(radicle_daemon::project::Peer::replicated_remote)
+        .collect::<Vec<_>>();
+    let upstream = match pull.peer_id {
+        None => radicle_daemon::config::RAD_REMOTE.to_string(),
+        Some(peer_id) => {
+            let (_, person) = tracked
+                .iter()
+                .find(|(tracked, _)| *tracked == peer_id)
+                .ok_or(error::Error::PeerNotTracked(peer_id))?;
+            super::checkout::remote_name(peer_id, person)?
+        },
+    };
+
+    let mut specs = vec![Fetchspec::from_str(&format!(
+        "+refs/heads/*:refs/remotes/{}/*",
+        radicle_daemon::config::RAD_REMOTE
+    ))
+    .expect("fetchspec of the rad remote is valid")];
+    for (peer_id, person) in &tracked {
+        // A peer whose handle doesn't make a valid remote name must not stop us from pulling the
+        // others.
+        let spec = super::checkout::remote_name(*peer_id, person).and_then(|name| {
+            Fetchspec::from_str(&format!(
+                "+refs/remotes/{}/heads/*:refs/remotes/{}/*",
+                peer_id, name
+            ))
+            .map_err(|_| error::Error::InvalidRemoteName(name))
+        });
+        match spec {
+            Ok(spec) => specs.push(spec),
+            Err(err) => {
+                tracing::warn!(project_urn = %urn, %peer_id, ?err, "skipping peer in pull");
+            },
+        }
+    }
+    let specs = NonEmpty::from_vec(specs).expect("there is always the fetchspec of the local peer");
+
+    let settings = radicle_daemon::state::settings(peer.librad_peer());
+    let path = pull.path.clone();
+    let updated = tokio::task::spawn_blocking(move || -> Result<Vec<String>, error::Error> {
+        let repo = git2::Repository::open(path)?;
+        let mut rad = Remote::rad_remote(
+            LocalUrl::from(urn),
+            Refspec {
+                src: librad::refspec_pattern!("refs/heads/*"),
+                dst: librad::refspec_pattern!("refs/remotes/rad/*"),
+                force: Force::True,
+            },
+        );
+        let fetched = rad.fetch(settings, &repo, LocalFetchspec::Specs(specs))?;
+        Ok(fetched.map(|(name, _)| name.to_string()).collect())
+    })
+    .await
+    .expect("task to pull working copy was aborted")?;
+
+    let head = fast_forward(&pull.path, &pull.branch, &upstream).await?;
+
+    Ok(Pulled {
+        updated,
+        branch: pull.branch,
+        head,
+    })
+}
+
+/// Fast-forward `branch` of the working copy at `path` to the branch of the same name on
+/// `upstream`. A branch that doesn't exist yet is created.
+async fn fast_forward(path: &Path, branch: &str, upstream: &str) -> Result<Oid, error::Error> {
+    let target_ref = format!("refs/remotes/{}/{}", upstream, branch);
+    let local_ref = format!("refs/heads/{}", branch);
+
+    let checked_out = {
+        let repo = git2::Repository::open(path)?;
+        let target = repo
+            .refname_to_id(&target_ref)
+            .map_err(|_| error::Error::BranchNotFound(format!("{}/{}", upstream, branch)))?;
+        if let Ok(local) = repo.refname_to_id(&local_ref) {
+            if local == target {
+                return Ok(Oid::from(target));
+            }
+            if !repo.graph_descendant_of(target, local)? {
+                return Err(error::Error::NotFastForward(branch.to_string()));
+            }
+        }
+        let head = repo.head().ok();
+        head.as_ref().and_then(git2::Reference::name) == Some(local_ref.as_str())
+    };
+
+    // `git` takes care of updating the index and the working tree and refuses to overwrite
+    // uncommitted changes.
+    let args = if checked_out {
+        vec![
+            "merge".to_string(),
+            "--ff-only".to_string(),
+            target_ref.clone(),
+        ]
+    } else {
+        vec![
+            "fetch".to_string(),
+            ".".to_string(),
+            format!("{}:{}", target_ref, local_ref),
+        ]
+    };
+    super::git(path, &args).await?;
+
+    let repo = git2::Repository::open(path)?;
+    Ok(Oid::from(repo.refname_to_id(&local_ref)?))
+}
+
+/// Reject with [`error::Error::WorkingCopyNotFound`] unless `path` is a registered working copy of
+/// project `urn`.
+fn ensure_registered(store: &kv::Store, urn: &Urn, path: &Path) -> Result<(), error::Error> {
+    if paths(store, urn)?
+        .iter()
+        .any(|registered| registered == path)
+    {
+        Ok(())
+    } else {
+        Err(error::Error::WorkingCopyNotFound(path.to_path_buf()))
+    }
+}
+
+/// Read the state of the working copy at `path`.
+fn status(path: PathBuf) -> Result<WorkingCopy, error::Error> {
+    let repo = match git2::Repository::open(&path) {
+        Ok(repo) => repo,
+        Err(err) if err.code() == git2::ErrorCode::NotFound => {
+            return Ok(WorkingCopy {
+                path,
+                exists: false,
+                branch: None,
+                dirty: false,
+                ahead: None,
+                behind: None,
+            })
+        },
+        Err(err) => return Err(err.into()),
+    };
+
+    let dirty = !repo
+        .statuses(Some(
+            git2::StatusOptions::new()
+                .include_untracked(true)
+                .include_ignored(false),
+        ))?
+        .is_empty();
+
+    let head = match repo.head() {
+        Ok(head) if head.is_branch() => Some(head),
+        Ok(_) => None,
+        // An unborn branch has no commits to compare yet.
+        Err(err) if err.code() == git2::ErrorCode::UnbornBranch => None,
+        Err(err) => return Err(err.into()),
+    };
+    let branch = head
+        .as_ref()
+        .and_then(|head| head.shorthand())
+        .map(ToString::to_string);
+
+    let (ahead, behind) = match (&head, &branch) {
+        (Some(head), Some(branch)) => {
+            let upstream = format!(
+                "refs/remotes/{}/{}",
+                radicle_daemon::config::RAD_REMOTE,
+                branch
+            );
+            match (head.target(), repo.refname_to_id(&upstream).ok()) {
+                (Some(local), Some(upstream)) => {
+                    let (ahead, behind) = repo.graph_ahead_behind(local, upstream)?;
+                    (Some(ahead), Some(behind))
+                },
+                _ => (None, None),
+            }
+        },
+        _ => (None, None),
+    };
+
+    Ok(WorkingCopy {
+        path,
+        exists: true,
+        branch,
+        dirty,
+        ahead,
+        behind,
+    })
+}
+
+fn set(store: &kv::Store, urn: &Urn, registrations: Vec<Registration>) -> Result<(), error::Error> {
+    Ok(store
+        .bucket::<&str, kv::Json<Vec<Registration>>>(Some(BUCKET_NAME))?
+        .set(urn.to_string().as_str(), kv::Json(registrations))?)
+}
@@ -9,6 +9,9 @@
 
 use serde::{Deserialize, Serialize};
 
+use link_crypto::PeerId;
+use link_identities::git::Urn;
+
 use crate::{error, identity};
 
 pub mod settings;
@@ -97,6 +100,72 @@ pub fn update_seeds(store: &kv::Store, seeds: Vec<String>) -> Result<(), error::
     Ok(())
 }
 
+/// Get the blocked peers (see [`settings::Settings::blocklist`]) from the current session.
+///
+/// If there is no session yet, nobody is blocked.
+///
+/// # Errors
+///
+/// Errors if we cannot read data from the store.
+pub fn blocklist(store: &kv::Store) -> Result<Vec<PeerId>, error::Error> {
+    Ok(get_current(store)?
+        .map(|session| session.settings.blocklist)
+        .unwrap_or_default())
+}
+
+/// Replace the blocked peers of the current session.
+/// Does nothing if there is no session yet.
+///
+/// # Errors
+///
+/// * Errors when we cannot write to the store.
+pub fn update_blocklist(store: &kv::Store, blocklist: Vec<PeerId>) -> Result<(), error::Error> {
+    if let Some(mut session) = get_current(store)? {
+        session.settings.blocklist = blocklist;
+        set_current(store, session)?;
+    }
+    Ok(())
+}
+
+/// Get the [`settings::TrackingPolicy`] of project `urn` from the current session.
+///
+/// If there is no session yet or no policy for the project, returns
+/// [`settings::TrackingPolicy::Manual`].
+///
+/// # Errors
+///
+/// Errors if we cannot read data from the store.
+pub fn tracking_policy(
+    store: &kv::Store,
+    urn: &Urn,
+) -> Result<settings::TrackingPolicy, error::Error> {
+    Ok(get_current(store)?
+        .and_then(|session| session.settings.tracking.get(&urn.to_string()).copied())
+        .unwrap_or_default())
+}
+
+/// Set the [`settings::TrackingPolicy`] of project `urn` in the current session.
+/// Does nothing if there is no session yet.
+///
+/// # Errors
+///
+/// * Errors when we cannot write to the store.
+pub fn update_tracking_policy(
+    store: &kv::Store,
+    urn: &Urn,
+    policy: settings::TrackingPolicy,
+) -> Result<(), error::Error> {
+    if let Some(mut session) = get_current(store)? {
+        if policy == settings::TrackingPolicy::Manual {
+            session.settings.tracking.remove(&urn.to_string());
+        } else {
+            session.settings.tracking.insert(urn.to_string(), policy);
+        }
+        set_current(store, session)?;
+    }
+    Ok(())
+}
+
 /// Initialize a session for tests.
 ///
 /// Creates an owner identity for the session using `owner_handle` and stores the current session.
@@ -5,14 +5,25 @@
 // LICENSE file.
 
 //! User controlled parameters for application appearance, behaviour and state.
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
+use link_crypto::PeerId;
+
 /// User controlled parameters for application appearance, behaviour and state.
 #[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Settings {
     /// User-determined p2p parameters.
     pub coco: CoCo,
+    /// Tracking policies by project URN. Projects without an entry use
+    /// [`TrackingPolicy::Manual`].
+    #[serde(default)]
+    pub tracking: BTreeMap<String, TrackingPolicy>,
+    /// Peers whose data is ignored, see [`crate::blocklist`].
+    #[serde(default)]
+    pub blocklist: Vec<PeerId>,
 }
 
 /// `CoCo` config parameters subject to user preferences
@@ -29,3 +40,21 @@ impl Default for CoCo {
         Self { seeds: vec![] }
     }
 }
+
+/// Which peers of a project are tracked without the user asking for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TrackingPolicy {
+    /// Only peers the user tracks explicitly.
+    Manual,
+    /// The delegates of the project, nobody else.
+    Delegates,
+    /// Every peer that announces the project.
+    Everyone,
+}
+
+impl Default for TrackingPolicy {
+    fn default() -> Self {
+        Self::Manual
+    }
+}
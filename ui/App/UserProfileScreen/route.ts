@@ -29,6 +29,6 @@ export async function load(params: Params): Promise<LoadedRoute> {
     type: "userProfile",
     ownUserUrn: session.identity.urn,
     user,
-    projects,
+    projects: [...projects.contributed, ...projects.tracked],
   };
 }
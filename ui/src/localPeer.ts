@@ -74,6 +74,7 @@ const statusSchema = zod.union([
 ]);
 
 enum EventType {
+  CheckoutProgress = "checkoutProgress",
   ProjectUpdated = "projectUpdated",
   RequestCreated = "requestCreated",
   RequestQueried = "requestQueried",
@@ -83,6 +84,20 @@ enum EventType {
   WaitingRoomTransition = "waitingRoomTransition",
 }
 
+type CheckoutStage =
+  | { type: "cloning" }
+  | { type: "fetchingRemote"; peerId: identity.PeerId }
+  | { type: "checkingOut"; branch: string }
+  | { type: "done"; path: string }
+  | { type: "failed"; message: string };
+
+export interface CheckoutProgress {
+  type: EventType.CheckoutProgress;
+  urn: string;
+  path: string;
+  progress: CheckoutStage;
+}
+
 interface ProjectUpdated {
   type: EventType.ProjectUpdated;
   provider: identity.PeerId;
@@ -125,12 +140,27 @@ type RequestEvent =
   | RequestTimedOut;
 
 export type Event =
+  | CheckoutProgress
   | ProjectUpdated
   | RequestEvent
   | WaitingRoomTransition
   | { type: EventType.StatusChanged; old: Status; new: Status };
 
+const checkoutStageSchema: zod.Schema<CheckoutStage> = zod.union([
+  zod.object({ type: zod.literal("cloning") }),
+  zod.object({ type: zod.literal("fetchingRemote"), peerId: zod.string() }),
+  zod.object({ type: zod.literal("checkingOut"), branch: zod.string() }),
+  zod.object({ type: zod.literal("done"), path: zod.string() }),
+  zod.object({ type: zod.literal("failed"), message: zod.string() }),
+]);
+
 const eventSchema: zod.Schema<Event> = zod.union([
+  zod.object({
+    type: zod.literal(EventType.CheckoutProgress),
+    urn: zod.string(),
+    path: zod.string(),
+    progress: checkoutStageSchema,
+  }),
   zod.object({
     type: zod.literal(EventType.ProjectUpdated),
     provider: zod.string(),